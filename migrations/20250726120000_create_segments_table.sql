@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE segments (
+    segment_id uuid PRIMARY KEY,
+    name text NOT NULL UNIQUE,
+    definition jsonb NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+ALTER TABLE newsletter_issues
+ADD COLUMN segment_id uuid NULL REFERENCES segments (segment_id);
@@ -0,0 +1,11 @@
+-- Add migration script here
+ALTER TABLE subscriptions
+ADD COLUMN attributes jsonb NOT NULL DEFAULT '{}'::jsonb;
+
+CREATE TABLE subscriber_tags (
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+    tag text NOT NULL,
+    PRIMARY KEY (subscriber_id, tag)
+);
+
+CREATE INDEX subscriber_tags_tag_idx ON subscriber_tags (tag);
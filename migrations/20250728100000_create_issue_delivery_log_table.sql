@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE issue_delivery_log (
+    id bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (
+        newsletter_issue_id
+    ),
+    subscriber_email text NOT NULL,
+    outcome text NOT NULL,
+    detail text NULL,
+    recorded_at timestamptz NOT NULL
+);
+
+CREATE INDEX issue_delivery_log_subscriber_email_idx
+ON issue_delivery_log (subscriber_email);
+
+ALTER TABLE subscription_tokens
+ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
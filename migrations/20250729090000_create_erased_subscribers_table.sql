@@ -0,0 +1,5 @@
+-- Add migration script here
+CREATE TABLE erased_subscribers (
+    email_hash text PRIMARY KEY,
+    erased_at timestamptz NOT NULL
+);
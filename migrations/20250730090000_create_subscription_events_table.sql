@@ -0,0 +1,15 @@
+-- Add migration script here
+CREATE TABLE subscription_events (
+    id bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    subscriber_email text NOT NULL,
+    event_type text NOT NULL,
+    source text NULL,
+    source_ip text NULL,
+    user_agent text NULL,
+    admin_user_id uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+    detail text NULL,
+    occurred_at timestamptz NOT NULL
+);
+
+CREATE INDEX subscription_events_subscriber_email_idx
+ON subscription_events (subscriber_email);
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Bounces and complaints reported by the email provider cannot always be
+-- traced back to the issue that caused them.
+ALTER TABLE issue_delivery_log ALTER COLUMN newsletter_issue_id DROP NOT NULL;
@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE suppressions (
+    email_hash text PRIMARY KEY,
+    -- Kept for bounces, complaints and manual entries so admins can tell them
+    -- apart, erased addresses only leave their hash behind.
+    email text NULL,
+    reason text NOT NULL,
+    detail text NULL,
+    created_at timestamptz NOT NULL
+);
+
+INSERT INTO suppressions (email_hash, reason, created_at)
+SELECT email_hash, 'erased', erased_at
+FROM erased_subscribers;
+
+DROP TABLE erased_subscribers;
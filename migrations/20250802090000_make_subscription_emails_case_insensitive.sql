@@ -0,0 +1,79 @@
+-- Add migration script here
+-- Addresses differing only by case are the same mailbox. Merge the duplicate
+-- subscriptions into one, preferring a confirmed one, then the oldest.
+CREATE TEMPORARY TABLE duplicate_subscriptions ON COMMIT DROP AS
+SELECT s.id, s.email, k.id AS keeper_id, k.email AS keeper_email
+FROM subscriptions s
+JOIN LATERAL (
+    SELECT id, email
+    FROM subscriptions
+    WHERE lower(email) = lower(s.email)
+    ORDER BY status = 'confirmed' DESC, subscribed_at, id
+    LIMIT 1
+) k ON TRUE
+WHERE k.id <> s.id;
+
+UPDATE subscriptions k
+SET attributes = merged.attributes || k.attributes
+FROM (
+    SELECT d.keeper_id, jsonb_object_agg(a.key, a.value) AS attributes
+    FROM duplicate_subscriptions d
+    JOIN subscriptions s ON s.id = d.id
+    CROSS JOIN LATERAL jsonb_each(s.attributes) a
+    GROUP BY d.keeper_id
+) merged
+WHERE k.id = merged.keeper_id;
+
+INSERT INTO subscriber_tags (subscriber_id, tag)
+SELECT d.keeper_id, t.tag
+FROM duplicate_subscriptions d
+JOIN subscriber_tags t ON t.subscriber_id = d.id
+ON CONFLICT DO NOTHING;
+
+UPDATE subscription_tokens t
+SET subscriber_id = d.keeper_id
+FROM duplicate_subscriptions d
+WHERE t.subscriber_id = d.id;
+
+DELETE FROM issue_delivery_queue q
+USING duplicate_subscriptions d
+WHERE q.subscriber_email = d.email;
+
+UPDATE issue_delivery_log l
+SET subscriber_email = d.keeper_email
+FROM duplicate_subscriptions d
+WHERE l.subscriber_email = d.email;
+
+UPDATE subscription_events e
+SET subscriber_email = d.keeper_email
+FROM duplicate_subscriptions d
+WHERE e.subscriber_email = d.email;
+
+DELETE FROM subscriptions s
+USING duplicate_subscriptions d
+WHERE s.id = d.id;
+
+-- Domains are now stored lowercased, as `SubscriberEmail` does.
+UPDATE subscriptions
+SET email = left(email, length(email) - strpos(reverse(email), '@'))
+    || lower(right(email, strpos(reverse(email), '@')))
+WHERE email <> left(email, length(email) - strpos(reverse(email), '@'))
+    || lower(right(email, strpos(reverse(email), '@')));
+
+UPDATE issue_delivery_queue q
+SET subscriber_email = s.email
+FROM subscriptions s
+WHERE lower(q.subscriber_email) = lower(s.email) AND q.subscriber_email <> s.email;
+
+UPDATE issue_delivery_log l
+SET subscriber_email = s.email
+FROM subscriptions s
+WHERE lower(l.subscriber_email) = lower(s.email) AND l.subscriber_email <> s.email;
+
+UPDATE subscription_events e
+SET subscriber_email = s.email
+FROM subscriptions s
+WHERE lower(e.subscriber_email) = lower(s.email) AND e.subscriber_email <> s.email;
+
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_email_key;
+CREATE UNIQUE INDEX subscriptions_email_lower_key ON subscriptions (lower(email));
@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN email text NULL;
+
+CREATE TABLE login_failures (
+    -- 'username' or 'ip'
+    scope text NOT NULL,
+    key text NOT NULL,
+    failed_attempts integer NOT NULL,
+    last_failed_at timestamptz NOT NULL,
+    locked_until timestamptz NULL,
+    PRIMARY KEY (scope, key)
+);
@@ -0,0 +1,14 @@
+-- Add migration script here
+ALTER TABLE users
+    -- Base32 TOTP secret, set once the user confirmed their authenticator app.
+    ADD COLUMN totp_secret text NULL,
+    -- The last time step a code was accepted for, codes cannot be replayed.
+    ADD COLUMN totp_last_step bigint NULL;
+
+CREATE TABLE recovery_codes (
+    id uuid PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    code_hash text NOT NULL,
+    used_at timestamptz NULL
+);
+CREATE INDEX recovery_codes_user_id_idx ON recovery_codes (user_id);
@@ -0,0 +1,8 @@
+-- Add migration script here
+ALTER TABLE users
+    ADD COLUMN role text NOT NULL DEFAULT 'owner'
+        CHECK (role IN ('owner', 'publisher', 'editor', 'viewer')),
+    ADD COLUMN disabled_at timestamptz NULL;
+
+-- Existing accounts keep full control, new ones are given a role explicitly.
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
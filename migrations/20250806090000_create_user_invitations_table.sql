@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE user_invitations (
+    invitation_id uuid PRIMARY KEY,
+    email text NOT NULL,
+    role text NOT NULL CHECK (role IN ('owner', 'publisher', 'editor', 'viewer')),
+    invited_by uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    -- Set once, when the invitee created their account or an owner revoked it.
+    accepted_at timestamptz NULL,
+    revoked_at timestamptz NULL
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE password_resets (
+    -- SHA-256 of the token sent by email, the token itself is never stored.
+    token_hash text PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    used_at timestamptz NULL
+);
+CREATE INDEX password_resets_user_id_idx ON password_resets (user_id);
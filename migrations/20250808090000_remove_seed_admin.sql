@@ -0,0 +1,12 @@
+-- Add migration script here
+-- The seeded `admin` shipped with a well known password. It is removed unless
+-- that password was changed, in which case it is someone's real account.
+DELETE FROM idempotency
+WHERE user_id IN (
+    SELECT user_id FROM users
+    WHERE user_id = 'e72e3140-0519-4d5e-b691-9aa778b1a6d9'
+        AND password_hash = '$argon2id$v=19$m=15000,t=2,p=1$wG0tsH/jej8UwL2OhnN0UA$/mIlTssOQ184sBTg+OcHPXHtAHQqAdHwImoRjNAk5Po'
+);
+DELETE FROM users
+WHERE user_id = 'e72e3140-0519-4d5e-b691-9aa778b1a6d9'
+    AND password_hash = '$argon2id$v=19$m=15000,t=2,p=1$wG0tsH/jej8UwL2OhnN0UA$/mIlTssOQ184sBTg+OcHPXHtAHQqAdHwImoRjNAk5Po';
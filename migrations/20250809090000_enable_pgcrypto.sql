@@ -0,0 +1,3 @@
+-- Add migration script here
+-- The suppression list is keyed with HMAC-SHA256, which queries compute too.
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- Addresses are looked up with lower(subscriber_email) = lower($1), which
+-- the plain indexes cannot serve.
+CREATE INDEX issue_delivery_queue_subscriber_email_lower_idx
+ON issue_delivery_queue (lower(subscriber_email));
+
+CREATE INDEX issue_delivery_log_subscriber_email_lower_idx
+ON issue_delivery_log (lower(subscriber_email));
+
+CREATE INDEX subscription_events_subscriber_email_lower_idx
+ON subscription_events (lower(subscriber_email));
@@ -0,0 +1,196 @@
+use std::{sync::Arc, time::Duration};
+
+use futures_util::future::BoxFuture;
+use htmlescape::encode_attribute;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+/// A human verification widget shown in the subscription form, such as
+/// hCaptcha or Turnstile, and the server side check of its response.
+pub trait ChallengeVerifier: Send + Sync {
+    /// The form field the widget submits its response in.
+    fn response_field(&self) -> &'static str;
+
+    /// The HTML rendering the widget inside the form.
+    fn widget(&self) -> String;
+
+    fn verify<'a>(
+        &'a self,
+        response: &'a str,
+        remote_ip: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<bool, anyhow::Error>>;
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum ChallengeSettings {
+    None,
+    /// Accepts a fixed response, for local development and tests.
+    Fake {
+        expected_response: String,
+    },
+    Turnstile {
+        site_key: String,
+        secret_key: SecretString,
+        #[serde(default = "turnstile_verify_url")]
+        verify_url: String,
+    },
+    Hcaptcha {
+        site_key: String,
+        secret_key: SecretString,
+        #[serde(default = "hcaptcha_verify_url")]
+        verify_url: String,
+    },
+}
+
+fn turnstile_verify_url() -> String {
+    "https://challenges.cloudflare.com/turnstile/v0/siteverify".into()
+}
+
+fn hcaptcha_verify_url() -> String {
+    "https://api.hcaptcha.com/siteverify".into()
+}
+
+impl ChallengeSettings {
+    pub fn verifier(&self, timeout: Duration) -> Arc<dyn ChallengeVerifier> {
+        match self.clone() {
+            ChallengeSettings::None => Arc::new(NoChallenge),
+            ChallengeSettings::Fake { expected_response } => {
+                Arc::new(FakeChallenge { expected_response })
+            }
+            ChallengeSettings::Turnstile {
+                site_key,
+                secret_key,
+                verify_url,
+            } => Arc::new(SiteVerifyChallenge {
+                script_url: "https://challenges.cloudflare.com/turnstile/v0/api.js",
+                widget_class: "cf-turnstile",
+                response_field: "cf-turnstile-response",
+                site_key,
+                secret_key,
+                verify_url,
+                http_client: reqwest::Client::new(),
+                timeout,
+            }),
+            ChallengeSettings::Hcaptcha {
+                site_key,
+                secret_key,
+                verify_url,
+            } => Arc::new(SiteVerifyChallenge {
+                script_url: "https://js.hcaptcha.com/1/api.js",
+                widget_class: "h-captcha",
+                response_field: "h-captcha-response",
+                site_key,
+                secret_key,
+                verify_url,
+                http_client: reqwest::Client::new(),
+                timeout,
+            }),
+        }
+    }
+}
+
+/// No challenge at all, the honeypot and form token still apply.
+pub struct NoChallenge;
+
+impl ChallengeVerifier for NoChallenge {
+    fn response_field(&self) -> &'static str {
+        "challenge_response"
+    }
+
+    fn widget(&self) -> String {
+        String::new()
+    }
+
+    fn verify<'a>(
+        &'a self,
+        _response: &'a str,
+        _remote_ip: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<bool, anyhow::Error>> {
+        Box::pin(async { Ok(true) })
+    }
+}
+
+pub struct FakeChallenge {
+    pub expected_response: String,
+}
+
+impl ChallengeVerifier for FakeChallenge {
+    fn response_field(&self) -> &'static str {
+        "challenge_response"
+    }
+
+    fn widget(&self) -> String {
+        r#"<input type="text" placeholder="Challenge" name="challenge_response" />"#.into()
+    }
+
+    fn verify<'a>(
+        &'a self,
+        response: &'a str,
+        _remote_ip: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<bool, anyhow::Error>> {
+        Box::pin(async move { Ok(response == self.expected_response) })
+    }
+}
+
+/// hCaptcha and Turnstile share the same `siteverify` protocol.
+pub struct SiteVerifyChallenge {
+    script_url: &'static str,
+    widget_class: &'static str,
+    response_field: &'static str,
+    site_key: String,
+    secret_key: SecretString,
+    verify_url: String,
+    http_client: reqwest::Client,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+}
+
+impl ChallengeVerifier for SiteVerifyChallenge {
+    fn response_field(&self) -> &'static str {
+        self.response_field
+    }
+
+    fn widget(&self) -> String {
+        format!(
+            r#"<script src="{}" async defer></script><div class="{}" data-sitekey="{}"></div>"#,
+            self.script_url,
+            self.widget_class,
+            encode_attribute(&self.site_key),
+        )
+    }
+
+    fn verify<'a>(
+        &'a self,
+        response: &'a str,
+        remote_ip: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<bool, anyhow::Error>> {
+        Box::pin(async move {
+            if response.is_empty() {
+                return Ok(false);
+            }
+            let mut form = vec![
+                ("secret", self.secret_key.expose_secret()),
+                ("response", response),
+            ];
+            if let Some(remote_ip) = remote_ip {
+                form.push(("remoteip", remote_ip));
+            }
+            let outcome: SiteVerifyResponse = self
+                .http_client
+                .post(&self.verify_url)
+                .form(&form)
+                .timeout(self.timeout)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok(outcome.success)
+        })
+    }
+}
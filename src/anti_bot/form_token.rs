@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use rand::distr::{Alphanumeric, SampleString};
+use secrecy::SecretString;
+
+use crate::signing;
+
+/// A signed timestamp and nonce embedded in the subscription form when it is
+/// served, proving the form came from us and telling how long it was on
+/// screen. The nonce lets each form be submitted only once.
+#[derive(Debug)]
+pub struct FormToken(String);
+
+/// What a token issued by us tells about the form it was served with.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ServedForm<'a> {
+    pub age: Duration,
+    pub nonce: &'a str,
+}
+
+impl FormToken {
+    pub fn issue(secret: &SecretString) -> FormToken {
+        let issued_at = Utc::now().timestamp_millis();
+        let nonce = Alphanumeric.sample_string(&mut rand::rng(), 20);
+        let signature = signing::sign(secret, &Self::message(issued_at, &nonce));
+        FormToken(format!("{issued_at}.{nonce}.{signature}"))
+    }
+
+    /// Read a token, `None` if it was not issued by us.
+    pub fn verify<'a>(secret: &SecretString, token: &'a str) -> Option<ServedForm<'a>> {
+        let (issued_at, rest) = token.split_once('.')?;
+        let (nonce, signature) = rest.split_once('.')?;
+        let issued_at: i64 = issued_at.parse().ok()?;
+        if !signing::verify(secret, &Self::message(issued_at, nonce), signature) {
+            return None;
+        }
+        let elapsed = Utc::now().timestamp_millis() - issued_at;
+        // A token from the future is as suspicious as a forged one.
+        let age = u64::try_from(elapsed).ok().map(Duration::from_millis)?;
+        Some(ServedForm { age, nonce })
+    }
+
+    fn message(issued_at: i64, nonce: &str) -> String {
+        format!("subscription_form\n{issued_at}\n{nonce}")
+    }
+}
+
+impl AsRef<str> for FormToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+    use secrecy::SecretString;
+
+    use super::FormToken;
+
+    #[test]
+    fn issued_tokens_are_verified_with_their_own_nonce() {
+        let secret = SecretString::from("secret");
+        let first = FormToken::issue(&secret);
+        let second = FormToken::issue(&secret);
+        let first = FormToken::verify(&secret, first.as_ref()).unwrap();
+        let second = FormToken::verify(&secret, second.as_ref()).unwrap();
+        assert_that!(first.nonce).is_not_equal_to(second.nonce);
+    }
+
+    #[test]
+    fn a_tampered_token_is_rejected() {
+        let secret = SecretString::from("secret");
+        let token = FormToken::issue(&secret);
+        let (_, rest) = token.as_ref().split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        assert_that!(FormToken::verify(&secret, &format!("0.{rest}"))).is_none();
+        assert_that!(FormToken::verify(&secret, &format!("0.other.{signature}"))).is_none();
+        assert_that!(FormToken::verify(
+            &SecretString::from("other"),
+            token.as_ref()
+        ))
+        .is_none();
+        assert_that!(FormToken::verify(&secret, "garbage")).is_none();
+    }
+}
@@ -0,0 +1,192 @@
+mod challenge;
+mod form_token;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use fred::{
+    prelude::{KeysInterface, Pool},
+    types::{Expiration, SetOptions},
+};
+use secrecy::SecretString;
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+pub use challenge::{
+    ChallengeSettings, ChallengeVerifier, FakeChallenge, NoChallenge, SiteVerifyChallenge,
+};
+pub use form_token::{FormToken, ServedForm};
+
+/// Name of the field real users never see nor fill in.
+pub const HONEYPOT_FIELD: &str = "website";
+
+#[derive(Deserialize, Clone)]
+pub struct AntiBotSettings {
+    /// Forms submitted sooner than this after being served are rejected.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_submit_seconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_form_age_seconds: u64,
+    pub challenge: ChallengeSettings,
+    /// How long to wait for the challenge provider to answer.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub challenge_timeout_milliseconds: u64,
+}
+
+impl AntiBotSettings {
+    pub fn challenge_timeout(&self) -> Duration {
+        Duration::from_millis(self.challenge_timeout_milliseconds)
+    }
+}
+
+/// The checks a public form submission goes through before we send any email.
+pub struct AntiBot {
+    secret: SecretString,
+    min_submit: Duration,
+    max_form_age: Duration,
+    verifier: Arc<dyn ChallengeVerifier>,
+    /// Where the nonces of submitted forms are remembered, in memory while
+    /// Redis cannot be reached.
+    redis: Option<Pool>,
+    used_nonces: Mutex<HashMap<String, i64>>,
+}
+
+/// Why a submission was refused.
+#[derive(Debug, thiserror::Error)]
+pub enum BotCheckError {
+    #[error("The form has expired, please reload the page and try again")]
+    InvalidToken,
+    #[error("The form was submitted too quickly, please try again")]
+    TooFast,
+    #[error("The challenge was not passed, please try again")]
+    ChallengeFailed,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl AntiBot {
+    pub fn new(settings: &AntiBotSettings, secret: SecretString, redis: Option<Pool>) -> Self {
+        AntiBot {
+            secret,
+            min_submit: Duration::from_secs(settings.min_submit_seconds),
+            max_form_age: Duration::from_secs(settings.max_form_age_seconds),
+            verifier: settings.challenge.verifier(settings.challenge_timeout()),
+            redis,
+            used_nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn issue_token(&self) -> FormToken {
+        FormToken::issue(&self.secret)
+    }
+
+    pub fn challenge_widget(&self) -> String {
+        self.verifier.widget()
+    }
+
+    pub fn challenge_field(&self) -> &'static str {
+        self.verifier.response_field()
+    }
+
+    /// Check the signed form token and the challenge response of a submission.
+    /// A form that passes is used up, submitting it again is refused.
+    pub async fn check(
+        &self,
+        form_token: &str,
+        challenge_response: &str,
+        remote_ip: Option<&str>,
+    ) -> Result<(), BotCheckError> {
+        let form =
+            FormToken::verify(&self.secret, form_token).ok_or(BotCheckError::InvalidToken)?;
+        if form.age > self.max_form_age {
+            return Err(BotCheckError::InvalidToken);
+        }
+        if form.age < self.min_submit {
+            return Err(BotCheckError::TooFast);
+        }
+        if !self.verifier.verify(challenge_response, remote_ip).await? {
+            return Err(BotCheckError::ChallengeFailed);
+        }
+        if !self.use_nonce(form.nonce).await {
+            return Err(BotCheckError::InvalidToken);
+        }
+        Ok(())
+    }
+
+    /// Remember a nonce until its form expires, `false` if it was used already.
+    async fn use_nonce(&self, nonce: &str) -> bool {
+        let key = format!("form_token:{nonce}");
+        if let Some(redis) = &self.redis {
+            let outcome: Result<Option<String>, _> = redis
+                .set(
+                    key.as_str(),
+                    1,
+                    Some(Expiration::EX(self.max_form_age.as_secs() as i64)),
+                    Some(SetOptions::NX),
+                    false,
+                )
+                .await;
+            match outcome {
+                Ok(stored) => return stored.is_some(),
+                Err(e) => {
+                    tracing::warn!(error.message = %e, "Remembering form nonces in memory, Redis failed");
+                }
+            }
+        }
+        let now = Utc::now().timestamp_millis();
+        let mut used_nonces = self.used_nonces.lock().unwrap_or_else(|e| e.into_inner());
+        used_nonces.retain(|_, expires_at| *expires_at > now);
+        let expires_at = now + self.max_form_age.as_millis() as i64;
+        used_nonces.insert(key, expires_at).is_none()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+    use secrecy::SecretString;
+
+    use super::{AntiBot, AntiBotSettings, BotCheckError, ChallengeSettings};
+
+    fn anti_bot(min_submit_seconds: u64) -> AntiBot {
+        let settings = AntiBotSettings {
+            min_submit_seconds,
+            max_form_age_seconds: 60,
+            challenge: ChallengeSettings::Fake {
+                expected_response: "human".into(),
+            },
+            challenge_timeout_milliseconds: 1000,
+        };
+        AntiBot::new(&settings, SecretString::from("secret"), None)
+    }
+
+    #[tokio::test]
+    async fn instant_submissions_are_rejected() {
+        let anti_bot = anti_bot(3);
+        let token = anti_bot.issue_token();
+        let outcome = anti_bot.check(token.as_ref(), "human", None).await;
+        assert_that!(matches!(outcome, Err(BotCheckError::TooFast))).is_true();
+    }
+
+    #[tokio::test]
+    async fn a_failed_challenge_is_rejected() {
+        let anti_bot = anti_bot(0);
+        let token = anti_bot.issue_token();
+        let outcome = anti_bot.check(token.as_ref(), "robot", None).await;
+        assert_that!(matches!(outcome, Err(BotCheckError::ChallengeFailed))).is_true();
+        assert_that!(anti_bot.check(token.as_ref(), "human", None).await).is_ok();
+    }
+
+    #[tokio::test]
+    async fn a_form_can_only_be_submitted_once() {
+        let anti_bot = anti_bot(0);
+        let token = anti_bot.issue_token();
+        assert_that!(anti_bot.check(token.as_ref(), "human", None).await).is_ok();
+        let outcome = anti_bot.check(token.as_ref(), "human", None).await;
+        assert_that!(matches!(outcome, Err(BotCheckError::InvalidToken))).is_true();
+    }
+}
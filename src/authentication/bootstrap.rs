@@ -0,0 +1,70 @@
+use anyhow::Context;
+use rand::distr::{Alphanumeric, SampleString};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{NewAdminUser, PasswordHashing, UserError, users::insert_user};
+
+/// Proves its holder can read the server logs, where it is printed when
+/// there is no user yet. It lets them create the first owner.
+pub struct SetupToken {
+    token_hash: String,
+}
+
+impl SetupToken {
+    pub fn generate() -> (Self, SecretString) {
+        let token = Alphanumeric.sample_string(&mut rand::rng(), 40);
+        let setup_token = SetupToken {
+            token_hash: Self::hash(&token),
+        };
+        (setup_token, SecretString::from(token))
+    }
+
+    fn hash(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    pub fn matches(&self, token: &SecretString) -> bool {
+        Self::hash(token.expose_secret()) == self.token_hash
+    }
+}
+
+#[tracing::instrument(name = "Check for users", skip(pool))]
+pub async fn has_users(pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(r#"SELECT EXISTS(SELECT 1 FROM users) AS "exists!""#)
+        .fetch_one(pool)
+        .await
+        .context("Failed to count the users")?;
+    Ok(row.exists)
+}
+
+/// Create `user` as an owner, as long as there is no user yet. Returns `None`
+/// once the first owner exists.
+#[tracing::instrument(name = "Create first owner", skip(pool, user, hashing), fields(username = %user.username))]
+pub async fn create_first_owner(
+    pool: &PgPool,
+    user: NewAdminUser,
+    hashing: &PasswordHashing,
+) -> Result<Option<Uuid>, UserError> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    // Two setup requests racing each other cannot both see an empty table.
+    sqlx::query!("LOCK TABLE users IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *txn)
+        .await
+        .context("Failed to lock the users")?;
+    let row = sqlx::query!(r#"SELECT EXISTS(SELECT 1 FROM users) AS "exists!""#)
+        .fetch_one(&mut *txn)
+        .await
+        .context("Failed to count the users")?;
+    if row.exists {
+        return Ok(None);
+    }
+    let user_id = insert_user(&mut txn, user, hashing).await?;
+    txn.commit().await.context("Failed to commit")?;
+    Ok(Some(user_id))
+}
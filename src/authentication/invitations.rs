@@ -0,0 +1,167 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{NewAdminUser, PasswordHashing, Role, UserError, users::insert_user};
+use crate::domain::SubscriberEmail;
+
+/// How long an invitation can be accepted after it was sent.
+pub const INVITATION_VALIDITY: Duration = Duration::hours(72);
+
+#[derive(Debug)]
+pub struct Invitation {
+    pub invitation_id: Uuid,
+    pub email: String,
+    pub role: Role,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum InvitationError {
+    #[error("This invitation was already used, revoked or has expired")]
+    NoLongerValid,
+    #[error("The username {0} is already taken")]
+    UsernameTaken(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+#[tracing::instrument(name = "Create invitation", skip(pool))]
+pub async fn create_invitation(
+    pool: &PgPool,
+    email: &SubscriberEmail,
+    role: Role,
+    invited_by: Uuid,
+) -> Result<Invitation, anyhow::Error> {
+    let invitation = Invitation {
+        invitation_id: Uuid::new_v4(),
+        email: email.as_ref().to_string(),
+        role,
+        expires_at: Utc::now() + INVITATION_VALIDITY,
+    };
+    sqlx::query!(
+        r#"
+INSERT INTO user_invitations (invitation_id, email, role, invited_by, created_at, expires_at)
+VALUES ($1, $2, $3, $4, now(), $5)
+        "#,
+        invitation.invitation_id,
+        invitation.email,
+        invitation.role.as_str(),
+        invited_by,
+        invitation.expires_at,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the invitation")?;
+    Ok(invitation)
+}
+
+/// Invitations that were neither accepted, revoked nor left to expire.
+#[tracing::instrument(name = "List pending invitations", skip(pool))]
+pub async fn list_pending_invitations(pool: &PgPool) -> Result<Vec<Invitation>, anyhow::Error> {
+    sqlx::query!(
+        r#"
+SELECT invitation_id, email, role, expires_at
+FROM user_invitations
+WHERE accepted_at IS NULL AND revoked_at IS NULL AND expires_at > now()
+ORDER BY created_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to list the invitations")?
+    .into_iter()
+    .map(|r| {
+        Ok(Invitation {
+            invitation_id: r.invitation_id,
+            email: r.email,
+            role: Role::parse(&r.role).map_err(|e| anyhow::anyhow!(e))?,
+            expires_at: r.expires_at,
+        })
+    })
+    .collect()
+}
+
+#[tracing::instrument(name = "Get pending invitation", skip(pool))]
+pub async fn get_pending_invitation(
+    pool: &PgPool,
+    invitation_id: Uuid,
+) -> Result<Option<Invitation>, anyhow::Error> {
+    sqlx::query!(
+        r#"
+SELECT email, role, expires_at
+FROM user_invitations
+WHERE invitation_id = $1
+    AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > now()
+        "#,
+        invitation_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the invitation")?
+    .map(|r| {
+        Ok(Invitation {
+            invitation_id,
+            email: r.email,
+            role: Role::parse(&r.role).map_err(|e| anyhow::anyhow!(e))?,
+            expires_at: r.expires_at,
+        })
+    })
+    .transpose()
+}
+
+/// Returns `false` when there was no pending invitation to revoke.
+#[tracing::instrument(name = "Revoke invitation", skip(pool))]
+pub async fn revoke_invitation(pool: &PgPool, invitation_id: Uuid) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+UPDATE user_invitations SET revoked_at = now()
+WHERE invitation_id = $1 AND accepted_at IS NULL AND revoked_at IS NULL
+        "#,
+        invitation_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the invitation")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Create the account of the invitee with the email and role they were
+/// invited with. The invitation is used up in the same transaction, so a link
+/// creates at most one user.
+#[tracing::instrument(name = "Accept invitation", skip(pool, user, hashing), fields(username = %user.username))]
+pub async fn accept_invitation(
+    pool: &PgPool,
+    invitation_id: Uuid,
+    user: NewAdminUser,
+    hashing: &PasswordHashing,
+) -> Result<Uuid, InvitationError> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let accepted = sqlx::query!(
+        r#"
+UPDATE user_invitations SET accepted_at = now()
+WHERE invitation_id = $1
+    AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > now()
+        "#,
+        invitation_id,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to use up the invitation")?;
+    if accepted.rows_affected() == 0 {
+        return Err(InvitationError::NoLongerValid);
+    }
+    let user_id = match insert_user(&mut txn, user, hashing).await {
+        Ok(user_id) => user_id,
+        Err(UserError::UsernameTaken(username)) => {
+            return Err(InvitationError::UsernameTaken(username));
+        }
+        Err(UserError::UnexpectedError(e)) => return Err(e.into()),
+    };
+    txn.commit().await.context("Failed to commit")?;
+    Ok(user_id)
+}
@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use sqlx::PgPool;
+
+use crate::{domain::SubscriberEmail, email_client::EmailClient};
+
+/// How failed logins slow down, then lock out, a username or a client address.
+#[derive(Deserialize, Clone, Debug)]
+pub struct LoginThrottling {
+    /// Failed attempts allowed before delays kick in.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub free_attempts: i32,
+    /// The delay after the first throttled failure, doubled after each one.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub base_delay_seconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_delay_seconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lockout_threshold: i32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lockout_minutes: u64,
+    /// Failures older than this are forgotten, it should exceed the lockout.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub reset_after_minutes: u64,
+}
+
+impl LoginThrottling {
+    /// How long to refuse logins after the `failed_attempts`-th failure.
+    fn block_for(&self, failed_attempts: i32) -> Option<Duration> {
+        if failed_attempts >= self.lockout_threshold {
+            return Some(Duration::from_secs(self.lockout_minutes * 60));
+        }
+        let throttled = u32::try_from(failed_attempts - self.free_attempts).ok()?;
+        if throttled == 0 {
+            return None;
+        }
+        let delay = self
+            .base_delay_seconds
+            .saturating_mul(2u64.saturating_pow(throttled - 1))
+            .min(self.max_delay_seconds);
+        Some(Duration::from_secs(delay))
+    }
+}
+
+/// What failed logins are counted against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockoutScope {
+    Username,
+    Ip,
+}
+
+impl LockoutScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockoutScope::Username => "username",
+            LockoutScope::Ip => "ip",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "username" => Some(LockoutScope::Username),
+            "ip" => Some(LockoutScope::Ip),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoginFailure {
+    pub scope: String,
+    pub key: String,
+    pub failed_attempts: i32,
+    pub last_failed_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// When logins for `username` or from `ip` will be accepted again, if they are
+/// currently refused.
+#[tracing::instrument(name = "Check login lockout", skip(pool))]
+pub async fn login_blocked_until(
+    pool: &PgPool,
+    username: &str,
+    ip: Option<&str>,
+) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+SELECT max(locked_until) AS locked_until
+FROM login_failures
+WHERE locked_until > now()
+    AND ((scope = 'username' AND key = $1) OR (scope = 'ip' AND key = $2))
+        "#,
+        username,
+        ip,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to look up login lockouts")?;
+    Ok(row.locked_until)
+}
+
+/// Count a failed login against `username` and `ip`, returns whether it just
+/// locked the username out.
+#[tracing::instrument(name = "Record failed login", skip(pool, throttling))]
+pub async fn record_failed_login(
+    pool: &PgPool,
+    throttling: &LoginThrottling,
+    username: &str,
+    ip: Option<&str>,
+) -> Result<bool, anyhow::Error> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let forget_before =
+        Utc::now() - chrono::Duration::minutes(throttling.reset_after_minutes as i64);
+    // Every username ever tried gets a row, the forgotten ones are dropped.
+    sqlx::query!(
+        r#"
+DELETE FROM login_failures
+WHERE last_failed_at < $1 AND (locked_until IS NULL OR locked_until < now())
+        "#,
+        forget_before,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to forget old failed logins")?;
+    let mut username_locked_out = false;
+    let keys = std::iter::once((LockoutScope::Username, username))
+        .chain(ip.map(|ip| (LockoutScope::Ip, ip)));
+    for (scope, key) in keys {
+        let failed_attempts = sqlx::query_scalar!(
+            r#"
+INSERT INTO login_failures (scope, key, failed_attempts, last_failed_at)
+VALUES ($1, $2, 1, now())
+ON CONFLICT (scope, key) DO UPDATE SET
+    failed_attempts = CASE
+        WHEN login_failures.last_failed_at < $3 THEN 1
+        ELSE login_failures.failed_attempts + 1
+    END,
+    last_failed_at = now()
+RETURNING failed_attempts
+            "#,
+            scope.as_str(),
+            key,
+            forget_before,
+        )
+        .fetch_one(&mut *txn)
+        .await
+        .context("Failed to count the failed login")?;
+
+        if let Some(block_for) = throttling.block_for(failed_attempts) {
+            let locked_until = Utc::now()
+                + chrono::Duration::from_std(block_for).context("Lockout is too long")?;
+            sqlx::query!(
+                r#"UPDATE login_failures SET locked_until = $3 WHERE scope = $1 AND key = $2"#,
+                scope.as_str(),
+                key,
+                locked_until,
+            )
+            .execute(&mut *txn)
+            .await
+            .context("Failed to lock out the login")?;
+        }
+        if scope == LockoutScope::Username && failed_attempts == throttling.lockout_threshold {
+            username_locked_out = true;
+        }
+    }
+    txn.commit().await.context("Failed to commit")?;
+    Ok(username_locked_out)
+}
+
+/// A successful login wipes the slate clean for the user. The counter of the
+/// address is left to expire, or anyone with one valid account could reset it
+/// while guessing the passwords of the others.
+#[tracing::instrument(name = "Clear failed logins", skip(pool))]
+pub async fn clear_failed_logins(pool: &PgPool, username: &str) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+DELETE FROM login_failures
+WHERE scope = 'username' AND key = $1
+        "#,
+        username,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to clear failed logins")?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "List login failures", skip(pool))]
+pub async fn list_login_failures(pool: &PgPool) -> Result<Vec<LoginFailure>, anyhow::Error> {
+    sqlx::query_as!(
+        LoginFailure,
+        r#"
+SELECT scope, key, failed_attempts, last_failed_at, locked_until
+FROM login_failures
+ORDER BY last_failed_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to list login failures")
+}
+
+/// Returns `false` if nothing was recorded against `key`.
+#[tracing::instrument(name = "Unlock login", skip(pool))]
+pub async fn unlock_login(
+    pool: &PgPool,
+    scope: LockoutScope,
+    key: &str,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"DELETE FROM login_failures WHERE scope = $1 AND key = $2"#,
+        scope.as_str(),
+        key,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to unlock the login")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Tell the owner of `username` their account was locked out. Users without an
+/// email address on file are not notified.
+#[tracing::instrument(name = "Notify account lockout", skip(pool, email_client))]
+pub async fn notify_lockout(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    username: &str,
+    ip: Option<&str>,
+    lockout_minutes: u64,
+) -> Result<(), anyhow::Error> {
+    let email = sqlx::query_scalar!(r#"SELECT email FROM users WHERE username = $1"#, username)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to look up the account owner")?
+        .flatten();
+    let Some(email) = email else {
+        return Ok(());
+    };
+    let email = SubscriberEmail::parse(email).map_err(|e| anyhow::anyhow!(e))?;
+    let origin = ip.unwrap_or("an unknown address");
+    let plain_body = format!(
+        "There were too many failed attempts to log into your account {username} from {origin}.\n\
+        Logins are refused for the next {lockout_minutes} minutes.\n\
+        If it was not you, consider changing your password."
+    );
+    let html_body = format!(
+        "There were too many failed attempts to log into your account {} from {}.<br />\
+        Logins are refused for the next {lockout_minutes} minutes.<br />\
+        If it was not you, consider changing your password.",
+        htmlescape::encode_minimal(username),
+        htmlescape::encode_minimal(origin),
+    );
+    email_client
+        .send_email(
+            &email,
+            "Your account has been locked",
+            &html_body,
+            &plain_body,
+        )
+        .await
+        .context("Failed to send the lockout email")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use assertor::*;
+
+    use super::LoginThrottling;
+
+    fn throttling() -> LoginThrottling {
+        LoginThrottling {
+            free_attempts: 3,
+            base_delay_seconds: 1,
+            max_delay_seconds: 8,
+            lockout_threshold: 10,
+            lockout_minutes: 15,
+            reset_after_minutes: 60,
+        }
+    }
+
+    #[test]
+    fn delays_double_after_the_free_attempts() {
+        let throttling = throttling();
+        assert_that!(throttling.block_for(3)).is_none();
+        assert_that!(throttling.block_for(4)).is_equal_to(Some(Duration::from_secs(1)));
+        assert_that!(throttling.block_for(5)).is_equal_to(Some(Duration::from_secs(2)));
+        assert_that!(throttling.block_for(6)).is_equal_to(Some(Duration::from_secs(4)));
+        assert_that!(throttling.block_for(9)).is_equal_to(Some(Duration::from_secs(8)));
+    }
+
+    #[test]
+    fn the_threshold_locks_out() {
+        let throttling = throttling();
+        assert_that!(throttling.block_for(10)).is_equal_to(Some(Duration::from_secs(15 * 60)));
+        assert_that!(throttling.block_for(42)).is_equal_to(Some(Duration::from_secs(15 * 60)));
+    }
+}
@@ -1,22 +1,26 @@
 use anyhow::Context;
 use axum::{
+    Extension,
     extract::{Request, State},
     middleware::Next,
     response::{IntoResponse, Redirect},
 };
+use axum_messages::Messages;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use super::Role;
 use crate::{
     session_state::TypedSession,
     startup::AppState,
-    utils::{AppError, e500},
+    utils::{AppError, e403, e500},
 };
 
 #[derive(Clone)]
 pub struct CurrentUser {
     pub user_id: Uuid,
     pub username: String,
+    pub role: Role,
 }
 
 pub async fn reject_anonymous_users(
@@ -25,26 +29,70 @@ pub async fn reject_anonymous_users(
     mut request: Request,
     next: Next,
 ) -> Result<impl IntoResponse, AppError> {
-    match session
+    let Some(user_id) = session
         .get_user_id()
         .await
         .context("cannot get user id from session storage")
         .map_err(e500)?
+    else {
+        return Ok(Redirect::to("/login").into_response());
+    };
+    let metadata = session.get_metadata().await.map_err(e500)?;
+    if let Some(metadata) = &metadata
+        && state.session_settings.has_expired(metadata)
     {
-        Some(user_id) => {
-            let username = get_username(&user_id, &state.db_pool).await?;
-            request
-                .extensions_mut()
-                .insert(CurrentUser { user_id, username });
+        session.log_out().await.map_err(e500)?;
+        // Not an extractor argument: extracting `Messages` twice in a request
+        // drops the ones the handler should show.
+        if let Some(messages) = request.extensions().get::<Messages>() {
+            messages
+                .clone()
+                .info("Your session has expired, please log in again.");
+        }
+        return Ok(Redirect::to("/login").into_response());
+    }
+    match get_active_user(&user_id, &state.db_pool).await? {
+        Some(current_user) => {
+            session.touch().await.map_err(e500)?;
+            request.extensions_mut().insert(current_user);
             let response = next.run(request).await;
+            // Saving the session would otherwise give it the idle expiry of
+            // anonymous sessions.
+            if let Some(metadata) = &metadata
+                && session.is_modified()
+                && session.get_user_id().await.map_err(e500)?.is_some()
+            {
+                session.keep_until(state.session_settings.end_of(metadata));
+            }
             Ok(response.into_response())
         }
-        None => Ok(Redirect::to("/login").into_response()),
+        // The account was disabled or deleted since the session started.
+        None => {
+            session.log_out().await.map_err(e500)?;
+            Ok(Redirect::to("/login").into_response())
+        }
     }
 }
 
+/// Refuse users below `minimum`, to be layered inside
+/// [`reject_anonymous_users`].
+pub async fn require_role(
+    State(minimum): State<Role>,
+    Extension(current_user): Extension<CurrentUser>,
+    request: Request,
+    next: Next,
+) -> Result<impl IntoResponse, AppError> {
+    if current_user.role < minimum {
+        return Err(e403(anyhow::anyhow!(
+            "You need the {} role to do this",
+            minimum.as_str()
+        )));
+    }
+    Ok(next.run(request).await)
+}
+
 #[tracing::instrument(name = "Get username", skip(pool))]
-async fn get_username(user_id: &Uuid, pool: &PgPool) -> Result<String, anyhow::Error> {
+pub async fn get_username(user_id: &Uuid, pool: &PgPool) -> Result<String, anyhow::Error> {
     let row = sqlx::query!(
         r#"
     SELECT username
@@ -59,3 +107,30 @@ async fn get_username(user_id: &Uuid, pool: &PgPool) -> Result<String, anyhow::E
 
     Ok(row.username)
 }
+
+#[tracing::instrument(name = "Get active user", skip(pool))]
+async fn get_active_user(
+    user_id: &Uuid,
+    pool: &PgPool,
+) -> Result<Option<CurrentUser>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+    SELECT username, role
+    FROM users
+    WHERE user_id = $1 AND disabled_at IS NULL
+        "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve the current user")?;
+
+    row.map(|r| {
+        Ok(CurrentUser {
+            user_id: *user_id,
+            username: r.username,
+            role: Role::parse(&r.role).map_err(|e| anyhow::anyhow!(e))?,
+        })
+    })
+    .transpose()
+}
@@ -1,7 +1,38 @@
+mod bootstrap;
+mod invitations;
+mod lockout;
 mod middleware;
 mod password;
+mod password_policy;
+mod password_reset;
+mod role;
+mod totp;
+mod users;
 
-pub use middleware::{CurrentUser, reject_anonymous_users};
+pub use bootstrap::{SetupToken, create_first_owner, has_users};
+pub use invitations::{
+    INVITATION_VALIDITY, Invitation, InvitationError, accept_invitation, create_invitation,
+    get_pending_invitation, list_pending_invitations, revoke_invitation,
+};
+pub use lockout::{
+    LockoutScope, LoginFailure, LoginThrottling, clear_failed_logins, list_login_failures,
+    login_blocked_until, notify_lockout, record_failed_login, unlock_login,
+};
+pub use middleware::{CurrentUser, get_username, reject_anonymous_users, require_role};
 pub use password::{
-    AuthError, Credentials, change_password, compute_password_hash, validate_credentials,
+    AuthError, Credentials, PasswordHashing, change_password, compute_password_hash,
+    validate_credentials,
+};
+pub use password_policy::{PasswordError, PasswordPolicy, validate_password_security};
+pub use password_reset::{
+    PASSWORD_RESET_VALIDITY, ResetRecipient, check_password_reset, find_reset_recipients,
+    issue_password_reset, reset_password,
+};
+pub use role::Role;
+pub use totp::{
+    Totp, count_unused_recovery_codes, disable_totp, enable_totp, get_totp, verify_second_factor,
+};
+pub use users::{
+    AdminUser, NewAdminUser, UserError, create_user, delete_user, list_users, set_user_disabled,
+    set_user_role,
 };
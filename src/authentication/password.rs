@@ -1,14 +1,53 @@
 use anyhow::Context;
 use argon2::{
-    Argon2, PasswordHash, PasswordVerifier,
+    Algorithm, Argon2, Params, PasswordHash, PasswordVerifier, Version,
     password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
 };
 use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::telemetry::spawn_blocking_with_tracing;
 
+/// Argon2id cost of the password hashes we compute. Stored hashes that are
+/// cheaper than this are upgraded when their user logs in.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct PasswordHashing {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl PasswordHashing {
+    fn hasher(&self) -> Result<Argon2<'static>, anyhow::Error> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .context("Failed to build Argon2 parameters.")?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    /// Whether a hash that was just verified is weaker than what we would
+    /// compute today, or made with another algorithm.
+    pub fn needs_rehash(&self, password_hash: &SecretString) -> bool {
+        let Ok(password_hash) = PasswordHash::new(password_hash.expose_secret()) else {
+            return true;
+        };
+        if password_hash.algorithm != Algorithm::Argon2id.ident()
+            || password_hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+        match Params::try_from(&password_hash) {
+            Ok(params) => {
+                params.m_cost() < self.memory_kib
+                    || params.t_cost() < self.iterations
+                    || params.p_cost() < self.parallelism
+            }
+            Err(_) => true,
+        }
+    }
+}
+
 pub struct Credentials {
     pub username: String,
     pub password: SecretString,
@@ -22,31 +61,80 @@ pub enum AuthError {
     UnexpectedError(#[from] anyhow::Error),
 }
 
-#[tracing::instrument(name = "Validate credentials", skip(credentials, pool))]
+#[tracing::instrument(name = "Validate credentials", skip(credentials, hashing, pool))]
 pub async fn validate_credentials(
     credentials: Credentials,
+    hashing: &PasswordHashing,
     pool: &PgPool,
 ) -> Result<Uuid, AuthError> {
-    let (user_id, password_hash) = match get_stored_credentials(&credentials.username, pool).await?
-    {
-        Some((user_id, password_hash)) => (Some(user_id), password_hash),
-        None => (
-            None,
-            "$argon2id$v=19$m=15000,t=2,p=1$\
-                gySEVMmPuRVG7WfKGI3kkA$\
-                jLtcZMQ/KWaNuM2q7nYYcGan0wijjF7hCAYa56V28Ts"
-                .into(),
-        ),
+    let Some((user_id, password_hash)) =
+        get_stored_credentials(&credentials.username, pool).await?
+    else {
+        // Hashing costs as much as verifying with our parameters, so unknown
+        // usernames cannot be told apart by the response time.
+        let hashing = *hashing;
+        spawn_blocking_with_tracing(move || compute_password_hash(credentials.password, &hashing))
+            .await
+            .context("Failed to spawn blocking task.")
+            .map_err(AuthError::UnexpectedError)??;
+        return Err(AuthError::InvalidCredentials(anyhow::anyhow!(
+            "Unknown username"
+        )));
     };
 
-    spawn_blocking_with_tracing(move || verify_password_hash(credentials.password, password_hash))
+    let password = credentials.password.clone();
+    let expected_password_hash = password_hash.clone();
+    spawn_blocking_with_tracing(move || verify_password_hash(password, expected_password_hash))
         .await
         .context("Failed to spawn blocking task.")
         .map_err(AuthError::UnexpectedError)??;
 
-    user_id
-        .ok_or_else(|| anyhow::anyhow!("Unknown username"))
-        .map_err(AuthError::InvalidCredentials)
+    if hashing.needs_rehash(&password_hash) {
+        tokio::spawn(upgrade_password_hash(
+            pool.clone(),
+            user_id,
+            credentials.password,
+            password_hash,
+            *hashing,
+        ));
+    }
+    Ok(user_id)
+}
+
+/// Replace a verified hash by one with the current parameters, in the
+/// background so logins are not slowed down. Failing is harmless, the next
+/// login tries again.
+#[tracing::instrument(
+    name = "Upgrade password hash",
+    skip(pool, password, old_password_hash)
+)]
+async fn upgrade_password_hash(
+    pool: PgPool,
+    user_id: Uuid,
+    password: SecretString,
+    old_password_hash: SecretString,
+    hashing: PasswordHashing,
+) {
+    let upgrade = async {
+        let password_hash =
+            spawn_blocking_with_tracing(move || compute_password_hash(password, &hashing))
+                .await
+                .context("Failed to spawn blocking task.")??;
+        // The password may have been changed meanwhile, it must not be reverted.
+        sqlx::query!(
+            r#"UPDATE users SET password_hash = $3 WHERE user_id = $1 AND password_hash = $2"#,
+            user_id,
+            old_password_hash.expose_secret(),
+            password_hash.expose_secret(),
+        )
+        .execute(&pool)
+        .await
+        .context("Failed to store the upgraded password hash")?;
+        Ok::<_, anyhow::Error>(())
+    };
+    if let Err(e) = upgrade.await {
+        tracing::error!(error.cause_chain = ?e, "Failed to upgrade a password hash");
+    }
 }
 
 #[tracing::instrument(name = "Get stored credentials", skip(pool, username))]
@@ -58,7 +146,7 @@ async fn get_stored_credentials(
         r#"
     SELECT user_id, password_hash
     FROM users
-    WHERE username = $1;
+    WHERE username = $1 AND disabled_at IS NULL;
         "#,
         username,
     )
@@ -71,7 +159,7 @@ async fn get_stored_credentials(
 }
 
 #[tracing::instrument(name = "Verify password hash", skip(password, expected_password_hash))]
-fn verify_password_hash(
+pub(super) fn verify_password_hash(
     password: SecretString,
     expected_password_hash: SecretString,
 ) -> Result<(), AuthError> {
@@ -87,13 +175,15 @@ fn verify_password_hash(
     Ok(())
 }
 
-#[tracing::instrument(name = "Change password", skip(password, pool))]
+#[tracing::instrument(name = "Change password", skip(password, hashing, pool))]
 pub async fn change_password(
     username: &str,
     password: SecretString,
+    hashing: &PasswordHashing,
     pool: &PgPool,
 ) -> Result<(), AuthError> {
-    let password_hash = compute_password_hash(password).map_err(AuthError::UnexpectedError)?;
+    let password_hash =
+        compute_password_hash(password, hashing).map_err(AuthError::UnexpectedError)?;
     sqlx::query!(
         r#"
         UPDATE users
@@ -111,16 +201,61 @@ pub async fn change_password(
     Ok(())
 }
 
-pub fn compute_password_hash(password: SecretString) -> Result<SecretString, anyhow::Error> {
+pub fn compute_password_hash(
+    password: SecretString,
+    hashing: &PasswordHashing,
+) -> Result<SecretString, anyhow::Error> {
     let salt = SaltString::generate(&mut OsRng);
-    let password_hash = Argon2::new(
-        argon2::Algorithm::Argon2id,
-        argon2::Version::V0x13,
-        argon2::Params::new(15000, 2, 1, None).context("Failed to build Argon2 parameters.")?,
-    )
-    .hash_password(password.expose_secret().as_bytes(), &salt)
-    .context("Failed to hash password")?
-    .to_string();
+    let password_hash = hashing
+        .hasher()?
+        .hash_password(password.expose_secret().as_bytes(), &salt)
+        .context("Failed to hash password")?
+        .to_string();
 
     Ok(password_hash.into())
 }
+
+#[cfg(test)]
+mod test {
+    use argon2::{
+        Algorithm, Argon2, Params, Version,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+    use assertor::*;
+    use secrecy::SecretString;
+
+    use super::{PasswordHashing, compute_password_hash};
+
+    const HASHING: PasswordHashing = PasswordHashing {
+        memory_kib: 64,
+        iterations: 2,
+        parallelism: 1,
+    };
+
+    fn hash_with(algorithm: Algorithm, m: u32, t: u32, p: u32) -> SecretString {
+        let params = Params::new(m, t, p, None).unwrap();
+        Argon2::new(algorithm, Version::V0x13, params)
+            .hash_password(b"password", &SaltString::generate(&mut OsRng))
+            .unwrap()
+            .to_string()
+            .into()
+    }
+
+    #[test]
+    fn a_hash_with_the_current_parameters_is_kept() {
+        let password_hash = compute_password_hash("password".into(), &HASHING).unwrap();
+        assert_that!(HASHING.needs_rehash(&password_hash)).is_false();
+        assert_that!(HASHING.needs_rehash(&hash_with(Algorithm::Argon2id, 128, 3, 2))).is_false();
+    }
+
+    #[test]
+    fn a_weaker_hash_is_upgraded() {
+        assert_that!(HASHING.needs_rehash(&hash_with(Algorithm::Argon2id, 32, 2, 1))).is_true();
+        assert_that!(HASHING.needs_rehash(&hash_with(Algorithm::Argon2id, 64, 1, 1))).is_true();
+    }
+
+    #[test]
+    fn a_hash_from_another_algorithm_is_upgraded() {
+        assert_that!(HASHING.needs_rehash(&hash_with(Algorithm::Argon2i, 64, 2, 1))).is_true();
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use zxcvbn::zxcvbn;
+
+/// What a new password must be like to be accepted.
+#[derive(Deserialize, Clone, Debug)]
+pub struct PasswordPolicy {
+    /// Lowest zxcvbn strength score accepted, from 0 to 4.
+    pub min_strength: u8,
+    /// Breached passwords in the layout of the Have I Been Pwned range files:
+    /// one file per first five hex digits of the SHA-1 of a password, named
+    /// `{PREFIX}.txt`, listing the remaining digits as `SUFFIX:COUNT` lines.
+    #[serde(default)]
+    pub breached_passwords_dir: Option<PathBuf>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PasswordError {
+    #[error("{0}")]
+    Rejected(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl PasswordPolicy {
+    /// Shown next to forms where a new password is chosen.
+    pub fn requirements(&self) -> String {
+        format!(
+            "Use 12 to 128 characters. The password must score at least {} out of 4 on strength{}.",
+            self.min_strength,
+            if self.breached_passwords_dir.is_some() {
+                " and must not appear in known data breaches"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+/// The rules every new password must follow. `user_inputs` are words the
+/// password must not be built from, such as the username.
+pub async fn validate_password_security(
+    policy: &PasswordPolicy,
+    password: &SecretString,
+    user_inputs: &[&str],
+) -> Result<(), PasswordError> {
+    let password = password.expose_secret();
+    if password.len() < 12 {
+        return Err(rejected("New password must be at least 12 characters."));
+    }
+    if password.len() >= 128 {
+        return Err(rejected("New password must be less than 128 characters."));
+    }
+
+    let entropy = zxcvbn(password, user_inputs);
+    if u8::from(entropy.score()) < policy.min_strength {
+        let mut message = format!(
+            "New password is too easy to guess (strength {} out of 4, at least {} needed).",
+            entropy.score(),
+            policy.min_strength
+        );
+        if let Some(feedback) = entropy.feedback() {
+            if let Some(warning) = feedback.warning() {
+                message.push_str(&format!(" {warning}"));
+            }
+            for suggestion in feedback.suggestions() {
+                message.push_str(&format!(" {suggestion}"));
+            }
+        }
+        return Err(PasswordError::Rejected(message));
+    }
+
+    if let Some(dir) = &policy.breached_passwords_dir
+        && is_breached(dir, password).await?
+    {
+        return Err(rejected(
+            "New password appears in a known data breach, choose another one.",
+        ));
+    }
+    Ok(())
+}
+
+fn rejected(message: &str) -> PasswordError {
+    PasswordError::Rejected(message.to_string())
+}
+
+/// Only the file for the first five digits of the hash is read, the list
+/// never has to be loaded whole.
+async fn is_breached(dir: &Path, password: &str) -> Result<bool, anyhow::Error> {
+    let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = hash.split_at(5);
+    let range = match tokio::fs::read_to_string(dir.join(format!("{prefix}.txt"))).await {
+        Ok(range) => range,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).context("Failed to read the breached passwords"),
+    };
+    Ok(range.lines().any(|line| {
+        line.split(':')
+            .next()
+            .is_some_and(|s| s.trim().eq_ignore_ascii_case(suffix))
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use assertor::*;
+    use secrecy::SecretString;
+    use sha1::{Digest, Sha1};
+    use uuid::Uuid;
+
+    use super::{PasswordError, PasswordPolicy, validate_password_security};
+
+    fn policy(breached_passwords_dir: Option<PathBuf>) -> PasswordPolicy {
+        PasswordPolicy {
+            min_strength: 3,
+            breached_passwords_dir,
+        }
+    }
+
+    async fn check(policy: &PasswordPolicy, password: &str) -> Result<(), PasswordError> {
+        validate_password_security(policy, &SecretString::from(password), &["ursula"]).await
+    }
+
+    fn rejection(result: Result<(), PasswordError>) -> String {
+        match result {
+            Err(PasswordError::Rejected(message)) => message,
+            other => panic!("expected a rejection, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_long_but_predictable_password_is_rejected_with_feedback() {
+        let message = rejection(check(&policy(None), "aaaaaaaaaaaa").await);
+        assert_that!(message).contains("strength 0 out of 4");
+        assert_that!(message).contains("Repeats like");
+    }
+
+    #[tokio::test]
+    async fn a_password_built_from_the_username_is_weaker() {
+        let policy = PasswordPolicy {
+            min_strength: 4,
+            breached_passwords_dir: None,
+        };
+        let password = SecretString::from("leguin-ursula");
+
+        assert_that!(validate_password_security(&policy, &password, &[]).await).is_ok();
+        let message = rejection(check(&policy, "leguin-ursula").await);
+        assert_that!(message).contains("too easy to guess");
+    }
+
+    #[tokio::test]
+    async fn a_strong_password_is_accepted() {
+        assert_that!(check(&policy(None), "correct horse battery staple").await).is_ok();
+    }
+
+    #[tokio::test]
+    async fn a_breached_password_is_rejected() {
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let hash = hex::encode_upper(Sha1::digest(b"correct horse battery staple"));
+        std::fs::write(
+            dir.join(format!("{}.txt", &hash[..5])),
+            format!(
+                "0000000000000000000000000000000000A:3\r\n{}:42\r\n",
+                &hash[5..]
+            ),
+        )
+        .unwrap();
+        let policy = policy(Some(dir.clone()));
+
+        let message = rejection(check(&policy, "correct horse battery staple").await);
+        let accepted = check(&policy, "a-long-chosen-password").await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_that!(message).contains("known data breach");
+        assert_that!(accepted).is_ok();
+    }
+}
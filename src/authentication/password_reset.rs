@@ -0,0 +1,142 @@
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use rand::distr::{Alphanumeric, SampleString};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{PasswordHashing, compute_password_hash};
+use crate::telemetry::spawn_blocking_with_tracing;
+
+/// How long a password reset link can be used after it was requested.
+pub const PASSWORD_RESET_VALIDITY: Duration = Duration::minutes(30);
+
+/// An active user a password reset link can be sent to.
+#[derive(Debug)]
+pub struct ResetRecipient {
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: String,
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[tracing::instrument(name = "Find password reset recipients", skip(pool))]
+pub async fn find_reset_recipients(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Vec<ResetRecipient>, anyhow::Error> {
+    let recipients = sqlx::query_as!(
+        ResetRecipient,
+        r#"
+SELECT user_id, username, email AS "email!"
+FROM users
+WHERE lower(email) = lower($1) AND disabled_at IS NULL
+        "#,
+        email,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to look up the users")?;
+    Ok(recipients)
+}
+
+/// Store a new reset token for the user and return it, it is only ever sent
+/// by email.
+#[tracing::instrument(name = "Issue password reset token", skip(pool))]
+pub async fn issue_password_reset(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<SecretString, anyhow::Error> {
+    let token = Alphanumeric.sample_string(&mut rand::rng(), 40);
+    sqlx::query!(
+        r#"
+INSERT INTO password_resets (token_hash, user_id, created_at, expires_at)
+VALUES ($1, $2, now(), $3)
+        "#,
+        hash_token(&token),
+        user_id,
+        Utc::now() + PASSWORD_RESET_VALIDITY,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the password reset token")?;
+    Ok(SecretString::from(token))
+}
+
+/// The user a reset token was issued for, if it is unused and not expired.
+#[tracing::instrument(name = "Check password reset token", skip(pool, token))]
+pub async fn check_password_reset(
+    pool: &PgPool,
+    token: &SecretString,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+SELECT password_resets.user_id
+FROM password_resets JOIN users USING (user_id)
+WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now() AND disabled_at IS NULL
+        "#,
+        hash_token(token.expose_secret()),
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to look up the password reset token")?;
+    Ok(row.map(|r| r.user_id))
+}
+
+/// Set the new password of the user the token was issued for. The token and
+/// every other token of the user are used up, so a leaked link stops working.
+/// Returns `None` if the token cannot be used.
+#[tracing::instrument(name = "Reset password", skip(pool, token, password, hashing))]
+pub async fn reset_password(
+    pool: &PgPool,
+    token: &SecretString,
+    password: SecretString,
+    hashing: &PasswordHashing,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let hashing = *hashing;
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password, &hashing))
+            .await
+            .context("Failed to spawn blocking task.")??;
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let Some(row) = sqlx::query!(
+        r#"
+UPDATE password_resets SET used_at = now()
+FROM users
+WHERE password_resets.user_id = users.user_id
+    AND token_hash = $1 AND used_at IS NULL AND expires_at > now() AND disabled_at IS NULL
+RETURNING password_resets.user_id
+        "#,
+        hash_token(token.expose_secret()),
+    )
+    .fetch_optional(&mut *txn)
+    .await
+    .context("Failed to use up the password reset token")?
+    else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        r#"UPDATE password_resets SET used_at = now() WHERE user_id = $1 AND used_at IS NULL"#,
+        row.user_id,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to use up the other password reset tokens")?;
+    sqlx::query!(
+        r#"UPDATE users SET password_hash = $2 WHERE user_id = $1"#,
+        row.user_id,
+        password_hash.expose_secret(),
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to update the password")?;
+    txn.commit().await.context("Failed to commit")?;
+    Ok(Some(row.user_id))
+}
@@ -0,0 +1,52 @@
+/// What an admin user is allowed to do, each role can do everything the ones
+/// before it can:
+/// - viewers browse subscribers, segments and past issues,
+/// - editors prepare the audience: segments, imports, confirmations,
+///   unsubscriptions and new suppressions,
+/// - publishers also send newsletters, delete or erase subscribers and remove
+///   suppressions,
+/// - owners also manage users and login lockouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Viewer,
+    Editor,
+    Publisher,
+    Owner,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Owner, Role::Publisher, Role::Editor, Role::Viewer];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Publisher => "publisher",
+            Role::Owner => "owner",
+        }
+    }
+
+    pub fn parse(role: &str) -> Result<Self, String> {
+        Role::ALL
+            .into_iter()
+            .find(|r| r.as_str() == role)
+            .ok_or_else(|| format!("{role} is not a role"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+
+    use super::Role;
+
+    #[test]
+    fn roles_round_trip_and_are_ordered_by_privilege() {
+        for role in Role::ALL {
+            assert_that!(Role::parse(role.as_str())).is_equal_to(Ok(role));
+        }
+        assert_that!(Role::parse("admin")).is_err();
+        assert_that!(Role::Owner > Role::Publisher).is_true();
+        assert_that!(Role::Editor > Role::Viewer).is_true();
+    }
+}
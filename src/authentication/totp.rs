@@ -0,0 +1,431 @@
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, KeyInit as _, Payload},
+};
+use anyhow::Context;
+use chrono::Utc;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use qrcode::{QrCode, render::svg};
+use rand::{
+    RngCore,
+    distr::{Alphanumeric, SampleString},
+};
+use secrecy::{ExposeSecret, SecretString};
+use sha1::Sha1;
+use sha2::Sha256;
+use sqlx::{PgConnection, PgPool};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use super::password::{PasswordHashing, compute_password_hash, verify_password_hash};
+use crate::telemetry::spawn_blocking_with_tracing;
+
+/// Name authenticator apps list the account under.
+const ISSUER: &str = "zero2prod";
+const TIME_STEP_SECONDS: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes from the previous and next time step are accepted too, to make up for
+/// clock drift and slow typists.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Marks the secrets stored encrypted, older ones were stored as is.
+const ENCRYPTED_PREFIX: &str = "aes-gcm:";
+const NONCE_LENGTH: usize = 12;
+
+/// A time-based one-time password generator as specified by RFC 6238, with the
+/// SHA-1, 6 digits and 30 seconds defaults every authenticator app supports.
+pub struct Totp {
+    secret: SecretString,
+}
+
+impl Totp {
+    /// A new random secret, not stored anywhere yet.
+    pub fn generate() -> Self {
+        let mut key = [0u8; 20];
+        rand::rng().fill_bytes(&mut key);
+        Totp {
+            secret: base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &key).into(),
+        }
+    }
+
+    pub fn from_secret(secret: SecretString) -> Self {
+        Totp { secret }
+    }
+
+    /// The base32 secret, for users who cannot scan the QR code.
+    pub fn secret(&self) -> &SecretString {
+        &self.secret
+    }
+
+    pub fn current_step() -> i64 {
+        Utc::now().timestamp() / TIME_STEP_SECONDS
+    }
+
+    pub fn code_at(&self, step: i64) -> Result<String, anyhow::Error> {
+        let key = base32::decode(
+            base32::Alphabet::Rfc4648 { padding: false },
+            self.secret.expose_secret(),
+        )
+        .context("The TOTP secret is not valid base32")?;
+        let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(&key).context("Invalid TOTP key")?;
+        mac.update(&step.to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+        let offset = usize::from(digest[19] & 0x0f);
+        let truncated = u32::from_be_bytes(digest[offset..offset + 4].try_into()?) & 0x7fff_ffff;
+        Ok(format!(
+            "{:0width$}",
+            truncated % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        ))
+    }
+
+    /// The time step `code` is valid for, if it is valid now and was not used
+    /// at or before `last_step`.
+    pub fn verify(&self, code: &str, last_step: Option<i64>) -> Result<Option<i64>, anyhow::Error> {
+        let code = code.trim().replace(' ', "");
+        let now = Self::current_step();
+        for step in now - ALLOWED_DRIFT_STEPS..=now + ALLOWED_DRIFT_STEPS {
+            if last_step.is_some_and(|last_step| step <= last_step) {
+                continue;
+            }
+            if bool::from(self.code_at(step)?.as_bytes().ct_eq(code.as_bytes())) {
+                return Ok(Some(step));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The `otpauth://` URI authenticator apps import.
+    pub fn provisioning_uri(&self, username: &str) -> String {
+        format!(
+            "otpauth://totp/{issuer}:{username}?secret={secret}&issuer={issuer}&digits={DIGITS}&period={TIME_STEP_SECONDS}",
+            issuer = urlencoding::encode(ISSUER),
+            username = urlencoding::encode(username),
+            secret = self.secret.expose_secret(),
+        )
+    }
+
+    /// The provisioning URI as an SVG QR code.
+    pub fn qr_code_svg(&self, username: &str) -> Result<String, anyhow::Error> {
+        let code = QrCode::new(self.provisioning_uri(username).as_bytes())
+            .context("Failed to encode the QR code")?;
+        Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+    }
+}
+
+/// TOTP secrets are encrypted at rest with a key derived from the application
+/// secret, so a copy of the database alone does not give away second factors.
+fn secret_cipher(hmac_secret: &SecretString) -> Aes256Gcm {
+    let mut key = Key::<Aes256Gcm>::default();
+    Hkdf::<Sha256>::new(None, hmac_secret.expose_secret().as_bytes())
+        .expand(b"zero2prod totp_secret", &mut key)
+        .expect("HKDF can output 32 bytes");
+    Aes256Gcm::new(&key)
+}
+
+/// The secret of `user_id` as stored in the database, it cannot be moved to
+/// another user.
+fn encrypt_secret(
+    hmac_secret: &SecretString,
+    user_id: Uuid,
+    secret: &SecretString,
+) -> Result<String, anyhow::Error> {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::rng().fill_bytes(&mut nonce);
+    let ciphertext = secret_cipher(hmac_secret)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: secret.expose_secret().as_bytes(),
+                aad: user_id.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt the TOTP secret"))?;
+    Ok(format!(
+        "{ENCRYPTED_PREFIX}{}{}",
+        hex::encode(nonce),
+        hex::encode(ciphertext)
+    ))
+}
+
+fn decrypt_secret(
+    hmac_secret: &SecretString,
+    user_id: Uuid,
+    stored: &str,
+) -> Result<SecretString, anyhow::Error> {
+    let encrypted = stored
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .context("The TOTP secret is not encrypted")?;
+    let encrypted = hex::decode(encrypted).context("The TOTP secret is not valid hex")?;
+    if encrypted.len() < NONCE_LENGTH {
+        anyhow::bail!("The TOTP secret is too short");
+    }
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+    let secret = secret_cipher(hmac_secret)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: user_id.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt the TOTP secret"))?;
+    Ok(String::from_utf8(secret)
+        .context("The TOTP secret is not valid UTF-8")?
+        .into())
+}
+
+/// The second factor of a user, `None` if they did not enroll.
+#[tracing::instrument(name = "Get TOTP settings", skip(pool, hmac_secret))]
+pub async fn get_totp(
+    pool: &PgPool,
+    hmac_secret: &SecretString,
+    user_id: Uuid,
+) -> Result<Option<(Totp, Option<i64>)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"SELECT totp_secret, totp_last_step FROM users WHERE user_id = $1"#,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to fetch the TOTP settings")?;
+    let Some(stored) = row.totp_secret else {
+        return Ok(None);
+    };
+    let secret = decrypt_secret(hmac_secret, user_id, &stored)?;
+    Ok(Some((Totp::from_secret(secret), row.totp_last_step)))
+}
+
+/// Check a code typed in by `user_id`, either from their authenticator app or
+/// one of their recovery codes, which are then used up.
+#[tracing::instrument(name = "Verify second factor", skip(pool, hmac_secret, code))]
+pub async fn verify_second_factor(
+    pool: &PgPool,
+    hmac_secret: &SecretString,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, anyhow::Error> {
+    let Some((totp, last_step)) = get_totp(pool, hmac_secret, user_id).await? else {
+        return Ok(false);
+    };
+    if let Some(step) = totp.verify(code, last_step)? {
+        let updated = sqlx::query!(
+            r#"
+UPDATE users SET totp_last_step = $2
+WHERE user_id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)
+            "#,
+            user_id,
+            step,
+        )
+        .execute(pool)
+        .await
+        .context("Failed to record the TOTP time step")?;
+        // A concurrent login got there first with the same code.
+        return Ok(updated.rows_affected() == 1);
+    }
+    use_recovery_code(pool, user_id, code).await
+}
+
+async fn use_recovery_code(
+    pool: &PgPool,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, anyhow::Error> {
+    let candidates = sqlx::query!(
+        r#"SELECT id, code_hash FROM recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the recovery codes")?;
+    let code = SecretString::from(code.trim().to_lowercase());
+    let hashes: Vec<(Uuid, SecretString)> = candidates
+        .into_iter()
+        .map(|row| (row.id, row.code_hash.into()))
+        .collect();
+    let matched = spawn_blocking_with_tracing(move || {
+        hashes
+            .into_iter()
+            .find(|(_, hash)| verify_password_hash(code.clone(), hash.clone()).is_ok())
+            .map(|(id, _)| id)
+    })
+    .await
+    .context("Failed to spawn blocking task.")?;
+    let Some(id) = matched else {
+        return Ok(false);
+    };
+    let updated = sqlx::query!(
+        r#"UPDATE recovery_codes SET used_at = now() WHERE id = $1 AND used_at IS NULL"#,
+        id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to use up the recovery code")?;
+    Ok(updated.rows_affected() == 1)
+}
+
+/// Turn on two-factor authentication for `user_id` and return the recovery
+/// codes, which are only ever shown this once.
+#[tracing::instrument(name = "Enable TOTP", skip(pool, hmac_secret, totp, hashing))]
+pub async fn enable_totp(
+    pool: &PgPool,
+    hmac_secret: &SecretString,
+    user_id: Uuid,
+    totp: &Totp,
+    confirmed_step: i64,
+    hashing: &PasswordHashing,
+) -> Result<Vec<String>, anyhow::Error> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    sqlx::query!(
+        r#"UPDATE users SET totp_secret = $2, totp_last_step = $3 WHERE user_id = $1"#,
+        user_id,
+        encrypt_secret(hmac_secret, user_id, totp.secret())?,
+        confirmed_step,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to store the TOTP secret")?;
+    let codes = replace_recovery_codes(&mut txn, user_id, hashing).await?;
+    txn.commit().await.context("Failed to commit")?;
+    Ok(codes)
+}
+
+#[tracing::instrument(name = "Disable TOTP", skip(pool))]
+pub async fn disable_totp(pool: &PgPool, user_id: Uuid) -> Result<(), anyhow::Error> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    sqlx::query!(
+        r#"UPDATE users SET totp_secret = NULL, totp_last_step = NULL WHERE user_id = $1"#,
+        user_id,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to clear the TOTP secret")?;
+    sqlx::query!(r#"DELETE FROM recovery_codes WHERE user_id = $1"#, user_id)
+        .execute(&mut *txn)
+        .await
+        .context("Failed to delete the recovery codes")?;
+    txn.commit().await.context("Failed to commit")?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Count recovery codes", skip(pool))]
+pub async fn count_unused_recovery_codes(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<i64, anyhow::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT count(*) AS "count!" FROM recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count the recovery codes")
+}
+
+async fn replace_recovery_codes(
+    txn: &mut PgConnection,
+    user_id: Uuid,
+    hashing: &PasswordHashing,
+) -> Result<Vec<String>, anyhow::Error> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = Alphanumeric
+                .sample_string(&mut rand::rng(), 10)
+                .to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect();
+    let to_hash = codes.clone();
+    let hashing = *hashing;
+    let hashes = spawn_blocking_with_tracing(move || {
+        to_hash
+            .into_iter()
+            .map(|code| {
+                compute_password_hash(code.into(), &hashing)
+                    .map(|hash| hash.expose_secret().to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .context("Failed to spawn blocking task.")??;
+
+    let ids: Vec<Uuid> = hashes.iter().map(|_| Uuid::new_v4()).collect();
+
+    sqlx::query!(r#"DELETE FROM recovery_codes WHERE user_id = $1"#, user_id)
+        .execute(&mut *txn)
+        .await
+        .context("Failed to delete the previous recovery codes")?;
+    sqlx::query!(
+        r#"
+INSERT INTO recovery_codes (id, user_id, code_hash)
+SELECT id, $1, code_hash FROM UNNEST($2::uuid[], $3::text[]) AS t (id, code_hash)
+        "#,
+        user_id,
+        &ids,
+        &hashes,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to store the recovery codes")?;
+    Ok(codes)
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+    use secrecy::{ExposeSecret, SecretString};
+    use uuid::Uuid;
+
+    use super::{Totp, decrypt_secret, encrypt_secret};
+
+    /// The SHA-1 test vectors of RFC 6238, truncated to 6 digits.
+    #[test]
+    fn codes_match_the_rfc_test_vectors() {
+        let secret = base32::encode(
+            base32::Alphabet::Rfc4648 { padding: false },
+            b"12345678901234567890",
+        );
+        let totp = Totp::from_secret(secret.into());
+        assert_that!(totp.code_at(59 / 30).unwrap()).is_equal_to("287082".to_string());
+        assert_that!(totp.code_at(1111111109 / 30).unwrap()).is_equal_to("081804".to_string());
+        assert_that!(totp.code_at(2000000000 / 30).unwrap()).is_equal_to("279037".to_string());
+    }
+
+    #[test]
+    fn a_code_cannot_be_replayed() {
+        let totp = Totp::generate();
+        let code = totp.code_at(Totp::current_step()).unwrap();
+        let step = totp.verify(&code, None).unwrap();
+        assert_that!(step).is_some();
+        assert_that!(totp.verify(&code, step).unwrap()).is_none();
+    }
+
+    #[test]
+    fn a_stored_secret_only_opens_with_the_same_key_and_user() {
+        let key = SecretString::from("secret");
+        let user_id = Uuid::new_v4();
+        let totp = Totp::generate();
+        let stored = encrypt_secret(&key, user_id, totp.secret()).unwrap();
+
+        assert_that!(stored.contains(totp.secret().expose_secret())).is_false();
+        let secret = decrypt_secret(&key, user_id, &stored).unwrap();
+        assert_that!(secret.expose_secret()).is_equal_to(totp.secret().expose_secret());
+        assert_that!(decrypt_secret(
+            &SecretString::from("other"),
+            user_id,
+            &stored
+        ))
+        .is_err();
+        assert_that!(decrypt_secret(&key, Uuid::new_v4(), &stored)).is_err();
+        // A secret written in clear into the database is refused.
+        let plaintext = totp.secret().expose_secret();
+        assert_that!(decrypt_secret(&key, user_id, plaintext)).is_err();
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use super::{PasswordHashing, Role, compute_password_hash};
+use crate::{domain::SubscriberEmail, telemetry::spawn_blocking_with_tracing};
+
+#[derive(Debug)]
+pub struct AdminUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    pub role: Role,
+    pub two_factor_enabled: bool,
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+pub struct NewAdminUser {
+    pub username: String,
+    pub email: Option<SubscriberEmail>,
+    pub role: Role,
+    pub password: SecretString,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum UserError {
+    #[error("The username {0} is already taken")]
+    UsernameTaken(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+#[tracing::instrument(name = "List admin users", skip(pool))]
+pub async fn list_users(pool: &PgPool) -> Result<Vec<AdminUser>, anyhow::Error> {
+    sqlx::query!(
+        r#"
+SELECT user_id, username, email, role, totp_secret IS NOT NULL AS "two_factor_enabled!", disabled_at
+FROM users
+ORDER BY username
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to list the users")?
+    .into_iter()
+    .map(|r| {
+        Ok(AdminUser {
+            user_id: r.user_id,
+            username: r.username,
+            email: r.email,
+            role: Role::parse(&r.role).map_err(|e| anyhow::anyhow!(e))?,
+            two_factor_enabled: r.two_factor_enabled,
+            disabled_at: r.disabled_at,
+        })
+    })
+    .collect()
+}
+
+#[tracing::instrument(name = "Create admin user", skip(pool, user, hashing), fields(username = %user.username))]
+pub async fn create_user(
+    pool: &PgPool,
+    user: NewAdminUser,
+    hashing: &PasswordHashing,
+) -> Result<Uuid, UserError> {
+    let mut conn = pool
+        .acquire()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    insert_user(&mut conn, user, hashing).await
+}
+
+/// Hash the password and insert the user, on a connection so it can be part
+/// of a larger transaction.
+pub(super) async fn insert_user(
+    conn: &mut PgConnection,
+    user: NewAdminUser,
+    hashing: &PasswordHashing,
+) -> Result<Uuid, UserError> {
+    let password = user.password;
+    let hashing = *hashing;
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password, &hashing))
+            .await
+            .context("Failed to spawn blocking task.")??;
+    let user_id = Uuid::new_v4();
+    let result = sqlx::query!(
+        r#"
+INSERT INTO users (user_id, username, password_hash, email, role)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (username) DO NOTHING
+        "#,
+        user_id,
+        user.username,
+        password_hash.expose_secret(),
+        user.email.as_ref().map(|email| email.as_ref()),
+        user.role.as_str(),
+    )
+    .execute(conn)
+    .await
+    .context("Failed to insert the user")?;
+    if result.rows_affected() == 0 {
+        return Err(UserError::UsernameTaken(user.username));
+    }
+    Ok(user_id)
+}
+
+/// The functions below return `false` when the user does not exist.
+#[tracing::instrument(name = "Change user role", skip(pool))]
+pub async fn set_user_role(
+    pool: &PgPool,
+    user_id: Uuid,
+    role: Role,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"UPDATE users SET role = $2 WHERE user_id = $1"#,
+        user_id,
+        role.as_str(),
+    )
+    .execute(pool)
+    .await
+    .context("Failed to change the role")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Disabled users cannot log in and their open sessions stop working.
+#[tracing::instrument(name = "Disable user", skip(pool))]
+pub async fn set_user_disabled(
+    pool: &PgPool,
+    user_id: Uuid,
+    disabled: bool,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+UPDATE users
+SET disabled_at = CASE WHEN $2 THEN coalesce(disabled_at, now()) END
+WHERE user_id = $1
+        "#,
+        user_id,
+        disabled,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the user")?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(name = "Delete user", skip(pool))]
+pub async fn delete_user(pool: &PgPool, user_id: Uuid) -> Result<bool, anyhow::Error> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    // Saved responses are only useful to the user who made the requests.
+    sqlx::query!(r#"DELETE FROM idempotency WHERE user_id = $1"#, user_id)
+        .execute(&mut *txn)
+        .await
+        .context("Failed to delete the saved responses")?;
+    let result = sqlx::query!(r#"DELETE FROM users WHERE user_id = $1"#, user_id)
+        .execute(&mut *txn)
+        .await
+        .context("Failed to delete the user")?;
+    txn.commit().await.context("Failed to commit")?;
+    Ok(result.rows_affected() > 0)
+}
@@ -7,7 +7,13 @@ use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::ConnectOptions;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 
-use crate::domain::SubscriberEmail;
+use crate::{
+    anti_bot::AntiBotSettings,
+    authentication::{LoginThrottling, PasswordHashing, PasswordPolicy},
+    domain::{SignupPolicy, SubscriberAttributeRules, SubscriberEmail},
+    rate_limit::RateLimitSettings,
+    session_state::SessionSettings,
+};
 
 #[derive(Deserialize, Clone)]
 pub struct Settings {
@@ -15,6 +21,13 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: SecretString,
+    pub subscriber_attributes: SubscriberAttributeRules,
+    pub signup_policy: SignupPolicy,
+    pub anti_bot: AntiBotSettings,
+    pub rate_limit: RateLimitSettings,
+    pub login_throttling: LoginThrottling,
+    pub password_hashing: PasswordHashing,
+    pub password_policy: PasswordPolicy,
 }
 
 #[derive(Deserialize, Clone)]
@@ -35,6 +48,7 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: SecretString,
+    pub session: SessionSettings,
 }
 
 #[derive(Deserialize, Clone)]
@@ -42,6 +56,8 @@ pub struct EmailClientSettings {
     pub base_url: String,
     pub sender_email: String,
     pub authorization_token: SecretString,
+    /// Password providers must present, with basic auth, when calling our webhooks.
+    pub webhook_token: SecretString,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub timeout_milliseconds: u64,
 }
@@ -0,0 +1,126 @@
+use anyhow::anyhow;
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::{FromRequest, FromRequestParts, Multipart, Request},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use futures_util::{StreamExt, stream};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    html,
+    html::Markup,
+    session_state::TypedSession,
+    utils::{AppError, e400, e403, e500},
+};
+
+/// The form field carrying the token.
+const FIELD: &str = "csrf_token";
+/// Largest form we read to find the token, the same as the default limit of
+/// the `Form` extractor.
+const MAX_FORM_SIZE: usize = 2 * 1024 * 1024;
+/// How much of a multipart body we read to find the token, which must be its
+/// first part.
+const MAX_MULTIPART_PREFIX: usize = 16 * 1024;
+
+/// The CSRF token of the current session, for the forms of a page.
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    /// The hidden input every form posting to a protected route must carry,
+    /// first in multipart forms.
+    pub fn field(&self) -> Markup {
+        html!(
+            r#"<input type="hidden" name="{FIELD}" value="{}" />"#,
+            self.0,
+            FIELD,
+        )
+    }
+}
+
+impl<S> FromRequestParts<S> for CsrfToken
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session = TypedSession::from_request_parts(parts, state)
+            .await
+            .map_err(|(_, e)| e500(anyhow!(e)))?;
+        let token = session.csrf_token().await.map_err(e500)?;
+        Ok(CsrfToken(token))
+    }
+}
+
+/// Refuse requests changing state unless their form carries the CSRF token of
+/// the session, so other sites cannot submit forms on behalf of our users.
+pub async fn verify_csrf_token(
+    session: TypedSession,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if request.method().is_safe() {
+        return Ok(next.run(request).await);
+    }
+    let expected = session.get_csrf_token().await.map_err(e500)?;
+    let (token, request) = read_token(request).await?;
+    let valid = match (expected, token) {
+        (Some(expected), Some(token)) => bool::from(expected.as_bytes().ct_eq(token.as_bytes())),
+        _ => false,
+    };
+    if !valid {
+        tracing::warn!(uri = %request.uri(), "Refused a request without a valid CSRF token");
+        return Err(e403(anyhow!(
+            "The form has expired or did not come from this site, reload the page and try again"
+        )));
+    }
+    Ok(next.run(request).await)
+}
+
+/// Find the token in the form, and give back a request with the same body.
+async fn read_token(request: Request) -> Result<(Option<String>, Request), AppError> {
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+    if is_multipart {
+        return read_multipart_token(request).await;
+    }
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_FORM_SIZE).await.map_err(e400)?;
+    let token = form_urlencoded::parse(&body)
+        .find(|(key, _)| key == FIELD)
+        .map(|(_, value)| value.into_owned());
+    Ok((token, Request::from_parts(parts, Body::from(body))))
+}
+
+/// Uploads are streamed to their handler, so only the start of the body is
+/// read, enough to hold a first part with the token.
+async fn read_multipart_token(request: Request) -> Result<(Option<String>, Request), AppError> {
+    let (parts, body) = request.into_parts();
+    let mut rest = body.into_data_stream();
+    let mut prefix = Vec::new();
+    while prefix.len() < MAX_MULTIPART_PREFIX
+        && let Some(chunk) = rest.next().await
+    {
+        prefix.extend_from_slice(&chunk.map_err(e400)?);
+    }
+    let prefix = Bytes::from(prefix);
+
+    let mut probe = Request::new(Body::from(prefix.clone()));
+    *probe.headers_mut() = parts.headers.clone();
+    let token = match Multipart::from_request(probe, &()).await {
+        Ok(mut multipart) => match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some(FIELD) => field.text().await.ok(),
+            _ => None,
+        },
+        Err(_) => None,
+    };
+
+    let body = Body::from_stream(stream::iter([Ok(prefix)]).chain(rest));
+    Ok((token, Request::from_parts(parts, body)))
+}
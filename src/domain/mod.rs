@@ -1,7 +1,13 @@
 mod new_subscriber;
+mod signup_policy;
+mod subscriber_attributes;
 mod subscriber_email;
 mod subscriber_name;
+mod subscriber_tag;
 
 pub use new_subscriber::NewSubscriber;
+pub use signup_policy::{RoleAddressPolicy, SignupPolicy};
+pub use subscriber_attributes::{AttributeRule, SubscriberAttributeRules, SubscriberAttributes};
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_tag::SubscriberTag;
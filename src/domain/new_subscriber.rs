@@ -1,6 +1,29 @@
-use super::{SubscriberEmail, SubscriberName};
+use std::collections::BTreeMap;
+
+use super::{
+    SubscriberAttributeRules, SubscriberAttributes, SubscriberEmail, SubscriberName, SubscriberTag,
+};
 
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    pub attributes: SubscriberAttributes,
+    pub tags: Vec<SubscriberTag>,
+}
+
+impl NewSubscriber {
+    pub fn parse(
+        email: String,
+        name: String,
+        tags: &str,
+        attributes: BTreeMap<String, String>,
+        rules: &SubscriberAttributeRules,
+    ) -> Result<NewSubscriber, String> {
+        Ok(NewSubscriber {
+            email: SubscriberEmail::parse(email)?,
+            name: SubscriberName::parse(name)?,
+            attributes: SubscriberAttributes::parse(attributes, rules)?,
+            tags: SubscriberTag::parse_list(tags)?,
+        })
+    }
 }
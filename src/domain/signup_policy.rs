@@ -0,0 +1,208 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+use super::{NewSubscriber, SubscriberTag};
+
+/// Domains of throwaway mailbox providers, bundled with the application.
+static DISPOSABLE_DOMAINS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    include_str!("disposable_domains.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+});
+
+/// Local parts reaching a team or a system rather than a person.
+const ROLE_LOCAL_PARTS: &[&str] = &[
+    "abuse",
+    "admin",
+    "administrator",
+    "billing",
+    "contact",
+    "help",
+    "hostmaster",
+    "info",
+    "mailer-daemon",
+    "marketing",
+    "no-reply",
+    "noreply",
+    "postmaster",
+    "root",
+    "sales",
+    "security",
+    "support",
+    "webmaster",
+];
+
+/// Tag added to subscribers with a role address when they are only flagged.
+const ROLE_ADDRESS_TAG: &str = "role-address";
+
+/// Which addresses may subscribe through the public form.
+#[derive(Deserialize, Clone, Debug)]
+pub struct SignupPolicy {
+    pub reject_disposable_domains: bool,
+    /// Added to the bundled list of disposable domains.
+    #[serde(default, deserialize_with = "deserialize_domains")]
+    pub disposable_domains: Vec<String>,
+    /// Removed from the bundled list of disposable domains.
+    #[serde(default, deserialize_with = "deserialize_domains")]
+    pub allowed_domains: Vec<String>,
+    pub role_addresses: RoleAddressPolicy,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoleAddressPolicy {
+    Allow,
+    /// Accept them, tagged so they can be reviewed or left out of a segment.
+    Flag,
+    Reject,
+}
+
+impl SignupPolicy {
+    pub fn apply(&self, subscriber: &mut NewSubscriber) -> Result<(), String> {
+        let email = subscriber.email.as_ref();
+        let (local_part, domain) = email
+            .rsplit_once('@')
+            .ok_or_else(|| format!("{email} is not a valid subscriber email"))?;
+
+        if self.reject_disposable_domains && self.is_disposable(domain) {
+            return Err(format!(
+                "Addresses at {domain} are disposable, please subscribe with a permanent address"
+            ));
+        }
+
+        if is_role_address(local_part) {
+            match self.role_addresses {
+                RoleAddressPolicy::Allow => {}
+                RoleAddressPolicy::Flag => {
+                    let tag = SubscriberTag::parse(ROLE_ADDRESS_TAG.to_string())?;
+                    if !subscriber.tags.contains(&tag) {
+                        subscriber.tags.push(tag);
+                    }
+                }
+                RoleAddressPolicy::Reject => {
+                    return Err(format!(
+                        "{email} is a role address, please subscribe with a personal address"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A domain is disposable when it, or a domain it belongs to, is listed.
+    fn is_disposable(&self, domain: &str) -> bool {
+        let matches = |listed: &str| {
+            domain == listed
+                || domain
+                    .strip_suffix(listed)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        };
+        if self.allowed_domains.iter().any(|d| matches(d)) {
+            return false;
+        }
+        DISPOSABLE_DOMAINS.iter().any(|d| matches(d))
+            || self.disposable_domains.iter().any(|d| matches(d))
+    }
+}
+
+/// Configured domains are compared with the ones of subscriber emails, which
+/// are lowercased and converted to punycode by `SubscriberEmail::parse`.
+fn deserialize_domains<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|domain| {
+            idna::domain_to_ascii(domain.trim())
+                .map_err(|_| D::Error::custom(format!("{domain} is not a valid domain")))
+        })
+        .collect()
+}
+
+fn is_role_address(local_part: &str) -> bool {
+    // `postmaster+list@` reaches the same mailbox as `postmaster@`.
+    let mailbox = local_part.split('+').next().unwrap_or(local_part);
+    ROLE_LOCAL_PARTS.contains(&mailbox.to_lowercase().as_str())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use assertor::*;
+    use rstest::rstest;
+
+    use super::{RoleAddressPolicy, SignupPolicy};
+    use crate::domain::{NewSubscriber, SubscriberAttributeRules};
+
+    fn policy(role_addresses: RoleAddressPolicy) -> SignupPolicy {
+        SignupPolicy {
+            reject_disposable_domains: true,
+            disposable_domains: vec!["throwaway.example".into()],
+            allowed_domains: vec!["yopmail.com".into()],
+            role_addresses,
+        }
+    }
+
+    fn subscriber(email: &str) -> NewSubscriber {
+        let rules = SubscriberAttributeRules {
+            max_attributes: 10,
+            max_value_length: 100,
+            known: Default::default(),
+        };
+        NewSubscriber::parse(email.into(), "Ursula".into(), "", BTreeMap::new(), &rules).unwrap()
+    }
+
+    #[rstest]
+    #[case("ursula@mailinator.com")]
+    #[case("ursula@eu.mailinator.com")]
+    #[case("ursula@throwaway.example")]
+    fn disposable_domains_are_rejected(#[case] email: &str) {
+        let result = policy(RoleAddressPolicy::Allow).apply(&mut subscriber(email));
+        assert_that!(result).is_err();
+    }
+
+    #[rstest]
+    #[case("ursula@gmail.com")]
+    #[case("ursula@notmailinator.com")]
+    #[case("ursula@yopmail.com")]
+    fn other_domains_are_accepted(#[case] email: &str) {
+        let result = policy(RoleAddressPolicy::Allow).apply(&mut subscriber(email));
+        assert_that!(result).is_ok();
+    }
+
+    #[test]
+    fn configured_domains_are_normalized() {
+        let policy: SignupPolicy = serde_json::from_value(serde_json::json!({
+            "reject_disposable_domains": true,
+            "disposable_domains": ["Throwaway.Example", "bücher.example"],
+            "allowed_domains": [" Mailinator.COM "],
+            "role_addresses": "allow",
+        }))
+        .unwrap();
+        assert_that!(policy.apply(&mut subscriber("ursula@throwaway.example"))).is_err();
+        assert_that!(policy.apply(&mut subscriber("ursula@Bücher.example"))).is_err();
+        assert_that!(policy.apply(&mut subscriber("ursula@mailinator.com"))).is_ok();
+    }
+
+    #[test]
+    fn role_addresses_can_be_rejected() {
+        let result =
+            policy(RoleAddressPolicy::Reject).apply(&mut subscriber("Postmaster+news@example.com"));
+        assert_that!(result).is_err();
+    }
+
+    #[test]
+    fn role_addresses_can_be_flagged() {
+        let mut subscriber = subscriber("postmaster@example.com");
+        policy(RoleAddressPolicy::Flag)
+            .apply(&mut subscriber)
+            .unwrap();
+        let tags: Vec<&str> = subscriber.tags.iter().map(AsRef::as_ref).collect();
+        assert_that!(tags).contains_exactly(vec!["role-address"]);
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Validation rules applied to the free-form attributes captured on signup.
+#[derive(Deserialize, Clone, Debug)]
+pub struct SubscriberAttributeRules {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_attributes: usize,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_value_length: usize,
+    #[serde(default)]
+    pub known: HashMap<String, AttributeRule>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct AttributeRule {
+    #[serde(default)]
+    pub required: bool,
+    pub max_length: Option<usize>,
+    #[serde(default)]
+    pub allowed_values: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SubscriberAttributes(BTreeMap<String, String>);
+
+impl SubscriberAttributes {
+    pub fn parse(
+        raw: BTreeMap<String, String>,
+        rules: &SubscriberAttributeRules,
+    ) -> Result<SubscriberAttributes, String> {
+        let attributes: BTreeMap<String, String> = raw
+            .into_iter()
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+
+        if attributes.len() > rules.max_attributes {
+            return Err(format!(
+                "A subscriber cannot have more than {} attributes",
+                rules.max_attributes
+            ));
+        }
+
+        for (name, rule) in &rules.known {
+            if rule.required && !attributes.contains_key(name) {
+                return Err(format!("The {name} attribute is required"));
+            }
+        }
+
+        for (name, value) in &attributes {
+            let is_valid_name = !name.is_empty()
+                && name.len() <= 50
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if !is_valid_name {
+                return Err(format!("{name} is not a valid attribute name"));
+            }
+
+            let rule = rules.known.get(name).cloned().unwrap_or_default();
+            let max_length = rule.max_length.unwrap_or(rules.max_value_length);
+            if value.graphemes(true).count() > max_length {
+                return Err(format!(
+                    "The {name} attribute must be at most {max_length} characters"
+                ));
+            }
+            if !rule.allowed_values.is_empty() && !rule.allowed_values.contains(value) {
+                return Err(format!("{value} is not an allowed value for {name}"));
+            }
+        }
+
+        Ok(SubscriberAttributes(attributes))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+impl AsRef<BTreeMap<String, String>> for SubscriberAttributes {
+    fn as_ref(&self) -> &BTreeMap<String, String> {
+        &self.0
+    }
+}
+
+impl From<BTreeMap<String, String>> for SubscriberAttributes {
+    /// Wrap attributes that were already validated, e.g. when loaded from the database.
+    fn from(attributes: BTreeMap<String, String>) -> Self {
+        SubscriberAttributes(attributes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+
+    use assertor::*;
+
+    use super::{AttributeRule, SubscriberAttributeRules, SubscriberAttributes};
+
+    fn rules() -> SubscriberAttributeRules {
+        SubscriberAttributeRules {
+            max_attributes: 3,
+            max_value_length: 10,
+            known: HashMap::from([
+                (
+                    "company".to_string(),
+                    AttributeRule {
+                        max_length: Some(20),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "plan".to_string(),
+                    AttributeRule {
+                        allowed_values: vec!["free".into(), "pro".into()],
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        }
+    }
+
+    fn attributes(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn names_are_normalized_and_empty_values_dropped() {
+        let parsed =
+            SubscriberAttributes::parse(attributes(&[(" Company ", "Acme"), ("x", "")]), &rules())
+                .unwrap();
+        assert_that!(parsed.get("company")).is_equal_to(Some("Acme"));
+        assert_that!(parsed.get("x")).is_none();
+    }
+
+    #[test]
+    fn known_attributes_use_their_own_length_limit() {
+        let company = "a".repeat(20);
+        let parsed = SubscriberAttributes::parse(attributes(&[("company", &company)]), &rules());
+        assert_that!(parsed).is_ok();
+
+        let other = SubscriberAttributes::parse(attributes(&[("source", &company)]), &rules());
+        assert_that!(other).is_err();
+    }
+
+    #[test]
+    fn values_outside_the_allowed_list_are_rejected() {
+        let parsed = SubscriberAttributes::parse(attributes(&[("plan", "gold")]), &rules());
+        assert_that!(parsed).is_err();
+    }
+
+    #[test]
+    fn missing_required_attributes_are_rejected() {
+        let mut rules = rules();
+        rules.known.get_mut("company").unwrap().required = true;
+        let parsed = SubscriberAttributes::parse(attributes(&[("plan", "pro")]), &rules);
+        assert_that!(parsed).is_err();
+    }
+
+    #[test]
+    fn too_many_attributes_are_rejected() {
+        let parsed = SubscriberAttributes::parse(
+            attributes(&[("a", "1"), ("b", "2"), ("c", "3"), ("d", "4")]),
+            &rules(),
+        );
+        assert_that!(parsed).is_err();
+    }
+
+    #[test]
+    fn invalid_attribute_names_are_rejected() {
+        let parsed = SubscriberAttributes::parse(attributes(&[("first-name", "x")]), &rules());
+        assert_that!(parsed).is_err();
+    }
+}
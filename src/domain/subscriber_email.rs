@@ -4,11 +4,19 @@ use validator::ValidateEmail;
 pub struct SubscriberEmail(String);
 
 impl SubscriberEmail {
+    /// Surrounding whitespace is dropped and the domain is lowercased, with
+    /// internationalized domains converted to punycode. The local part is kept
+    /// as typed, addresses are compared ignoring its case in the database.
     pub fn parse(s: String) -> Result<SubscriberEmail, String> {
-        if s.validate_email() {
-            Ok(SubscriberEmail(s))
+        let invalid = || format!("{s} is not a valid subscriber email");
+        let trimmed = s.trim();
+        let (local_part, domain) = trimmed.rsplit_once('@').ok_or_else(invalid)?;
+        let domain = idna::domain_to_ascii(domain).map_err(|_| invalid())?;
+        let email = format!("{local_part}@{domain}");
+        if email.validate_email() {
+            Ok(SubscriberEmail(email))
         } else {
-            Err(format!("{s} is not a valid subscriber email"))
+            Err(invalid())
         }
     }
 }
@@ -60,6 +68,18 @@ mod test {
         assertor::assert_that!(SubscriberEmail::parse(email)).is_err();
     }
 
+    #[test]
+    fn whitespace_is_trimmed_and_the_domain_lowercased() {
+        let email = SubscriberEmail::parse("  Ursula@Example.COM \n".to_string()).unwrap();
+        assert_that!(email.as_ref()).is_equal_to("Ursula@example.com");
+    }
+
+    #[test]
+    fn internationalized_domains_are_converted_to_punycode() {
+        let email = SubscriberEmail::parse("ursula@Bücher.example".to_string()).unwrap();
+        assert_that!(email.as_ref()).is_equal_to("ursula@xn--bcher-kva.example");
+    }
+
     #[quickcheck]
     fn valid_emails_are_parsed_successfully(valid_email: ValitEmailFixture) -> bool {
         SubscriberEmail::parse(valid_email.0).is_ok()
@@ -0,0 +1,64 @@
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SubscriberTag(String);
+
+impl SubscriberTag {
+    pub fn parse(s: String) -> Result<SubscriberTag, String> {
+        let tag = s.trim().to_lowercase();
+        let is_valid = !tag.is_empty()
+            && tag.len() <= 50
+            && tag
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+        if is_valid {
+            Ok(SubscriberTag(tag))
+        } else {
+            Err(format!("{s} is not a valid tag"))
+        }
+    }
+
+    /// Parse a comma separated list of tags, ignoring duplicates and blank entries.
+    pub fn parse_list(s: &str) -> Result<Vec<SubscriberTag>, String> {
+        let mut tags = s
+            .split(',')
+            .filter(|tag| !tag.trim().is_empty())
+            .map(|tag| SubscriberTag::parse(tag.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        tags.sort();
+        tags.dedup();
+        Ok(tags)
+    }
+}
+
+impl AsRef<str> for SubscriberTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+    use rstest::rstest;
+
+    use super::SubscriberTag;
+
+    #[test]
+    fn tags_are_lowercased_and_trimmed() {
+        let tag = SubscriberTag::parse(" Beta ".to_string()).unwrap();
+        assert_that!(tag.as_ref()).is_equal_to("beta");
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("beta tester")]
+    #[case("<script>")]
+    fn invalid_tags_are_rejected(#[case] tag: &str) {
+        assertor::assert_that!(SubscriberTag::parse(tag.to_string())).is_err();
+    }
+
+    #[test]
+    fn a_list_is_deduplicated() {
+        let tags = SubscriberTag::parse_list("beta, Beta,,early-access").unwrap();
+        assert_that!(tags.len()).is_equal_to(2);
+    }
+}
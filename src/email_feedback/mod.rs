@@ -0,0 +1,68 @@
+mod postmark;
+
+/// What an email provider reported back about a message we sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailFeedback {
+    pub email: String,
+    pub kind: FeedbackKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackKind {
+    /// The address does not exist or permanently refuses our mail.
+    HardBounce,
+    /// A temporary failure, the address stays subscribed.
+    SoftBounce,
+    /// The recipient marked our mail as spam.
+    Complaint,
+}
+
+impl FeedbackKind {
+    /// The outcome written to the delivery log.
+    pub fn outcome(&self) -> &'static str {
+        match self {
+            FeedbackKind::HardBounce => "hard_bounce",
+            FeedbackKind::SoftBounce => "soft_bounce",
+            FeedbackKind::Complaint => "complaint",
+        }
+    }
+
+    /// The status the subscriber is moved to, if the feedback stops deliveries.
+    pub fn suppressed_status(&self) -> Option<&'static str> {
+        match self {
+            FeedbackKind::HardBounce => Some("bounced"),
+            FeedbackKind::SoftBounce => None,
+            FeedbackKind::Complaint => Some("complained"),
+        }
+    }
+}
+
+/// An email provider able to call our webhooks.
+#[derive(Debug, Clone, Copy)]
+pub enum Provider {
+    Postmark,
+}
+
+impl Provider {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "postmark" => Some(Provider::Postmark),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Postmark => "postmark",
+        }
+    }
+
+    /// Parse a webhook payload. Events we have no use for, such as deliveries
+    /// or opens, parse to `None`.
+    pub fn parse(&self, body: &[u8]) -> Result<Option<EmailFeedback>, serde_json::Error> {
+        match self {
+            Provider::Postmark => postmark::parse(body),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use serde::Deserialize;
+
+use crate::email_feedback::{EmailFeedback, FeedbackKind};
+
+/// The fields we use from Postmark bounce and spam complaint webhooks.
+/// https://postmarkapp.com/developer/webhooks/bounce-webhook
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Payload {
+    record_type: String,
+    #[serde(rename = "Type", default)]
+    bounce_type: String,
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    description: String,
+}
+
+pub fn parse(body: &[u8]) -> Result<Option<EmailFeedback>, serde_json::Error> {
+    let payload: Payload = serde_json::from_slice(body)?;
+    let kind = match (payload.record_type.as_str(), payload.bounce_type.as_str()) {
+        ("SpamComplaint", _) => FeedbackKind::Complaint,
+        ("Bounce", "HardBounce" | "BadEmailAddress" | "ManuallyDeactivated") => {
+            FeedbackKind::HardBounce
+        }
+        ("Bounce", "SpamComplaint") => FeedbackKind::Complaint,
+        ("Bounce", _) => FeedbackKind::SoftBounce,
+        _ => return Ok(None),
+    };
+    Ok(Some(EmailFeedback {
+        email: payload.email,
+        kind,
+        detail: format!("{}: {}", payload.bounce_type, payload.description),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+
+    use super::parse;
+    use crate::email_feedback::FeedbackKind;
+
+    fn kind_of(body: serde_json::Value) -> Option<FeedbackKind> {
+        parse(body.to_string().as_bytes())
+            .unwrap()
+            .map(|feedback| feedback.kind)
+    }
+
+    #[test]
+    fn hard_bounces_are_recognised() {
+        let feedback = parse(
+            serde_json::json!({
+                "RecordType": "Bounce",
+                "Type": "HardBounce",
+                "TypeCode": 1,
+                "Email": "ursula@example.com",
+                "Description": "The server was unable to deliver your message",
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_that!(feedback.kind).is_equal_to(FeedbackKind::HardBounce);
+        assert_that!(feedback.email).is_equal_to("ursula@example.com".to_string());
+        assert_that!(feedback.detail).contains("HardBounce");
+    }
+
+    #[test]
+    fn temporary_failures_are_soft_bounces() {
+        let kind = kind_of(serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "Transient",
+            "Email": "ursula@example.com",
+        }));
+        assert_that!(kind).is_equal_to(Some(FeedbackKind::SoftBounce));
+    }
+
+    #[test]
+    fn spam_complaints_are_recognised() {
+        let kind = kind_of(serde_json::json!({
+            "RecordType": "SpamComplaint",
+            "Type": "SpamComplaint",
+            "Email": "ursula@example.com",
+        }));
+        assert_that!(kind).is_equal_to(Some(FeedbackKind::Complaint));
+    }
+
+    #[test]
+    fn other_events_are_ignored() {
+        let kind = kind_of(serde_json::json!({
+            "RecordType": "Delivery",
+            "Recipient": "ursula@example.com",
+        }));
+        assert_that!(kind).is_none();
+    }
+}
@@ -0,0 +1,210 @@
+//! Rendering of the pages: values interpolated with [`html!`](crate::html!)
+//! are escaped, unless they are [`Markup`] already.
+
+use std::fmt::{self, Display};
+
+use axum::response::{Html, IntoResponse, Response};
+use htmlescape::encode_minimal;
+
+/// A fragment of HTML, safe to put in a page as is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Markup(String);
+
+impl Markup {
+    /// Trust `html` without escaping it, only for markup written in the code.
+    pub fn raw(html: impl Into<String>) -> Self {
+        Markup(html.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Display for Markup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromIterator<Markup> for Markup {
+    fn from_iter<I: IntoIterator<Item = Markup>>(iter: I) -> Self {
+        Markup(iter.into_iter().map(|markup| markup.0).collect())
+    }
+}
+
+impl IntoResponse for Markup {
+    fn into_response(self) -> Response {
+        Html(self.0).into_response()
+    }
+}
+
+/// Text escaped to be shown inside an element or a quoted attribute.
+pub fn escape(text: &str) -> Markup {
+    Markup(encode_minimal(text))
+}
+
+/// Like `format!`, but every argument is escaped unless it is [`Markup`],
+/// and so is the page it returns.
+///
+/// A bare identifier argument is a named argument of the same name. Values
+/// must be passed as arguments, a template capturing one from the scope does
+/// not compile since it would not be escaped:
+///
+/// ```compile_fail
+/// let name = "<script>";
+/// zero2prod::html!("<p>{name}</p>");
+/// ```
+#[macro_export]
+macro_rules! html {
+    ($template:literal $(,)?) => {
+        $crate::html!(@args $template [] [])
+    };
+    ($template:literal, $($args:tt)+) => {
+        $crate::html!(@args $template [] [] $($args)+)
+    };
+    (@args $template:literal [$($out:tt)*] [$($names:ident)*] $name:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $crate::html!(@args $template [$($out)* $name = $crate::html!(@render $value),] [$($names)* $name] $($($rest)*)?)
+    };
+    (@args $template:literal [$($out:tt)*] [$($names:ident)*] $name:ident $(, $($rest:tt)*)?) => {
+        $crate::html!(@args $template [$($out)* $name = $crate::html!(@render $name),] [$($names)* $name] $($($rest)*)?)
+    };
+    (@args $template:literal [$($out:tt)*] [$($names:ident)*] $value:expr $(, $($rest:tt)*)?) => {
+        $crate::html!(@args $template [$($out)* $crate::html!(@render $value),] [$($names)*] $($($rest)*)?)
+    };
+    (@args $template:literal [$($out:tt)*] [$($names:ident)*]) => {{
+        const {
+            assert!(
+                $crate::html::captures_nothing($template, &[$(stringify!($names)),*]),
+                "html! templates must only name the arguments given, captured values are not escaped"
+            )
+        };
+        #[allow(unused_imports)]
+        use $crate::html::{RenderMarkup as _, RenderText as _};
+        $crate::html::Markup::raw(format!($template, $($out)*))
+    }};
+    (@render $value:expr) => {
+        (&$crate::html::Interpolated(&$value)).render()
+    };
+}
+
+/// Whether every named placeholder of `template` is one of `names`, that is
+/// `format!` captures nothing from the scope.
+#[doc(hidden)]
+pub const fn captures_nothing(template: &str, names: &[&str]) -> bool {
+    let template = template.as_bytes();
+    let mut i = 0;
+    while i < template.len() {
+        if template[i] != b'{' {
+            i += 1;
+            continue;
+        }
+        if i + 1 < template.len() && template[i + 1] == b'{' {
+            i += 2;
+            continue;
+        }
+        let start = i + 1;
+        let mut end = start;
+        while end < template.len() && template[end] != b'}' && template[end] != b':' {
+            end += 1;
+        }
+        let positional = start == end || template[start].is_ascii_digit();
+        if !positional && !is_one_of(template, start, end, names) {
+            return false;
+        }
+        i = end;
+    }
+    true
+}
+
+const fn is_one_of(template: &[u8], start: usize, end: usize, names: &[&str]) -> bool {
+    let mut n = 0;
+    while n < names.len() {
+        let name = names[n].as_bytes();
+        if name.len() == end - start {
+            let mut k = 0;
+            while k < name.len() && name[k] == template[start + k] {
+                k += 1;
+            }
+            if k == name.len() {
+                return true;
+            }
+        }
+        n += 1;
+    }
+    false
+}
+
+// Which of the traits below renders a value is picked by method resolution,
+// [`Markup`] as is before anything else that can be displayed, escaped.
+
+#[doc(hidden)]
+pub struct Interpolated<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait RenderMarkup {
+    fn render(&self) -> String;
+}
+
+impl RenderMarkup for Interpolated<'_, Markup> {
+    fn render(&self) -> String {
+        self.0.0.clone()
+    }
+}
+
+#[doc(hidden)]
+pub trait RenderText {
+    fn render(&self) -> String;
+}
+
+impl<T: Display + ?Sized> RenderText for &Interpolated<'_, T> {
+    fn render(&self) -> String {
+        encode_minimal(&self.0.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Markup, captures_nothing, escape};
+
+    #[test]
+    fn arguments_are_escaped() {
+        let name = "<script>alert(\"x\")</script>";
+        let page = crate::html!("<p>{name}</p>", name);
+        assert_eq!(
+            page.as_str(),
+            "<p>&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt;</p>"
+        );
+    }
+
+    #[test]
+    fn markup_is_kept_as_is() {
+        let field = Markup::raw(r#"<input name="a" />"#);
+        let page = crate::html!("<form>{field}{}</form>", escape("<b>"), field);
+        assert_eq!(page.as_str(), r#"<form><input name="a" />&lt;b&gt;</form>"#);
+    }
+
+    #[test]
+    fn attribute_values_cannot_be_closed() {
+        let page = crate::html!(r#"<input value="{value}" />"#, value = "\" onfocus='x'");
+        assert_eq!(
+            page.as_str(),
+            r#"<input value="&quot; onfocus=&#x27;x&#x27;" />"#
+        );
+    }
+
+    #[test]
+    fn only_the_arguments_given_can_be_named() {
+        assert!(captures_nothing(
+            "<p>{} {0} {name:>4} {{scope}}</p>",
+            &["name"]
+        ));
+        assert!(!captures_nothing("<p>{name}</p>", &[]));
+        assert!(!captures_nothing("<p>{name}{scope}</p>", &["name"]));
+        assert!(!captures_nothing("<p>{names}</p>", &["name"]));
+    }
+}
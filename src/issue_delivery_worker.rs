@@ -1,11 +1,11 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
 use anyhow::Context;
-use sqlx::PgPool;
+use sqlx::{PgPool, types::Json};
 
 use crate::{
     configuration::Settings, domain::SubscriberEmail, email_client::EmailClient,
-    startup::get_connection_pool, utils::Transaction,
+    personalization::MergeFields, startup::get_connection_pool, utils::Transaction,
 };
 
 pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
@@ -47,6 +47,12 @@ struct NewsletterIssue {
     html_content: String,
 }
 
+#[derive(Debug)]
+enum DeliveryOutcome {
+    Delivered,
+    Failed(String),
+}
+
 #[derive(Debug)]
 pub enum ExecutionOutCome {
     TaskCompleted,
@@ -73,22 +79,27 @@ pub async fn try_execute_task(
         };
 
         let task = get_newsletter_issue(pool, newsletter_issue_id).await?;
-        if let Err(err) = email_client
+        let merge_fields = get_merge_fields(pool, &subscriber_email).await?;
+        let outcome = match email_client
             .send_email(
                 &subscriber_email,
-                &task.title,
-                &task.html_content,
-                &task.text_content,
+                &merge_fields.render(&task.title, false),
+                &merge_fields.render(&task.html_content, true),
+                &merge_fields.render(&task.text_content, false),
             )
             .await
         {
-            tracing::error!(
-                error.cause_chain = ?err,
-                "Failed to delivery issue to a confirmed subscriber. Skipping."
-            )
-        }
+            Ok(()) => DeliveryOutcome::Delivered,
+            Err(err) => {
+                tracing::error!(
+                    error.cause_chain = ?err,
+                    "Failed to delivery issue to a confirmed subscriber. Skipping."
+                );
+                DeliveryOutcome::Failed(err.to_string())
+            }
+        };
 
-        delete_task(txn, task).await?;
+        delete_task(txn, task, &subscriber_email, outcome).await?;
         Ok(ExecutionOutCome::TaskCompleted)
     } else {
         Ok(ExecutionOutCome::EmptyQueue)
@@ -118,13 +129,42 @@ LIMIT 1
 }
 
 #[tracing::instrument(name = "Delete task", skip(txn), err(Debug))]
-async fn delete_task(mut txn: Transaction, task: NewsletterIssue) -> Result<(), anyhow::Error> {
+async fn delete_task(
+    mut txn: Transaction,
+    task: NewsletterIssue,
+    subscriber_email: &SubscriberEmail,
+    outcome: DeliveryOutcome,
+) -> Result<(), anyhow::Error> {
     sqlx::query!(
         r#"
 DELETE FROM issue_delivery_queue
-WHERE newsletter_issue_id = $1
+WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        subscriber_email.as_ref(),
+    )
+    .execute(&mut *txn)
+    .await?;
+
+    let (outcome, detail) = match outcome {
+        DeliveryOutcome::Delivered => ("delivered", None),
+        DeliveryOutcome::Failed(detail) => ("failed", Some(detail)),
+    };
+    sqlx::query!(
+        r#"
+INSERT INTO issue_delivery_log (
+    newsletter_issue_id,
+    subscriber_email,
+    outcome,
+    detail,
+    recorded_at
+)
+VALUES ($1, $2, $3, $4, now())
         "#,
-        task.newsletter_issue_id
+        task.newsletter_issue_id,
+        subscriber_email.as_ref(),
+        outcome,
+        detail,
     )
     .execute(&mut *txn)
     .await?;
@@ -155,3 +195,31 @@ WHERE newsletter_issue_id = $1
         html_content: row.html_content,
     })
 }
+
+#[tracing::instrument(name = "Get merge fields", skip(pool), err(Debug))]
+async fn get_merge_fields(
+    pool: &PgPool,
+    subscriber_email: &SubscriberEmail,
+) -> Result<MergeFields, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+SELECT name, attributes AS "attributes: Json<BTreeMap<String, String>>"
+FROM subscriptions
+WHERE email = $1
+        "#,
+        subscriber_email.as_ref(),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(match row {
+        Some(row) => MergeFields {
+            name: row.name,
+            attributes: row.attributes.0.into(),
+        },
+        None => MergeFields {
+            name: String::new(),
+            attributes: Default::default(),
+        },
+    })
+}
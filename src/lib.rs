@@ -1,11 +1,24 @@
+pub mod anti_bot;
 pub mod authentication;
 pub mod configuration;
+pub mod csrf;
 pub mod domain;
 pub mod email_client;
+pub mod email_feedback;
+pub mod html;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod personalization;
+pub mod rate_limit;
+pub mod request_metadata;
 pub mod routes;
+pub mod segment;
+pub mod session_index;
 pub mod session_state;
+pub mod signing;
 pub mod startup;
+pub mod subscriber_data;
+pub mod subscription_events;
+pub mod suppression;
 pub mod telemetry;
 pub mod utils;
@@ -0,0 +1,75 @@
+use crate::domain::SubscriberAttributes;
+
+/// Per-recipient values substituted into `{{name}}` and `{{attributes.<name>}}`
+/// placeholders of an issue. Unknown attributes render as an empty string, any
+/// other placeholder is left untouched.
+pub struct MergeFields {
+    pub name: String,
+    pub attributes: SubscriberAttributes,
+}
+
+impl MergeFields {
+    pub fn render(&self, template: &str, escape_html: bool) -> String {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let after_open = &rest[start + 2..];
+            let Some(end) = after_open.find("}}") else {
+                rest = &rest[start..];
+                break;
+            };
+            let placeholder = &rest[start..start + 2 + end + 2];
+            match self.lookup(after_open[..end].trim()) {
+                Some(value) if escape_html => output.push_str(&htmlescape::encode_minimal(value)),
+                Some(value) => output.push_str(value),
+                None => output.push_str(placeholder),
+            }
+            rest = &after_open[end + 2..];
+        }
+        output.push_str(rest);
+        output
+    }
+
+    fn lookup(&self, key: &str) -> Option<&str> {
+        if key == "name" {
+            return Some(&self.name);
+        }
+        key.strip_prefix("attributes.")
+            .map(|name| self.attributes.get(name).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use assertor::*;
+
+    use super::MergeFields;
+
+    fn merge_fields() -> MergeFields {
+        MergeFields {
+            name: "Ursula".into(),
+            attributes: BTreeMap::from([("company".to_string(), "<Acme>".to_string())]).into(),
+        }
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        let rendered = merge_fields().render("Hi {{ name }} from {{attributes.company}}!", false);
+        assert_that!(rendered).is_equal_to("Hi Ursula from <Acme>!".to_string());
+    }
+
+    #[test]
+    fn values_are_escaped_in_html() {
+        let rendered = merge_fields().render("<p>{{attributes.company}}</p>", true);
+        assert_that!(rendered).is_equal_to("<p>&lt;Acme&gt;</p>".to_string());
+    }
+
+    #[test]
+    fn missing_attributes_render_empty_and_unknown_placeholders_are_kept() {
+        let rendered = merge_fields().render("[{{attributes.plan}}] {{unknown}} {{", false);
+        assert_that!(rendered).is_equal_to("[] {{unknown}} {{".to_string());
+    }
+}
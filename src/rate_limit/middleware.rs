@@ -0,0 +1,177 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    body::{Body, to_bytes},
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::{RateLimitRule, RateLimiter};
+use crate::request_metadata::ClientIp;
+
+/// Largest form we read to find the target of a request, the same as the
+/// default limit of the `Form` extractor.
+const MAX_FORM_SIZE: usize = 2 * 1024 * 1024;
+
+/// The rate limit of one route, built by [`RateLimiter::policy`].
+#[derive(Clone)]
+pub struct RateLimit {
+    limiter: Arc<RateLimiter>,
+    name: &'static str,
+    rule: RateLimitRule,
+    target_field: Option<&'static str>,
+}
+
+impl RateLimit {
+    pub(super) fn new(limiter: Arc<RateLimiter>, name: &'static str, rule: RateLimitRule) -> Self {
+        RateLimit {
+            limiter,
+            name,
+            rule,
+            target_field: None,
+        }
+    }
+
+    /// Also limit requests about the same value of this form field, whatever
+    /// address they come from.
+    pub fn keyed_by_field(mut self, field: &'static str) -> Self {
+        self.target_field = Some(field);
+        self
+    }
+}
+
+pub async fn rate_limit(
+    State(limit): State<RateLimit>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let client_ip = client_ip(&request, &limit.limiter.settings().trusted_proxies);
+    if let Some(ip) = client_ip {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+    let client_ip = client_ip
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".into());
+    if let Err(wait) = limit
+        .limiter
+        .take(&format!("{}:ip:{client_ip}", limit.name), limit.rule.per_ip)
+        .await
+    {
+        return too_many_requests(wait);
+    }
+
+    let request = match (limit.target_field, limit.rule.per_target) {
+        (Some(field), Some(quota)) => {
+            let (parts, body) = request.into_parts();
+            let Ok(body) = to_bytes(body, MAX_FORM_SIZE).await else {
+                return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+            };
+            let target = form_urlencoded::parse(&body)
+                .find(|(key, _)| key == field)
+                .map(|(_, value)| value.trim().to_lowercase());
+            if let Some(target) = target.filter(|target| !target.is_empty())
+                && let Err(wait) = limit
+                    .limiter
+                    .take(&format!("{}:target:{target}", limit.name), quota)
+                    .await
+            {
+                return too_many_requests(wait);
+            }
+            Request::from_parts(parts, Body::from(body))
+        }
+        _ => request,
+    };
+    next.run(request).await
+}
+
+fn too_many_requests(wait_milliseconds: i64) -> Response {
+    let retry_after = (wait_milliseconds + 999) / 1000;
+    tracing::info!(retry_after, "Rate limited a request");
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        "Too many requests, please try again later.",
+    )
+        .into_response()
+}
+
+/// The peer address, or the address a trusted proxy says it forwarded the
+/// request for.
+fn client_ip(request: &Request, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip())?;
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+    Some(forwarded_for(request.headers(), trusted_proxies).unwrap_or(peer))
+}
+
+/// Proxies append the address they received a request from, so the client is
+/// the rightmost address not belonging to one of our proxies. Anything left of
+/// it was supplied by the client and cannot be trusted, nor can an entry our
+/// proxies would not have written.
+fn forwarded_for(headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let entries: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    for entry in entries.into_iter().rev() {
+        let address: IpAddr = entry.trim().parse().ok()?;
+        if !trusted_proxies.contains(&address) {
+            return Some(address);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+
+    use assertor::*;
+    use axum::http::HeaderMap;
+
+    use super::forwarded_for;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn the_rightmost_untrusted_address_is_the_client() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "1.1.1.1, 2.2.2.2, 10.0.0.2".parse().unwrap(),
+        );
+        let trusted_proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+        assert_that!(forwarded_for(&headers, &trusted_proxies)).is_equal_to(Some(ip("2.2.2.2")));
+    }
+
+    #[test]
+    fn a_malformed_header_is_ignored() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.1.1.1, nonsense".parse().unwrap());
+        assert_that!(forwarded_for(&headers, &[ip("10.0.0.1")])).is_none();
+    }
+
+    #[test]
+    fn a_malformed_entry_left_of_the_client_does_not_matter() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "nonsense, 2.2.2.2, 10.0.0.2".parse().unwrap(),
+        );
+        let trusted_proxies = [ip("10.0.0.2")];
+        assert_that!(forwarded_for(&headers, &trusted_proxies)).is_equal_to(Some(ip("2.2.2.2")));
+    }
+}
@@ -0,0 +1,106 @@
+mod middleware;
+mod store;
+
+use std::{net::IpAddr, sync::Arc};
+
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+pub use middleware::{RateLimit, rate_limit};
+pub use store::RateLimiter;
+
+#[derive(Deserialize, Clone)]
+pub struct RateLimitSettings {
+    /// Prepended to every Redis key, so deployments can share a Redis.
+    pub key_prefix: String,
+    /// Peers allowed to tell us the client address in `X-Forwarded-For`.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+    pub subscriptions: RateLimitRule,
+    pub confirm: RateLimitRule,
+    pub login: RateLimitRule,
+    pub password_reset: RateLimitRule,
+    pub data_request: RateLimitRule,
+}
+
+/// The quotas of a rate limited route.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct RateLimitRule {
+    pub per_ip: Quota,
+    /// Applies to the email address or username a request is about, when the
+    /// route is keyed by one.
+    #[serde(default)]
+    pub per_target: Option<Quota>,
+}
+
+/// A token bucket holding `burst` tokens, refilled at `per_minute` tokens a
+/// minute.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Quota {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub burst: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub per_minute: u32,
+}
+
+impl Quota {
+    /// Milliseconds it takes to refill one token.
+    fn emission_interval(&self) -> i64 {
+        60_000 / i64::from(self.per_minute.max(1))
+    }
+
+    /// The bucket is stored as its "theoretical arrival time" (GCRA): the
+    /// instant it will be full again. Returns the new arrival time to store if
+    /// a token is available, or how many milliseconds to wait for one.
+    fn take(&self, arrival_time: Option<i64>, now: i64) -> Result<i64, i64> {
+        let interval = self.emission_interval();
+        let new_arrival_time = arrival_time.unwrap_or(now).max(now) + interval;
+        let allowed_at = new_arrival_time - i64::from(self.burst) * interval;
+        if allowed_at > now {
+            Err(allowed_at - now)
+        } else {
+            Ok(new_arrival_time)
+        }
+    }
+}
+
+impl RateLimiter {
+    /// The rate limit of a route, to be layered with [`rate_limit`].
+    pub fn policy(self: &Arc<Self>, name: &'static str, rule: RateLimitRule) -> RateLimit {
+        RateLimit::new(self.clone(), name, rule)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+
+    use super::Quota;
+
+    #[test]
+    fn a_full_bucket_allows_a_burst_then_refills() {
+        let quota = Quota {
+            burst: 3,
+            per_minute: 60,
+        };
+        let now = 1_000_000;
+        let mut arrival_time = None;
+        for _ in 0..3 {
+            arrival_time = Some(quota.take(arrival_time, now).unwrap());
+        }
+        assert_that!(quota.take(arrival_time, now)).is_equal_to(Err(1_000));
+        assert_that!(quota.take(arrival_time, now + 1_000)).is_ok();
+    }
+
+    #[test]
+    fn an_idle_bucket_does_not_grow_past_its_burst() {
+        let quota = Quota {
+            burst: 2,
+            per_minute: 60,
+        };
+        let now = 1_000_000;
+        let arrival_time = quota.take(Some(0), now).unwrap();
+        let arrival_time = quota.take(Some(arrival_time), now).unwrap();
+        assert_that!(quota.take(Some(arrival_time), now)).is_err();
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use fred::prelude::{LuaInterface, Pool};
+
+use super::{Quota, RateLimitSettings};
+
+/// [`Quota::take`] run atomically inside Redis.
+const TAKE_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local interval = tonumber(ARGV[2])
+local burst = tonumber(ARGV[3])
+local arrival_time = tonumber(redis.call('GET', KEYS[1]) or now)
+if arrival_time < now then arrival_time = now end
+local new_arrival_time = arrival_time + interval
+local allowed_at = new_arrival_time - burst * interval
+if allowed_at > now then return allowed_at - now end
+redis.call('SET', KEYS[1], new_arrival_time, 'PX', new_arrival_time - now)
+return 0
+"#;
+
+/// Entries past this count trigger a sweep of the buckets that are full again.
+const MEMORY_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Keeps the token buckets in Redis, so every instance sees the same counts,
+/// and in memory while Redis cannot be reached.
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    redis: Option<Pool>,
+    memory: Mutex<HashMap<String, i64>>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings, redis: Option<Pool>) -> Arc<Self> {
+        Arc::new(RateLimiter {
+            settings,
+            redis,
+            memory: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn settings(&self) -> &RateLimitSettings {
+        &self.settings
+    }
+
+    /// Take a token from the bucket at `key`, returns how many milliseconds to
+    /// wait when it is empty.
+    pub(super) async fn take(&self, key: &str, quota: Quota) -> Result<(), i64> {
+        let key = format!("{}:{key}", self.settings.key_prefix);
+        let now = Utc::now().timestamp_millis();
+        if let Some(redis) = &self.redis {
+            let outcome: Result<i64, _> = redis
+                .eval(
+                    TAKE_SCRIPT,
+                    vec![key.as_str()],
+                    vec![now, quota.emission_interval(), i64::from(quota.burst)],
+                )
+                .await;
+            match outcome {
+                Ok(0) => return Ok(()),
+                Ok(wait) => return Err(wait),
+                Err(e) => {
+                    tracing::warn!(error.message = %e, "Rate limiting in memory, Redis failed");
+                }
+            }
+        }
+        self.take_in_memory(key, quota, now)
+    }
+
+    fn take_in_memory(&self, key: String, quota: Quota, now: i64) -> Result<(), i64> {
+        let mut buckets = self.memory.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > MEMORY_SWEEP_THRESHOLD {
+            buckets.retain(|_, arrival_time| *arrival_time > now);
+        }
+        let arrival_time = quota.take(buckets.get(&key).copied(), now)?;
+        buckets.insert(key, arrival_time);
+        Ok(())
+    }
+}
@@ -1,11 +1,30 @@
-use axum::{Extension, response::Html};
+use axum::Extension;
 
-use crate::authentication::CurrentUser;
+use crate::{
+    authentication::{CurrentUser, Role},
+    csrf::CsrfToken,
+    html,
+    html::Markup,
+};
 
 #[axum::debug_handler]
-pub async fn admin_dashboard(Extension(current_user): Extension<CurrentUser>) -> Html<String> {
-    let username = current_user.username;
-    Html(format!(
+pub async fn admin_dashboard(
+    Extension(current_user): Extension<CurrentUser>,
+    csrf_token: CsrfToken,
+) -> Markup {
+    let owner_actions = if current_user.role == Role::Owner {
+        Markup::raw(
+            r#"<li><a href="/admin/users">Manage users</a></li>
+      <li><a href="/admin/lockouts">Review login lockouts</a></li>
+      <li>
+        Export subscribers as <a href="/admin/subscribers/export?format=csv">CSV</a>
+        or <a href="/admin/subscribers/export?format=json">JSON</a>
+      </li>"#,
+        )
+    } else {
+        Markup::default()
+    };
+    html!(
         r#"
 <!doctype html>
 <html lang="en">
@@ -15,18 +34,30 @@ pub async fn admin_dashboard(Extension(current_user): Extension<CurrentUser>) ->
   </head>
   <body>
     <p> Welcome {username}!</p>
+    <p>You are signed in as {role}.</p>
     <p>Available actions:</p>
     <ol>
       <li><a href="/admin/newsletters">Publish newsletters</a></li>
+      <li><a href="/admin/segments">Manage segments</a></li>
+      <li><a href="/admin/subscribers">Browse subscribers</a></li>
+      <li><a href="/admin/subscribers/import">Import subscribers</a></li>
+      <li><a href="/admin/suppressions">Manage the suppression list</a></li>
+      {owner_actions}
       <li><a href="/admin/password">Change password</a></li>
+      <li><a href="/admin/security">Two-factor authentication</a></li>
       <li>
         <form name="logoutForm" action="/admin/logout" method="post">
+          {csrf_field}
           <input type="submit" value="Logout">
         </form>
       </li>
     </ol>
   </body>
 </html>
-"#
-    ))
+"#,
+        username = current_user.username,
+        role = current_user.role.as_str(),
+        owner_actions,
+        csrf_field = csrf_token.field(),
+    )
 }
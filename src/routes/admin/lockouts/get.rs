@@ -0,0 +1,68 @@
+use axum::extract::State;
+use axum_messages::Messages;
+use chrono::Utc;
+
+use crate::{
+    authentication::list_login_failures,
+    csrf::CsrfToken,
+    html,
+    html::Markup,
+    startup::AppState,
+    utils::{AppError, get_all_messages},
+};
+
+#[axum::debug_handler]
+pub async fn lockouts_form(
+    State(state): State<AppState>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Result<Markup, AppError> {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+
+    let now = Utc::now();
+    let rows = list_login_failures(&state.db_pool)
+        .await?
+        .iter()
+        .map(|f| {
+            let locked_until = match f.locked_until {
+                Some(locked_until) if locked_until > now => {
+                    locked_until.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+                }
+                _ => "-".into(),
+            };
+            html!(
+                r#"<tr><td>{scope}</td><td>{key}</td><td>{}</td><td>{}</td><td>{locked_until}</td><td><form action="/admin/lockouts/unlock" method="post">{csrf_field}<input type="hidden" name="scope" value="{scope}" /><input type="hidden" name="key" value="{key}" /><button type="submit">Unlock</button></form></td></tr>"#,
+                f.failed_attempts,
+                f.last_failed_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                scope = f.scope,
+                key = f.key,
+                locked_until,
+                csrf_field,
+            )
+        })
+        .collect::<Markup>();
+
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Login lockouts</title>
+  </head>
+  <body>
+    {message}
+    <p>Failed logins are counted per username and per client address.</p>
+    <table>
+      <tr><th>Scope</th><th>Username or address</th><th>Failed attempts</th><th>Last failure</th><th>Locked until</th><th></th></tr>
+      {rows}
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        rows,
+    ))
+}
@@ -0,0 +1,31 @@
+use axum::{Form, extract::State, response::Redirect};
+use axum_messages::Messages;
+use serde::Deserialize;
+
+use crate::{
+    authentication::{LockoutScope, unlock_login},
+    startup::AppState,
+    utils::{AppError, e400, e404},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct FormData {
+    scope: String,
+    key: String,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Unlock login", skip(state, messages))]
+pub async fn unlock_lockout(
+    State(state): State<AppState>,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Result<Redirect, AppError> {
+    let scope = LockoutScope::parse(&form.scope)
+        .ok_or_else(|| e400(anyhow::anyhow!("Unknown lockout scope")))?;
+    if !unlock_login(&state.db_pool, scope, &form.key).await? {
+        return Err(e404(anyhow::anyhow!("Nothing to unlock")));
+    }
+    messages.info(format!("{} has been unlocked.", form.key));
+    Ok(Redirect::to("/admin/lockouts"))
+}
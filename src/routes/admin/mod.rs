@@ -1,9 +1,21 @@
 mod dashboard;
+mod lockouts;
 mod logout;
 mod newsletters;
 mod password;
+mod security;
+mod segments;
+mod subscribers;
+mod suppressions;
+mod users;
 
 pub use dashboard::admin_dashboard;
+pub use lockouts::*;
 pub use logout::*;
 pub use newsletters::*;
 pub use password::*;
+pub use security::*;
+pub use segments::*;
+pub use subscribers::*;
+pub use suppressions::*;
+pub use users::*;
@@ -1,14 +1,43 @@
-use axum::response::Html;
+use axum::extract::State;
 use axum_messages::Messages;
 
-use crate::utils::get_all_messages;
+use crate::{
+    csrf::CsrfToken,
+    html,
+    html::Markup,
+    segment::{count_recipients, list_segments},
+    startup::AppState,
+    utils::{AppError, get_all_messages},
+};
 
 #[axum::debug_handler]
-pub async fn newsletters_form(messages: Messages) -> Html<String> {
+pub async fn newsletters_form(
+    State(state): State<AppState>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Result<Markup, AppError> {
     let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
     let idempotency_key = uuid::Uuid::new_v4();
 
-    Html(format!(
+    let all_count = count_recipients(&state.db_pool, &state.hmac_secret, None).await?;
+    let mut segment_options = vec![html!(
+        r#"<option value="">All confirmed subscribers ({all_count} recipients)</option>"#,
+        all_count,
+    )];
+    for segment in list_segments(&state.db_pool).await? {
+        let count =
+            count_recipients(&state.db_pool, &state.hmac_secret, Some(&segment.filter)).await?;
+        segment_options.push(html!(
+            r#"<option value="{}">{} ({count} recipients)</option>"#,
+            segment.segment_id,
+            segment.name,
+            count,
+        ));
+    }
+    let segment_options: Markup = segment_options.into_iter().collect();
+
+    Ok(html!(
         r#"
 <!doctype html>
 <html lang="en">
@@ -19,6 +48,7 @@ pub async fn newsletters_form(messages: Messages) -> Html<String> {
   <body>
     {message}
     <form action="/admin/newsletters" method="post">
+      {csrf_field}
       <label
         >Title
         <input type="text" placeholder="Enter the title" name="title" />
@@ -34,6 +64,11 @@ pub async fn newsletters_form(messages: Messages) -> Html<String> {
         <textarea placeholder="Enter text content" name="text_content"></textarea>
       </label>
       <br>
+      <label
+        >Segment
+        <select name="segment_id">{segment_options}</select>
+      </label>
+      <br>
       <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
       <button type="submit">Publish</button>
     </form>
@@ -41,5 +76,9 @@ pub async fn newsletters_form(messages: Messages) -> Html<String> {
   </body>
 </html>
             "#,
+        message,
+        csrf_field,
+        segment_options,
+        idempotency_key,
     ))
 }
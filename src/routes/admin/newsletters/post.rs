@@ -7,12 +7,15 @@ use axum::{
     response::{IntoResponse, Redirect},
 };
 use axum_messages::Messages;
+use secrecy::SecretString;
 use serde::Deserialize;
-use sqlx::PgConnection;
+use sqlx::{PgConnection, QueryBuilder};
+use uuid::Uuid;
 
 use crate::{
     authentication::CurrentUser,
     idempotency::{IdempotencyKey, NextAction, save_response, try_processing},
+    segment::{SegmentFilter, get_segment, push_recipients},
     startup::AppState,
     utils::{AppError, e400},
 };
@@ -23,6 +26,7 @@ pub struct FormData {
     html_content: String,
     text_content: String,
     idempotency_key: String,
+    segment_id: Option<String>,
 }
 
 #[axum::debug_handler]
@@ -37,8 +41,15 @@ pub async fn publish_newsletters(
         text_content,
         html_content,
         idempotency_key,
+        segment_id,
     } = form;
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
+    let segment_id = segment_id
+        .filter(|id| !id.is_empty())
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .context("invalid segment id")
+        .map_err(e400)?;
 
     let txn = state
         .db_pool
@@ -54,13 +65,28 @@ pub async fn publish_newsletters(
         }
     };
 
+    let segment = match segment_id {
+        Some(segment_id) => Some(
+            get_segment(&mut txn, segment_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("The segment does not exist"))
+                .map_err(e400)?,
+        ),
+        None => None,
+    };
+
     let newsletter_issue_id =
-        insert_newsletter_issue(&mut txn, &title, &text_content, &html_content)
+        insert_newsletter_issue(&mut txn, &title, &text_content, &html_content, segment_id)
             .await
             .context("cannot insert newsletter_issue")?;
-    enqueue_delivery_tasks(&mut txn, newsletter_issue_id)
-        .await
-        .context("failed to enqueue delivery tasks")?;
+    enqueue_delivery_tasks(
+        &mut txn,
+        &state.hmac_secret,
+        newsletter_issue_id,
+        segment.map(|s| s.filter),
+    )
+    .await
+    .context("failed to enqueue delivery tasks")?;
 
     messages.info("Successfully published a newsletter.");
     let response = Redirect::to("/admin/newsletters").into_response();
@@ -75,6 +101,7 @@ async fn insert_newsletter_issue(
     title: &str,
     text_content: &str,
     html_content: &str,
+    segment_id: Option<Uuid>,
 ) -> Result<uuid::Uuid, sqlx::Error> {
     let newsletter_issue_id = uuid::Uuid::new_v4();
     sqlx::query!(
@@ -84,14 +111,16 @@ INSERT INTO newsletter_issues (
     title,
     text_content,
     html_content,
-    published_at
+    published_at,
+    segment_id
 )
-VALUES ($1, $2, $3, $4, now())
+VALUES ($1, $2, $3, $4, now(), $5)
         "#,
         &newsletter_issue_id,
         title,
         text_content,
-        html_content
+        html_content,
+        segment_id,
     )
     .execute(txn)
     .await?;
@@ -100,20 +129,21 @@ VALUES ($1, $2, $3, $4, now())
 
 async fn enqueue_delivery_tasks(
     txn: &mut PgConnection,
+    secret: &SecretString,
     newsletter_issue_id: uuid::Uuid,
+    segment: Option<SegmentFilter>,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query!(
+    let mut qb = QueryBuilder::new(
         r#"
 INSERT INTO issue_delivery_queue  (
     newsletter_issue_id,
     subscriber_email
 )
-SELECT $1, email
-FROM subscriptions WHERE status = 'confirmed'
-        "#,
-        newsletter_issue_id,
-    )
-    .execute(txn)
-    .await?;
+SELECT "#,
+    );
+    qb.push_bind(newsletter_issue_id);
+    qb.push(", email FROM subscriptions WHERE ");
+    push_recipients(&mut qb, secret, segment.as_ref());
+    qb.build().execute(txn).await?;
     Ok(())
 }
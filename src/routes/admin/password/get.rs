@@ -1,12 +1,18 @@
-use axum::response::Html;
+use axum::extract::State;
 use axum_messages::Messages;
 
-use crate::utils::get_all_messages;
+use crate::{csrf::CsrfToken, html, html::Markup, startup::AppState, utils::get_all_messages};
 
 #[axum::debug_handler]
-pub async fn change_password_form(messages: Messages) -> Html<String> {
+pub async fn change_password_form(
+    State(state): State<AppState>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Markup {
     let message = get_all_messages(messages);
-    Html(format!(
+    let csrf_field = csrf_token.field();
+    let requirements = state.password_policy.requirements();
+    html!(
         r#"
 <!doctype html>
 <html lang="en">
@@ -16,7 +22,9 @@ pub async fn change_password_form(messages: Messages) -> Html<String> {
   </head>
   <body>
     {message}
+    <p>{requirements}</p>
     <form action="/admin/password" method="post">
+      {csrf_field}
       <label
         >Current password
         <input type="password" placeholder="Enter current password" name="current_password" />
@@ -38,5 +46,8 @@ pub async fn change_password_form(messages: Messages) -> Html<String> {
   </body>
 </html>
             "#,
-    ))
+        message,
+        requirements,
+        csrf_field,
+    )
 }
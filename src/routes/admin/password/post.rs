@@ -4,7 +4,11 @@ use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
 
 use crate::{
-    authentication::{self, Credentials, CurrentUser, validate_credentials},
+    authentication::{
+        self, Credentials, CurrentUser, PasswordError, validate_credentials,
+        validate_password_security,
+    },
+    session_state::TypedSession,
     startup::AppState,
     utils::{AppError, e500},
 };
@@ -20,6 +24,7 @@ pub struct FormData {
 pub async fn change_password(
     State(state): State<AppState>,
     Extension(current_user): Extension<CurrentUser>,
+    session: TypedSession,
     messages: Messages,
     Form(form): Form<FormData>,
 ) -> Result<Redirect, AppError> {
@@ -35,7 +40,7 @@ pub async fn change_password(
         username: username.clone(),
         password: form.current_password,
     };
-    if validate_credentials(credentials, &state.db_pool)
+    if validate_credentials(credentials, &state.password_hashing, &state.db_pool)
         .await
         .is_err()
     {
@@ -45,29 +50,34 @@ pub async fn change_password(
         ));
     }
 
-    if let Err(e) = validate_password_security(&form.new_password) {
-        return Ok(change_password_redirect(e, messages));
+    match validate_password_security(&state.password_policy, &form.new_password, &[&username]).await
+    {
+        Ok(()) => {}
+        Err(PasswordError::Rejected(e)) => return Ok(change_password_redirect(e, messages)),
+        Err(PasswordError::UnexpectedError(e)) => return Err(e500(e)),
     }
 
-    authentication::change_password(&username, form.new_password, &state.db_pool)
-        .await
-        .map_err(e500)?;
+    authentication::change_password(
+        &username,
+        form.new_password,
+        &state.password_hashing,
+        &state.db_pool,
+    )
+    .await
+    .map_err(e500)?;
+    // Whoever learned the old password is logged out, except here.
+    let current = session.id().await.map_err(e500)?;
+    state
+        .session_index
+        .revoke_all(current_user.user_id, current)
+        .await?;
     messages.info("Your password has been changed.");
     Ok(Redirect::to("/admin/password"))
 }
 
-fn change_password_redirect(e: &'static str, messages: Messages) -> Redirect {
+fn change_password_redirect(e: impl Into<String>, messages: Messages) -> Redirect {
+    let e = e.into();
     tracing::error!(error.message = %e, "Failed to change password");
     messages.error(e);
     Redirect::to("/admin/password")
 }
-
-fn validate_password_security(password: &SecretString) -> Result<(), &'static str> {
-    if password.expose_secret().len() < 12 {
-        return Err("New password must be at least 12 characters.");
-    }
-    if password.expose_secret().len() >= 128 {
-        return Err("New password must be less than 128 characters.");
-    }
-    Ok(())
-}
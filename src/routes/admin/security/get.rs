@@ -0,0 +1,100 @@
+use axum::{Extension, extract::State};
+use axum_messages::Messages;
+use secrecy::ExposeSecret;
+
+use crate::{
+    authentication::{CurrentUser, Totp, count_unused_recovery_codes, get_totp},
+    csrf::CsrfToken,
+    html,
+    html::Markup,
+    session_state::TypedSession,
+    startup::AppState,
+    utils::{AppError, e500, get_all_messages},
+};
+
+#[axum::debug_handler]
+pub async fn security_form(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    session: TypedSession,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Result<Markup, AppError> {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+
+    let body = if get_totp(&state.db_pool, &state.hmac_secret, current_user.user_id)
+        .await?
+        .is_some()
+    {
+        let remaining = count_unused_recovery_codes(&state.db_pool, current_user.user_id).await?;
+        html!(
+            r#"
+    <p>Two-factor authentication is enabled, {remaining} unused recovery code(s) left.</p>
+    <form action="/admin/security/totp/disable" method="post">
+      {csrf_field}
+      <label
+        >Code from your authenticator app, or a recovery code
+        <input type="text" autocomplete="one-time-code" name="code" />
+      </label>
+      <button type="submit">Disable two-factor authentication</button>
+    </form>"#,
+            remaining,
+            csrf_field,
+        )
+    } else {
+        // The same secret is shown until it is confirmed, reloading the page
+        // must not invalidate what was already scanned.
+        let totp = match session.get_totp_enrollment().await.map_err(e500)? {
+            Some(secret) => Totp::from_secret(secret.into()),
+            None => {
+                let totp = Totp::generate();
+                session
+                    .insert_totp_enrollment(totp.secret().expose_secret())
+                    .await
+                    .map_err(e500)?;
+                totp
+            }
+        };
+        // Only shapes drawn by the QR code encoder, nothing to escape.
+        let qr_code = Markup::raw(totp.qr_code_svg(&current_user.username)?);
+        html!(
+            r#"
+    <p>Two-factor authentication is disabled.</p>
+    <p>Scan this QR code with your authenticator app:</p>
+    {qr_code}
+    <p>Or enter this secret manually: <code id="totp-secret">{}</code></p>
+    <form action="/admin/security/totp" method="post">
+      {csrf_field}
+      <label
+        >Code shown by the app
+        <input type="text" inputmode="numeric" autocomplete="one-time-code" name="code" />
+      </label>
+      <button type="submit">Enable two-factor authentication</button>
+    </form>"#,
+            totp.secret().expose_secret(),
+            qr_code,
+            csrf_field,
+        )
+    };
+
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Security</title>
+  </head>
+  <body>
+    {message}
+    {body}
+    <p><a href="/admin/security/sessions">Where you are logged in</a></p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        body,
+    ))
+}
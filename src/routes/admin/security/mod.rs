@@ -0,0 +1,7 @@
+mod get;
+mod post;
+mod sessions;
+
+pub use get::security_form;
+pub use post::{disable_two_factor, enable_two_factor};
+pub use sessions::{revoke_other_sessions, revoke_session, sessions_form};
@@ -0,0 +1,100 @@
+use axum::{
+    Extension, Form,
+    extract::State,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use serde::Deserialize;
+
+use crate::{
+    authentication::{CurrentUser, Totp, disable_totp, enable_totp, verify_second_factor},
+    html,
+    html::Markup,
+    session_state::TypedSession,
+    startup::AppState,
+    utils::{AppError, e500},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct FormData {
+    code: String,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Enable two-factor authentication", skip_all)]
+pub async fn enable_two_factor(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    session: TypedSession,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Result<Response, AppError> {
+    let Some(secret) = session.get_totp_enrollment().await.map_err(e500)? else {
+        messages.error("Your enrollment expired, please scan the new QR code.");
+        return Ok(Redirect::to("/admin/security").into_response());
+    };
+    let totp = Totp::from_secret(secret.into());
+    let Some(step) = totp.verify(&form.code, None)? else {
+        messages.error("The code is not valid, check your device's clock and try again.");
+        return Ok(Redirect::to("/admin/security").into_response());
+    };
+    let recovery_codes = enable_totp(
+        &state.db_pool,
+        &state.hmac_secret,
+        current_user.user_id,
+        &totp,
+        step,
+        &state.password_hashing,
+    )
+    .await?;
+    session.remove_totp_enrollment().await.map_err(e500)?;
+
+    // Recovery codes are only stored hashed, this is the one chance to see them.
+    let recovery_codes = recovery_codes
+        .iter()
+        .map(|code| html!("<li><code>{code}</code></li>", code))
+        .collect::<Markup>();
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Recovery codes</title>
+  </head>
+  <body>
+    <p>Two-factor authentication is enabled.</p>
+    <p>Keep these recovery codes somewhere safe, each lets you log in once without your device. They will not be shown again.</p>
+    <ul id="recovery-codes">{recovery_codes}</ul>
+    <p><a href="/admin/security">Done</a></p>
+  </body>
+</html>
+            "#,
+        recovery_codes,
+    )
+    .into_response())
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Disable two-factor authentication", skip_all)]
+pub async fn disable_two_factor(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Result<Redirect, AppError> {
+    if !verify_second_factor(
+        &state.db_pool,
+        &state.hmac_secret,
+        current_user.user_id,
+        &form.code,
+    )
+    .await?
+    {
+        messages.error("The code is not valid.");
+        return Ok(Redirect::to("/admin/security"));
+    }
+    disable_totp(&state.db_pool, current_user.user_id).await?;
+    messages.info("Two-factor authentication has been disabled.");
+    Ok(Redirect::to("/admin/security"))
+}
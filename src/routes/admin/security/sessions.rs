@@ -0,0 +1,126 @@
+use axum::{
+    Extension,
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_messages::Messages;
+use uuid::Uuid;
+
+use crate::{
+    authentication::CurrentUser,
+    csrf::CsrfToken,
+    html,
+    html::Markup,
+    session_state::TypedSession,
+    startup::AppState,
+    utils::{AppError, e404, e500, get_all_messages},
+};
+
+#[axum::debug_handler]
+pub async fn sessions_form(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    session: TypedSession,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Result<Markup, AppError> {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+    let current = session.id().await.map_err(e500)?;
+
+    let rows = state
+        .session_index
+        .list(current_user.user_id)
+        .await?
+        .iter()
+        .map(|s| {
+            let action = if Some(s.id) == current {
+                html!("This session")
+            } else {
+                html!(
+                    r#"<form action="/admin/security/sessions/{}/revoke" method="post">{csrf_field}<button type="submit">Revoke</button></form>"#,
+                    s.metadata.handle,
+                    csrf_field,
+                )
+            };
+            html!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{action}</td></tr>",
+                s.metadata.created_at.format("%Y-%m-%d %H:%M"),
+                s.metadata.last_seen_at.format("%Y-%m-%d %H:%M"),
+                s.metadata.ip.as_deref().unwrap_or("-"),
+                s.metadata.user_agent.as_deref().unwrap_or("-"),
+                action,
+            )
+        })
+        .collect::<Markup>();
+
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Sessions</title>
+  </head>
+  <body>
+    {message}
+    <p>You are logged in here:</p>
+    <table>
+      <tr><th>Logged in</th><th>Last seen</th><th>Address</th><th>Browser</th><th></th></tr>
+      {rows}
+    </table>
+    <form action="/admin/security/sessions/revoke_others" method="post">
+      {csrf_field}
+      <button type="submit">Log out everywhere else</button>
+    </form>
+    <p><a href="/admin/security">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        rows,
+        csrf_field,
+    ))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Revoke session", skip(state, current_user, session, messages))]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    session: TypedSession,
+    messages: Messages,
+    Path(handle): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    let current = session.get_metadata().await.map_err(e500)?;
+    if current.is_some_and(|metadata| metadata.handle == handle) {
+        messages.error("Use the logout button to end this session.");
+        return Ok(Redirect::to("/admin/security/sessions"));
+    }
+    if !state
+        .session_index
+        .revoke(current_user.user_id, handle)
+        .await?
+    {
+        return Err(e404(anyhow::anyhow!("The session does not exist")));
+    }
+    messages.info("The session has been revoked.");
+    Ok(Redirect::to("/admin/security/sessions"))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Revoke other sessions", skip_all)]
+pub async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    session: TypedSession,
+    messages: Messages,
+) -> Result<Redirect, AppError> {
+    let current = session.id().await.map_err(e500)?;
+    state
+        .session_index
+        .revoke_all(current_user.user_id, current)
+        .await?;
+    messages.info("All your other sessions have been revoked.");
+    Ok(Redirect::to("/admin/security/sessions"))
+}
@@ -0,0 +1,77 @@
+use axum::extract::State;
+use axum_messages::Messages;
+
+use crate::{
+    csrf::CsrfToken,
+    html,
+    html::Markup,
+    segment::{count_recipients, list_segments},
+    startup::AppState,
+    utils::{AppError, e500, get_all_messages},
+};
+
+#[axum::debug_handler]
+pub async fn segments_form(
+    State(state): State<AppState>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Result<Markup, AppError> {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+
+    let mut rows = Vec::new();
+    for segment in list_segments(&state.db_pool).await? {
+        let count =
+            count_recipients(&state.db_pool, &state.hmac_secret, Some(&segment.filter)).await?;
+        let definition = serde_json::to_string(&segment.filter).map_err(e500)?;
+        rows.push(html!(
+            "<tr><td>{}</td><td><code>{definition}</code></td><td>{count}</td></tr>",
+            segment.name,
+            definition,
+            count,
+        ));
+    }
+    let rows: Markup = rows.into_iter().collect();
+
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Segments</title>
+  </head>
+  <body>
+    {message}
+    <table>
+      <tr><th>Name</th><th>Definition</th><th>Confirmed subscribers</th></tr>
+      {rows}
+    </table>
+    <form action="/admin/segments" method="post">
+      {csrf_field}
+      <label
+        >Name
+        <input type="text" placeholder="Enter the segment name" name="name" />
+      </label>
+      <br>
+      <label
+        >Definition
+        <textarea placeholder='{{"all": [{{"subscribed_within_days": 30}}]}}' name="definition"></textarea>
+      </label>
+      <br>
+      <button type="submit">Create segment</button>
+    </form>
+    <p>
+      A definition is a JSON filter combining <code>all</code>, <code>any</code> and <code>not</code>
+      with <code>status</code>, <code>subscribed_within_days</code>, <code>email_domain</code>,
+      <code>tag</code> and <code>attribute</code> (<code>{{"name": "company", "equals": "Acme"}}</code>).
+    </p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        rows,
+        csrf_field,
+    ))
+}
@@ -0,0 +1,53 @@
+use axum::{Form, extract::State, response::Redirect};
+use axum_messages::Messages;
+use serde::Deserialize;
+
+use crate::{
+    segment::{SegmentFilter, insert_segment},
+    startup::AppState,
+    utils::{AppError, e500},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct FormData {
+    name: String,
+    definition: String,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Create segment", skip(state, messages))]
+pub async fn create_segment(
+    State(state): State<AppState>,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Result<Redirect, AppError> {
+    let name = form.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Ok(segments_redirect(
+            "The segment name must be between 1 and 100 characters.",
+            messages,
+        ));
+    }
+    let filter = match SegmentFilter::try_from(form.definition) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(segments_redirect(&e.to_string(), messages)),
+    };
+
+    match insert_segment(&state.db_pool, name, filter).await {
+        Ok(_) => {
+            messages.info("The segment has been created.");
+            Ok(Redirect::to("/admin/segments"))
+        }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(segments_redirect(
+            "A segment with this name already exists.",
+            messages,
+        )),
+        Err(e) => Err(e500(e)),
+    }
+}
+
+fn segments_redirect(e: &str, messages: Messages) -> Redirect {
+    tracing::error!(error.message = %e, "Failed to create segment");
+    messages.error(e);
+    Redirect::to("/admin/segments")
+}
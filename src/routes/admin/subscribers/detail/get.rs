@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use axum_messages::Messages;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{CurrentUser, Role},
+    csrf::CsrfToken,
+    html,
+    html::Markup,
+    startup::AppState,
+    subscriber_data::collect_subscriber_data,
+    subscription_events::list_subscription_events,
+    utils::{AppError, e404, get_all_messages},
+};
+
+#[axum::debug_handler]
+#[tracing::instrument(
+    name = "Show subscriber details",
+    skip(state, current_user, csrf_token, messages)
+)]
+pub async fn subscriber_details(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<Markup, AppError> {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+    let subscriber = sqlx::query!(
+        r#"
+SELECT
+    email,
+    name,
+    status,
+    subscribed_at,
+    consent_source,
+    attributes AS "attributes: Json<BTreeMap<String, String>>",
+    ARRAY(
+        SELECT tag FROM subscriber_tags WHERE subscriber_id = $1 ORDER BY tag
+    ) AS "tags!"
+FROM subscriptions
+WHERE id = $1
+        "#,
+        subscriber_id,
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .context("Failed to fetch the subscriber")?
+    .ok_or_else(|| e404(anyhow::anyhow!("The subscriber does not exist")))?;
+
+    let tokens = sqlx::query!(
+        r#"
+SELECT created_at
+FROM subscription_tokens
+WHERE subscriber_id = $1
+ORDER BY created_at DESC
+        "#,
+        subscriber_id,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .context("Failed to fetch the subscription tokens")?;
+
+    let deliveries = sqlx::query!(
+        r#"
+SELECT l.recorded_at, l.outcome, l.detail, i.title AS "title?"
+FROM issue_delivery_log l
+LEFT JOIN newsletter_issues i ON i.newsletter_issue_id = l.newsletter_issue_id
+WHERE l.subscriber_email = $1
+ORDER BY l.recorded_at DESC
+LIMIT 100
+        "#,
+        subscriber.email,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .context("Failed to fetch the delivery history")?;
+
+    let events = list_subscription_events(&state.db_pool, &subscriber.email)
+        .await
+        .context("Failed to fetch the consent history")?;
+
+    let token_state = match (subscriber.status.as_str(), tokens.first()) {
+        ("pending_confirmation", Some(latest)) => format!(
+            "Awaiting confirmation, {} link(s) sent, the latest at {}.",
+            tokens.len(),
+            latest.created_at.format("%Y-%m-%d %H:%M UTC"),
+        ),
+        ("pending_confirmation", None) => "Awaiting confirmation, no link was sent.".into(),
+        (_, Some(_)) => "The confirmation link has been used.".into(),
+        (_, None) => "No confirmation link was needed.".into(),
+    };
+
+    let attributes = subscriber
+        .attributes
+        .0
+        .iter()
+        .map(|(name, value)| html!("<li>{name}: {value}</li>", name, value))
+        .collect::<Markup>();
+
+    let deliveries = deliveries
+        .iter()
+        .map(|d| {
+            html!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                d.recorded_at.format("%Y-%m-%d %H:%M UTC"),
+                d.title.as_deref().unwrap_or("-"),
+                d.outcome,
+                d.detail.as_deref().unwrap_or(""),
+            )
+        })
+        .collect::<Markup>();
+
+    let events = events
+        .iter()
+        .map(|e| {
+            html!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                e.occurred_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                e.event_type,
+                e.source.as_deref().unwrap_or("-"),
+                e.source_ip.as_deref().unwrap_or("-"),
+                e.user_agent.as_deref().unwrap_or("-"),
+                e.admin_username.as_deref().unwrap_or("-"),
+                e.detail.as_deref().unwrap_or(""),
+            )
+        })
+        .collect::<Markup>();
+
+    let mut actions = Vec::new();
+    if subscriber.status == "pending_confirmation" {
+        actions.push(html!(
+            r#"<form action="/admin/subscribers/{subscriber_id}/resend_confirmation" method="post">{csrf_field}<button type="submit">Resend confirmation</button></form>"#,
+            subscriber_id,
+            csrf_field,
+        ));
+    }
+    if subscriber.status != "unsubscribed" {
+        actions.push(html!(
+            r#"<form action="/admin/subscribers/{subscriber_id}/unsubscribe" method="post">{csrf_field}<button type="submit">Unsubscribe</button></form>"#,
+            subscriber_id,
+            csrf_field,
+        ));
+    }
+    if current_user.role == Role::Owner {
+        actions.push(html!(
+            r#"<p><a href="/admin/subscribers/{subscriber_id}/data">Export all data (JSON)</a></p>"#,
+            subscriber_id,
+        ));
+    }
+    if current_user.role >= Role::Publisher {
+        actions.push(html!(
+            r#"<form action="/admin/subscribers/{subscriber_id}/erase" method="post" onsubmit="return confirm('Erase all data about this subscriber? The address cannot be re-imported afterwards.');">{csrf_field}<button type="submit">Erase data</button></form>"#,
+            subscriber_id,
+            csrf_field,
+        ));
+        actions.push(html!(
+            r#"<form action="/admin/subscribers/{subscriber_id}/delete" method="post" onsubmit="return confirm('Delete this subscriber?');">{csrf_field}<button type="submit">Delete</button></form>"#,
+            subscriber_id,
+            csrf_field,
+        ));
+    }
+    let actions: Markup = actions.into_iter().collect();
+
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Subscriber</title>
+  </head>
+  <body>
+    {message}
+    <dl>
+      <dt>Email</dt><dd>{email}</dd>
+      <dt>Name</dt><dd>{name}</dd>
+      <dt>Status</dt><dd>{status}</dd>
+      <dt>Subscribed at</dt><dd>{subscribed_at}</dd>
+      <dt>Consent source</dt><dd>{consent_source}</dd>
+      <dt>Confirmation</dt><dd>{token_state}</dd>
+      <dt>Tags</dt><dd>{tags}</dd>
+      <dt>Attributes</dt><dd><ul>{attributes}</ul></dd>
+    </dl>
+    {actions}
+    <h2>Consent history</h2>
+    <table>
+      <tr><th>Date</th><th>Event</th><th>Source</th><th>IP address</th><th>User agent</th><th>Admin</th><th>Detail</th></tr>
+      {events}
+    </table>
+    <h2>Delivery history</h2>
+    <table>
+      <tr><th>Date</th><th>Issue</th><th>Outcome</th><th>Detail</th></tr>
+      {deliveries}
+    </table>
+    <p><a href="/admin/subscribers">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        email = subscriber.email,
+        name = subscriber.name,
+        status = subscriber.status,
+        subscribed_at = subscriber.subscribed_at.format("%Y-%m-%d %H:%M UTC"),
+        consent_source = subscriber.consent_source.as_deref().unwrap_or("-"),
+        token_state,
+        tags = subscriber.tags.join(", "),
+        attributes,
+        actions,
+        events,
+        deliveries,
+    ))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Export subscriber data", skip(state))]
+pub async fn export_subscriber_data(
+    State(state): State<AppState>,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let email = sqlx::query_scalar!(
+        r#"SELECT email FROM subscriptions WHERE id = $1"#,
+        subscriber_id,
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .context("Failed to fetch the subscriber")?
+    .ok_or_else(|| e404(anyhow::anyhow!("The subscriber does not exist")))?;
+
+    let data = collect_subscriber_data(&state.db_pool, &email).await?;
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            format!(r#"attachment; filename="subscriber-{subscriber_id}.json""#),
+        )],
+        axum::Json(data),
+    )
+        .into_response())
+}
@@ -0,0 +1,7 @@
+mod get;
+mod post;
+
+pub use get::{export_subscriber_data, subscriber_details};
+pub use post::{
+    delete_subscriber, erase_subscriber_data, resend_confirmation, unsubscribe_subscriber,
+};
@@ -0,0 +1,205 @@
+use anyhow::Context;
+use axum::{
+    Extension,
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_messages::Messages;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    authentication::CurrentUser,
+    domain::SubscriberEmail,
+    request_metadata::RequestMetadata,
+    routes::{generate_subscription_token, send_confirmation_email, store_token},
+    startup::AppState,
+    subscriber_data::erase_subscriber,
+    subscription_events::{SubscriptionEvent, SubscriptionEventKind},
+    utils::{AppError, e404, e500},
+};
+
+struct SubscriberRecord {
+    email: String,
+    status: String,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(
+    name = "Resend confirmation",
+    skip(state, messages, current_user, request)
+)]
+pub async fn resend_confirmation(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    request: RequestMetadata,
+    messages: Messages,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    let subscriber = get_subscriber(&state.db_pool, subscriber_id).await?;
+    if subscriber.status != "pending_confirmation" {
+        messages.error("Only pending subscribers can be sent a confirmation email.");
+        return Ok(details_redirect(subscriber_id));
+    }
+    let subscriber_email = SubscriberEmail::parse(subscriber.email)
+        .map_err(anyhow::Error::msg)
+        .map_err(e500)?;
+
+    let mut txn = state
+        .db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    SubscriptionEvent::new(SubscriptionEventKind::AdminEdit, subscriber_email.as_ref())
+        .request(&request)
+        .admin(current_user.user_id)
+        .detail("Resent the confirmation email")
+        .record(&mut txn)
+        .await
+        .context("Failed to record the subscription event")?;
+    let subscription_token = generate_subscription_token();
+    store_token(txn, subscriber_id, &subscription_token)
+        .await
+        .context("Failed to store the confirmation token.")?;
+    send_confirmation_email(
+        &state.email_client,
+        &subscriber_email,
+        &state.base_url,
+        &subscription_token,
+    )
+    .await
+    .context("Failed to send a confirmation email")?;
+
+    messages.info("A new confirmation email has been sent.");
+    Ok(details_redirect(subscriber_id))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(
+    name = "Unsubscribe subscriber",
+    skip(state, messages, current_user, request)
+)]
+pub async fn unsubscribe_subscriber(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    request: RequestMetadata,
+    messages: Messages,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    let subscriber = get_subscriber(&state.db_pool, subscriber_id).await?;
+    let mut txn = state
+        .db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = 'unsubscribed' WHERE id = $1"#,
+        subscriber_id,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to unsubscribe the subscriber")?;
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to delete the subscription tokens")?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+        subscriber.email,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to cancel pending deliveries")?;
+    SubscriptionEvent::new(SubscriptionEventKind::Unsubscribe, &subscriber.email)
+        .request(&request)
+        .admin(current_user.user_id)
+        .record(&mut txn)
+        .await
+        .context("Failed to record the subscription event")?;
+    txn.commit().await.context("Failed to commit")?;
+
+    messages.info("The subscriber has been unsubscribed.");
+    Ok(details_redirect(subscriber_id))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(
+    name = "Delete subscriber",
+    skip(state, messages, current_user, request)
+)]
+pub async fn delete_subscriber(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    request: RequestMetadata,
+    messages: Messages,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    let subscriber = get_subscriber(&state.db_pool, subscriber_id).await?;
+    let mut txn = state
+        .db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+        subscriber.email,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to cancel pending deliveries")?;
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to delete the subscription tokens")?;
+    sqlx::query!(r#"DELETE FROM subscriptions WHERE id = $1"#, subscriber_id,)
+        .execute(&mut *txn)
+        .await
+        .context("Failed to delete the subscriber")?;
+    SubscriptionEvent::new(SubscriptionEventKind::AdminEdit, &subscriber.email)
+        .request(&request)
+        .admin(current_user.user_id)
+        .detail("Deleted the subscriber")
+        .record(&mut txn)
+        .await
+        .context("Failed to record the subscription event")?;
+    txn.commit().await.context("Failed to commit")?;
+
+    messages.info("The subscriber has been deleted.");
+    Ok(Redirect::to("/admin/subscribers"))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Erase subscriber data", skip(state, messages))]
+pub async fn erase_subscriber_data(
+    State(state): State<AppState>,
+    messages: Messages,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    let subscriber = get_subscriber(&state.db_pool, subscriber_id).await?;
+    erase_subscriber(&state.db_pool, &state.hmac_secret, &subscriber.email).await?;
+
+    messages.info("All data about the subscriber has been erased.");
+    Ok(Redirect::to("/admin/subscribers"))
+}
+
+async fn get_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<SubscriberRecord, AppError> {
+    sqlx::query_as!(
+        SubscriberRecord,
+        r#"SELECT email, status FROM subscriptions WHERE id = $1"#,
+        subscriber_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the subscriber")?
+    .ok_or_else(|| e404(anyhow::anyhow!("The subscriber does not exist")))
+}
+
+fn details_redirect(subscriber_id: Uuid) -> Redirect {
+    Redirect::to(&format!("/admin/subscribers/{subscriber_id}"))
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, types::Json};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    startup::AppState,
+    utils::{AppError, e400},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ExportParameters {
+    format: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize)]
+struct ExportedSubscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    tags: Vec<String>,
+    attributes: BTreeMap<String, String>,
+}
+
+type Chunk = Result<Bytes, anyhow::Error>;
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Export subscribers", skip(state))]
+pub async fn export_subscribers(
+    State(state): State<AppState>,
+    Query(parameters): Query<ExportParameters>,
+) -> Result<Response, AppError> {
+    let format = match parameters.format.as_deref().unwrap_or("csv") {
+        "csv" => ExportFormat::Csv,
+        "json" => ExportFormat::Json,
+        other => return Err(e400(anyhow::anyhow!("{other} is not a supported format"))),
+    };
+    let status = parameters.status.filter(|s| !s.is_empty());
+
+    // Rows are written to the response as they come out of Postgres, the channel
+    // only buffers a handful of them so the export never sits in memory at once.
+    let (sender, receiver) = mpsc::channel::<Chunk>(16);
+    tokio::spawn(stream_subscribers(
+        state.db_pool.clone(),
+        status,
+        format,
+        sender,
+    ));
+    let body = Body::from_stream(futures_util::stream::unfold(
+        receiver,
+        |mut receiver| async move { receiver.recv().await.map(|chunk| (chunk, receiver)) },
+    ));
+
+    let (content_type, file_name) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "subscribers.csv"),
+        ExportFormat::Json => ("application/json", "subscribers.json"),
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(r#"attachment; filename="{file_name}""#),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+async fn stream_subscribers(
+    pool: PgPool,
+    status: Option<String>,
+    format: ExportFormat,
+    sender: mpsc::Sender<Chunk>,
+) {
+    if let Err(e) = try_stream_subscribers(&pool, status, format, &sender).await {
+        tracing::error!(error.cause_chain = ?e, "Failed to export subscribers");
+        let _ = sender.send(Err(e)).await;
+    }
+}
+
+async fn try_stream_subscribers(
+    pool: &PgPool,
+    status: Option<String>,
+    format: ExportFormat,
+    sender: &mpsc::Sender<Chunk>,
+) -> Result<(), anyhow::Error> {
+    let mut rows = sqlx::query!(
+        r#"
+SELECT
+    s.id,
+    s.email,
+    s.name,
+    s.status,
+    s.subscribed_at,
+    s.attributes AS "attributes: Json<BTreeMap<String, String>>",
+    COALESCE(
+        array_agg(t.tag ORDER BY t.tag) FILTER (WHERE t.tag IS NOT NULL),
+        '{}'
+    ) AS "tags!"
+FROM subscriptions s
+LEFT JOIN subscriber_tags t ON t.subscriber_id = s.id
+WHERE $1::text IS NULL OR s.status = $1
+GROUP BY s.id
+ORDER BY s.subscribed_at, s.id
+        "#,
+        status,
+    )
+    .fetch(pool);
+
+    let header = match format {
+        ExportFormat::Csv => csv_row(&[
+            "id",
+            "email",
+            "name",
+            "status",
+            "subscribed_at",
+            "tags",
+            "attributes",
+        ])?,
+        ExportFormat::Json => "[".into(),
+    };
+    send(sender, header).await?;
+
+    let mut first = true;
+    while let Some(row) = rows.next().await {
+        let row = row.context("Failed to fetch a subscriber")?;
+        let subscriber = ExportedSubscriber {
+            id: row.id,
+            email: row.email,
+            name: row.name,
+            status: row.status,
+            subscribed_at: row.subscribed_at,
+            tags: row.tags,
+            attributes: row.attributes.0,
+        };
+        let chunk = match format {
+            ExportFormat::Csv => csv_row(&[
+                &subscriber.id.to_string(),
+                &subscriber.email,
+                &subscriber.name,
+                &subscriber.status,
+                &subscriber.subscribed_at.to_rfc3339(),
+                &subscriber.tags.join(","),
+                &serde_json::to_string(&subscriber.attributes)?,
+            ])?,
+            ExportFormat::Json => {
+                let separator = if first { "" } else { "," };
+                format!("{separator}\n{}", serde_json::to_string(&subscriber)?)
+            }
+        };
+        first = false;
+        send(sender, chunk).await?;
+    }
+
+    if let ExportFormat::Json = format {
+        send(sender, "\n]\n".into()).await?;
+    }
+    Ok(())
+}
+
+fn csv_row(fields: &[&str]) -> Result<String, anyhow::Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(fields.iter().map(|field| neutralize_formula(field)))?;
+    let row = writer.into_inner().context("Failed to flush a CSV row")?;
+    Ok(String::from_utf8(row)?)
+}
+
+/// Names, tags and attributes come from the public form and from imports,
+/// the export is opened in spreadsheets: a cell that would be read as a
+/// formula is kept as text.
+fn neutralize_formula(field: &str) -> String {
+    if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}")
+    } else {
+        field.to_string()
+    }
+}
+
+async fn send(sender: &mpsc::Sender<Chunk>, chunk: String) -> Result<(), anyhow::Error> {
+    sender
+        .send(Ok(Bytes::from(chunk)))
+        .await
+        .context("The export was cancelled by the client")
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+
+    use super::csv_row;
+
+    #[test]
+    fn cells_read_as_formulas_are_kept_as_text() {
+        let row = csv_row(&["=1+1", "+1", "-1", "@SUM(A1)", "\tx", "Ursula", "a=b"]).unwrap();
+        assert_that!(row.as_str()).is_equal_to("'=1+1,'+1,'-1,'@SUM(A1),'\tx,Ursula,a=b\n");
+    }
+}
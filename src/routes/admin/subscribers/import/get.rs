@@ -0,0 +1,55 @@
+use axum_messages::Messages;
+
+use crate::{csrf::CsrfToken, html, html::Markup, utils::get_all_messages};
+
+#[axum::debug_handler]
+pub async fn import_subscribers_form(csrf_token: CsrfToken, messages: Messages) -> Markup {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+    html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Import subscribers</title>
+  </head>
+  <body>
+    {message}
+    <p>
+      Upload a CSV file with a header row containing <code>email</code> and <code>name</code>.
+      An optional <code>tags</code> column holds comma separated tags,
+      every other column is stored as a custom attribute.
+    </p>
+    <form action="/admin/subscribers/import" method="post" enctype="multipart/form-data">
+      {csrf_field}
+      <label>
+        <input type="radio" name="mode" value="send_confirmation" checked />
+        Send a confirmation email to every imported subscriber
+      </label>
+      <br>
+      <label>
+        <input type="radio" name="mode" value="confirmed" />
+        Mark imported subscribers as confirmed
+      </label>
+      <br>
+      <label
+        >Consent source (required when marking as confirmed)
+        <input type="text" placeholder="e.g. Mailchimp export 2025-07" name="consent_source" />
+      </label>
+      <br>
+      <label
+        >CSV file
+        <input type="file" accept=".csv,text/csv" name="file" />
+      </label>
+      <br>
+      <button type="submit">Import</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        csrf_field,
+    )
+}
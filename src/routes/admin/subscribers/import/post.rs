@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use axum::{
+    Extension,
+    extract::{Multipart, State, multipart::Field},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use csv_async::{AsyncReaderBuilder, StringRecord, Trim};
+use futures_util::{StreamExt, TryStreamExt};
+use tokio_util::io::StreamReader;
+use uuid::Uuid;
+
+use crate::{
+    authentication::CurrentUser,
+    domain::NewSubscriber,
+    html,
+    html::Markup,
+    request_metadata::RequestMetadata,
+    routes::{
+        generate_subscription_token, insert_subscriber, send_confirmation_email, store_token,
+    },
+    startup::AppState,
+    subscription_events::{SubscriptionEvent, SubscriptionEventKind},
+    suppression::is_suppressed,
+    utils::{AppError, e400},
+};
+
+#[derive(Debug, Clone)]
+enum ImportMode {
+    Confirmed { consent_source: String },
+    SendConfirmation,
+}
+
+impl ImportMode {
+    fn parse(mode: Option<String>, consent_source: Option<String>) -> Result<Self, &'static str> {
+        match mode.as_deref() {
+            Some("confirmed") => {
+                let consent_source = consent_source.unwrap_or_default().trim().to_string();
+                if consent_source.is_empty() {
+                    return Err("A consent source is required to import confirmed subscribers.");
+                }
+                Ok(ImportMode::Confirmed { consent_source })
+            }
+            Some("send_confirmation") => Ok(ImportMode::SendConfirmation),
+            _ => Err("Please choose how imported subscribers should be confirmed."),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ImportReport {
+    imported: usize,
+    duplicates: Vec<u64>,
+    suppressed: Vec<u64>,
+    errors: Vec<(u64, String)>,
+}
+
+/// What every imported row shares: how to confirm it and who imported it.
+struct ImportContext<'a> {
+    state: &'a AppState,
+    mode: ImportMode,
+    admin_user_id: Uuid,
+    request: &'a RequestMetadata,
+}
+
+enum RowOutcome {
+    Imported,
+    Duplicate,
+    Suppressed,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(
+    name = "Import subscribers",
+    skip(state, current_user, request, messages, multipart)
+)]
+pub async fn import_subscribers(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    request: RequestMetadata,
+    messages: Messages,
+    mut multipart: Multipart,
+) -> Result<Response, AppError> {
+    let mut mode = None;
+    let mut consent_source = None;
+    let mut report = None;
+
+    // The file is streamed row by row, so the options must come before it in the form.
+    while let Some(field) = multipart.next_field().await.map_err(e400)? {
+        match field.name() {
+            Some("mode") => mode = Some(field.text().await.map_err(e400)?),
+            Some("consent_source") => consent_source = Some(field.text().await.map_err(e400)?),
+            Some("file") => {
+                let import_mode = match ImportMode::parse(mode.take(), consent_source.take()) {
+                    Ok(import_mode) => import_mode,
+                    Err(e) => {
+                        messages.error(e);
+                        return Ok(Redirect::to("/admin/subscribers/import").into_response());
+                    }
+                };
+                let context = ImportContext {
+                    state: &state,
+                    mode: import_mode,
+                    admin_user_id: current_user.user_id,
+                    request: &request,
+                };
+                report = Some(import_csv(&context, field).await?);
+            }
+            _ => {}
+        }
+    }
+
+    let Some(report) = report else {
+        messages.error("Please select a CSV file to import.");
+        return Ok(Redirect::to("/admin/subscribers/import").into_response());
+    };
+    Ok(render_report(&report).into_response())
+}
+
+async fn import_csv(
+    context: &ImportContext<'_>,
+    field: Field<'_>,
+) -> Result<ImportReport, AppError> {
+    let reader = StreamReader::new(field.map_err(std::io::Error::other));
+    let mut csv = AsyncReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true)
+        .create_reader(reader);
+
+    let headers: Vec<String> = csv
+        .headers()
+        .await
+        .context("The CSV header row cannot be read")
+        .map_err(e400)?
+        .iter()
+        .map(|h| h.to_lowercase())
+        .collect();
+    if !headers.iter().any(|h| h == "email") || !headers.iter().any(|h| h == "name") {
+        return Err(e400(anyhow::anyhow!(
+            "The CSV header row must contain an email and a name column"
+        )));
+    }
+
+    let mut report = ImportReport::default();
+    let mut records = csv.records();
+    while let Some(record) = records.next().await {
+        let (line, result) = match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line());
+                (line, import_row(context, &headers, record).await)
+            }
+            Err(e) => (
+                e.position().map_or(0, |p| p.line()),
+                Err(format!("The row cannot be parsed: {e}")),
+            ),
+        };
+        match result {
+            Ok(RowOutcome::Imported) => report.imported += 1,
+            Ok(RowOutcome::Duplicate) => report.duplicates.push(line),
+            Ok(RowOutcome::Suppressed) => report.suppressed.push(line),
+            Err(e) => report.errors.push((line, e)),
+        }
+    }
+    Ok(report)
+}
+
+#[tracing::instrument(name = "Import subscriber row", skip(context, headers, record))]
+async fn import_row(
+    context: &ImportContext<'_>,
+    headers: &[String],
+    record: StringRecord,
+) -> Result<RowOutcome, String> {
+    let mut email = String::new();
+    let mut name = String::new();
+    let mut tags = String::new();
+    let mut attributes = BTreeMap::new();
+    for (header, value) in headers.iter().zip(record.iter()) {
+        match header.as_str() {
+            "email" => email = value.to_string(),
+            "name" => name = value.to_string(),
+            "tags" => tags = value.to_string(),
+            _ => {
+                attributes.insert(header.clone(), value.to_string());
+            }
+        }
+    }
+    let new_subscriber = NewSubscriber::parse(
+        email,
+        name,
+        &tags,
+        attributes,
+        &context.state.attribute_rules,
+    )?;
+
+    match store_subscriber(context, new_subscriber).await {
+        Ok(outcome) => Ok(outcome),
+        Err(e) => {
+            tracing::error!(error.cause_chain = ?e, "Failed to import a subscriber");
+            Err("The subscriber could not be stored.".into())
+        }
+    }
+}
+
+async fn store_subscriber(
+    context: &ImportContext<'_>,
+    new_subscriber: NewSubscriber,
+) -> Result<RowOutcome, anyhow::Error> {
+    let state = context.state;
+    let mut txn = state
+        .db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+
+    // Erased, bounced or complaining addresses must not come back through an old list.
+    if is_suppressed(&mut txn, &state.hmac_secret, new_subscriber.email.as_ref())
+        .await
+        .context("Failed to check the suppression list.")?
+    {
+        return Ok(RowOutcome::Suppressed);
+    }
+
+    let subscriber_id = match insert_subscriber(&mut txn, &new_subscriber).await {
+        Ok(subscriber_id) => subscriber_id,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Ok(RowOutcome::Duplicate);
+        }
+        Err(e) => return Err(e).context("Failed to insert new subscriber in the database."),
+    };
+
+    let detail = match &context.mode {
+        ImportMode::Confirmed { consent_source } => consent_source.as_str(),
+        ImportMode::SendConfirmation => "Sent a confirmation email",
+    };
+    SubscriptionEvent::new(SubscriptionEventKind::Signup, new_subscriber.email.as_ref())
+        .source("csv_import")
+        .request(context.request)
+        .admin(context.admin_user_id)
+        .detail(detail)
+        .record(&mut txn)
+        .await
+        .context("Failed to record the import.")?;
+
+    match &context.mode {
+        ImportMode::Confirmed { consent_source } => {
+            mark_as_confirmed(&mut txn, subscriber_id, consent_source).await?;
+            txn.commit().await?;
+        }
+        ImportMode::SendConfirmation => {
+            let subscription_token = generate_subscription_token();
+            store_token(txn, subscriber_id, &subscription_token)
+                .await
+                .context("Failed to store the confirmation token for a new subscriber.")?;
+            send_confirmation_email(
+                &state.email_client,
+                &new_subscriber.email,
+                &state.base_url,
+                &subscription_token,
+            )
+            .await
+            .context("Failed to send a confirmation email")?;
+        }
+    }
+    Ok(RowOutcome::Imported)
+}
+
+async fn mark_as_confirmed(
+    txn: &mut sqlx::PgConnection,
+    subscriber_id: Uuid,
+    consent_source: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'confirmed', consent_source = $2
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        consent_source,
+    )
+    .execute(txn)
+    .await?;
+    Ok(())
+}
+
+fn render_report(report: &ImportReport) -> Markup {
+    let errors = report
+        .errors
+        .iter()
+        .map(|(line, e)| html!("<tr><td>{line}</td><td>{e}</td></tr>", line, e))
+        .collect::<Markup>();
+    html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Import report</title>
+  </head>
+  <body>
+    <p>Imported {imported} subscribers.</p>
+    <p>Skipped {duplicates} duplicates. {duplicate_rows}</p>
+    <p>Skipped {suppressed} suppressed addresses. {suppressed_rows}</p>
+    <p>Rejected {error_count} rows.</p>
+    <table>
+      <tr><th>Line</th><th>Error</th></tr>
+      {errors}
+    </table>
+    <p><a href="/admin/subscribers/import">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        imported = report.imported,
+        duplicates = report.duplicates.len(),
+        duplicate_rows = join_lines(&report.duplicates),
+        suppressed = report.suppressed.len(),
+        suppressed_rows = join_lines(&report.suppressed),
+        error_count = report.errors.len(),
+        errors,
+    )
+}
+
+fn join_lines(lines: &[u64]) -> String {
+    lines
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
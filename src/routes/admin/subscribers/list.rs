@@ -0,0 +1,146 @@
+use anyhow::Context;
+use axum::extract::{Query, State};
+use axum_messages::Messages;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    html,
+    html::Markup,
+    startup::AppState,
+    utils::{AppError, get_all_messages},
+};
+
+const PAGE_SIZE: usize = 50;
+const STATUSES: [&str; 5] = [
+    "pending_confirmation",
+    "confirmed",
+    "unsubscribed",
+    "bounced",
+    "complained",
+];
+
+#[derive(Deserialize, Debug)]
+pub struct ListParameters {
+    q: Option<String>,
+    status: Option<String>,
+    after: Option<Uuid>,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "List subscribers", skip(state, messages))]
+pub async fn list_subscribers(
+    State(state): State<AppState>,
+    messages: Messages,
+    Query(parameters): Query<ListParameters>,
+) -> Result<Markup, AppError> {
+    let message = get_all_messages(messages);
+    let search = parameters.q.unwrap_or_default().trim().to_string();
+    let status = parameters.status.unwrap_or_default();
+    let pattern = (!search.is_empty()).then(|| format!("%{}%", escape_like(&search)));
+
+    // Keyset pagination: the page continues right after the last subscriber of
+    // the previous one, in (subscribed_at, id) order.
+    let mut rows = sqlx::query!(
+        r#"
+SELECT id, email, name, status, subscribed_at
+FROM subscriptions
+WHERE
+    ($1::text IS NULL OR email ILIKE $1 OR name ILIKE $1)
+    AND ($2::text IS NULL OR status = $2)
+    AND (
+        $3::uuid IS NULL
+        OR (subscribed_at, id) < (
+            SELECT subscribed_at, id FROM subscriptions WHERE id = $3
+        )
+    )
+ORDER BY subscribed_at DESC, id DESC
+LIMIT $4
+        "#,
+        pattern,
+        (!status.is_empty()).then_some(&status),
+        parameters.after,
+        PAGE_SIZE as i64 + 1,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .context("Failed to list subscribers")?;
+
+    let has_next_page = rows.len() > PAGE_SIZE;
+    rows.truncate(PAGE_SIZE);
+
+    let table = rows
+        .iter()
+        .map(|r| {
+            html!(
+                r#"<tr><td><a href="/admin/subscribers/{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+                r.id,
+                r.email,
+                r.name,
+                r.status,
+                r.subscribed_at.format("%Y-%m-%d %H:%M UTC"),
+            )
+        })
+        .collect::<Markup>();
+
+    let next_page = match rows.last() {
+        Some(last) if has_next_page => html!(
+            r#"<p><a href="/admin/subscribers?q={}&status={}&after={}">Next page -&gt;</a></p>"#,
+            urlencoding::encode(&search),
+            urlencoding::encode(&status),
+            last.id,
+        ),
+        _ => Markup::default(),
+    };
+
+    let status_options = std::iter::once("")
+        .chain(STATUSES)
+        .map(|s| {
+            let selected = if s == status { " selected" } else { "" };
+            let label = if s.is_empty() { "Any status" } else { s };
+            html!(
+                r#"<option value="{s}"{selected}>{label}</option>"#,
+                s,
+                selected = Markup::raw(selected),
+                label,
+            )
+        })
+        .collect::<Markup>();
+
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Subscribers</title>
+  </head>
+  <body>
+    {message}
+    <form action="/admin/subscribers" method="get">
+      <input type="search" placeholder="Search by email or name" name="q" value="{search}" />
+      <select name="status">{status_options}</select>
+      <button type="submit">Search</button>
+    </form>
+    <table>
+      <tr><th>Email</th><th>Name</th><th>Status</th><th>Subscribed at</th></tr>
+      {table}
+    </table>
+    {next_page}
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        search,
+        status_options,
+        table,
+        next_page,
+    ))
+}
+
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
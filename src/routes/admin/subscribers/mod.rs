@@ -0,0 +1,9 @@
+mod detail;
+mod export;
+mod import;
+mod list;
+
+pub use detail::*;
+pub use export::export_subscribers;
+pub use import::*;
+pub use list::list_subscribers;
@@ -0,0 +1,81 @@
+use axum::{Extension, extract::State};
+use axum_messages::Messages;
+
+use crate::{
+    authentication::{CurrentUser, Role},
+    csrf::CsrfToken,
+    html,
+    html::{Markup, escape},
+    startup::AppState,
+    suppression::list_suppressions,
+    utils::{AppError, get_all_messages},
+};
+
+#[axum::debug_handler]
+pub async fn suppressions_form(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Result<Markup, AppError> {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+
+    let rows = list_suppressions(&state.db_pool)
+        .await?
+        .iter()
+        .map(|s| {
+            let email = match &s.email {
+                Some(email) => escape(email),
+                None => html!("<i>hash {}</i>", &s.email_hash[..12]),
+            };
+            let remove = if current_user.role >= Role::Publisher {
+                html!(
+                    r#"<form action="/admin/suppressions/{}/delete" method="post">{csrf_field}<button type="submit">Remove</button></form>"#,
+                    s.email_hash,
+                    csrf_field,
+                )
+            } else {
+                Markup::default()
+            };
+            html!(
+                "<tr><td>{email}</td><td>{}</td><td>{}</td><td>{}</td><td>{remove}</td></tr>",
+                s.reason,
+                s.detail.as_deref().unwrap_or(""),
+                s.created_at.format("%Y-%m-%d %H:%M UTC"),
+                email,
+                remove,
+            )
+        })
+        .collect::<Markup>();
+
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Suppression list</title>
+  </head>
+  <body>
+    {message}
+    <p>Suppressed addresses never receive an issue and cannot subscribe or be imported again.</p>
+    <form action="/admin/suppressions" method="post">
+      {csrf_field}
+      <input type="email" placeholder="Email" name="email" />
+      <input type="text" placeholder="Why is it suppressed?" name="detail" />
+      <button type="submit">Suppress</button>
+    </form>
+    <table>
+      <tr><th>Address</th><th>Reason</th><th>Detail</th><th>Since</th><th></th></tr>
+      {rows}
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        csrf_field,
+        rows,
+    ))
+}
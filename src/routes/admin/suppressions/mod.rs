@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::suppressions_form;
+pub use post::{add_suppression, delete_suppression};
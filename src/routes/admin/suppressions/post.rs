@@ -0,0 +1,79 @@
+use anyhow::Context;
+use axum::{
+    Form,
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_messages::Messages;
+use serde::Deserialize;
+
+use crate::{
+    domain::SubscriberEmail,
+    startup::AppState,
+    suppression::{SuppressionReason, remove_suppression, suppress},
+    utils::{AppError, e404},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct FormData {
+    email: String,
+    #[serde(default)]
+    detail: String,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Add suppression", skip(state, messages))]
+pub async fn add_suppression(
+    State(state): State<AppState>,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Result<Redirect, AppError> {
+    let email = match SubscriberEmail::parse(form.email) {
+        Ok(email) => email,
+        Err(e) => {
+            messages.error(e);
+            return Ok(Redirect::to("/admin/suppressions"));
+        }
+    };
+    let detail = form.detail.trim();
+
+    let mut txn = state
+        .db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    suppress(
+        &mut txn,
+        &state.hmac_secret,
+        email.as_ref(),
+        SuppressionReason::Manual,
+        (!detail.is_empty()).then_some(detail),
+    )
+    .await
+    .context("Failed to add the address to the suppression list")?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE lower(subscriber_email) = lower($1)"#,
+        email.as_ref(),
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to cancel pending deliveries")?;
+    txn.commit().await.context("Failed to commit")?;
+
+    messages.info("The address has been suppressed.");
+    Ok(Redirect::to("/admin/suppressions"))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Delete suppression", skip(state, messages))]
+pub async fn delete_suppression(
+    State(state): State<AppState>,
+    messages: Messages,
+    Path(email_hash): Path<String>,
+) -> Result<Redirect, AppError> {
+    if !remove_suppression(&state.db_pool, &email_hash).await? {
+        return Err(e404(anyhow::anyhow!("The suppression does not exist")));
+    }
+    messages.info("The address has been removed from the suppression list.");
+    Ok(Redirect::to("/admin/suppressions"))
+}
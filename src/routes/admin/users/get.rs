@@ -0,0 +1,116 @@
+use axum::extract::State;
+use axum_messages::Messages;
+
+use crate::{
+    authentication::{Role, list_pending_invitations, list_users},
+    csrf::CsrfToken,
+    html,
+    html::Markup,
+    startup::AppState,
+    utils::{AppError, get_all_messages},
+};
+
+fn role_options(selected: Option<Role>) -> Markup {
+    Role::ALL
+        .iter()
+        .map(|role| {
+            let selected = if Some(*role) == selected {
+                " selected"
+            } else {
+                ""
+            };
+            html!(
+                r#"<option value="{role}"{selected}>{role}</option>"#,
+                role = role.as_str(),
+                selected = Markup::raw(selected),
+            )
+        })
+        .collect()
+}
+
+#[axum::debug_handler]
+pub async fn users_form(
+    State(state): State<AppState>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Result<Markup, AppError> {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+
+    let rows = list_users(&state.db_pool)
+        .await?
+        .iter()
+        .map(|u| {
+            let (status, toggle) = match u.disabled_at {
+                Some(disabled_at) => (
+                    format!("disabled since {}", disabled_at.format("%Y-%m-%d")),
+                    "enable",
+                ),
+                None => ("active".to_string(), "disable"),
+            };
+            html!(
+                r#"<tr><td>{}</td><td>{}</td><td><form action="/admin/users/{id}/role" method="post">{csrf_field}<select name="role">{}</select><button type="submit">Change</button></form></td><td>{status}</td><td>{}</td><td><form action="/admin/users/{id}/{toggle}" method="post">{csrf_field}<button type="submit">{toggle}</button></form><form action="/admin/users/{id}/delete" method="post" onsubmit="return confirm('Delete this user?');">{csrf_field}<button type="submit">delete</button></form></td></tr>"#,
+                u.username,
+                u.email.as_deref().unwrap_or("-"),
+                role_options(Some(u.role)),
+                if u.two_factor_enabled { "yes" } else { "no" },
+                id = u.user_id,
+                csrf_field,
+                status,
+                toggle,
+            )
+        })
+        .collect::<Markup>();
+
+    let invitations = list_pending_invitations(&state.db_pool)
+        .await?
+        .iter()
+        .map(|i| {
+            html!(
+                r#"<tr><td>{}</td><td>{}</td><td>{}</td><td><form action="/admin/users/invitations/{}/revoke" method="post">{csrf_field}<button type="submit">revoke</button></form></td></tr>"#,
+                i.email,
+                i.role.as_str(),
+                i.expires_at.format("%Y-%m-%d %H:%M"),
+                i.invitation_id,
+                csrf_field,
+            )
+        })
+        .collect::<Markup>();
+
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Users</title>
+  </head>
+  <body>
+    {message}
+    <table>
+      <tr><th>Username</th><th>Email</th><th>Role</th><th>Status</th><th>2FA</th><th></th></tr>
+      {rows}
+    </table>
+    <p>Pending invitations:</p>
+    <table>
+      <tr><th>Email</th><th>Role</th><th>Expires</th><th></th></tr>
+      {invitations}
+    </table>
+    <p>Invite a user, they will choose their own username and password:</p>
+    <form action="/admin/users/invite" method="post">
+      {csrf_field}
+      <input type="email" placeholder="Email" name="email" />
+      <select name="role">{options}</select>
+      <button type="submit">Send invitation</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+  </body>
+</html>
+            "#,
+        message,
+        rows,
+        invitations,
+        csrf_field,
+        options = role_options(Some(Role::Viewer)),
+    ))
+}
@@ -0,0 +1,7 @@
+mod get;
+mod post;
+
+pub use get::users_form;
+pub use post::{
+    change_user_role, delete_user, disable_user, enable_user, invite_user, revoke_invitation,
+};
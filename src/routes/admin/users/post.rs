@@ -0,0 +1,187 @@
+use anyhow::Context;
+use axum::{
+    Extension, Form,
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_messages::Messages;
+use htmlescape::encode_minimal;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{self, CurrentUser, INVITATION_VALIDITY, Role, create_invitation},
+    domain::SubscriberEmail,
+    email_client::EmailClient,
+    routes::SignedInvitationLink,
+    startup::AppState,
+    utils::{AppError, e404},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct InviteForm {
+    email: String,
+    role: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RoleForm {
+    role: String,
+}
+
+fn users_redirect(messages: Messages, message: impl Into<String>) -> Redirect {
+    messages.info(message.into());
+    Redirect::to("/admin/users")
+}
+
+fn users_error(messages: Messages, message: impl Into<String>) -> Redirect {
+    messages.error(message.into());
+    Redirect::to("/admin/users")
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Invite user", skip(state, messages, current_user, form))]
+pub async fn invite_user(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    messages: Messages,
+    Form(form): Form<InviteForm>,
+) -> Result<Redirect, AppError> {
+    let email = match SubscriberEmail::parse(form.email.trim().to_string()) {
+        Ok(email) => email,
+        Err(e) => return Ok(users_error(messages, e)),
+    };
+    let role = match Role::parse(&form.role) {
+        Ok(role) => role,
+        Err(e) => return Ok(users_error(messages, e)),
+    };
+
+    let invitation = create_invitation(&state.db_pool, &email, role, current_user.user_id).await?;
+    let link = SignedInvitationLink::new(&state, &invitation);
+    send_invitation_email(
+        &state.email_client,
+        &email,
+        &state.base_url,
+        &current_user.username,
+        &link,
+    )
+    .await
+    .context("Failed to send the invitation email")?;
+    Ok(users_redirect(messages, "The invitation has been sent."))
+}
+
+async fn send_invitation_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    base_url: &str,
+    invited_by: &str,
+    link: &SignedInvitationLink,
+) -> Result<(), reqwest::Error> {
+    let invitation_link = format!("{base_url}/invitations/accept?{}", link.query_string());
+    let validity = INVITATION_VALIDITY.num_hours();
+    let plain_body = format!(
+        "{invited_by} invited you to help manage the newsletter.\n\
+        Visit {invitation_link} within {validity} hours to choose a username and a password.\n\
+        If you were not expecting this, you can ignore this email."
+    );
+    let html_body = format!(
+        "{} invited you to help manage the newsletter.<br />\
+        Click <a href=\"{invitation_link}\">here</a> within {validity} hours to choose a username and a password.<br />\
+        If you were not expecting this, you can ignore this email.",
+        encode_minimal(invited_by),
+    );
+    email_client
+        .send_email(email, "You are invited", &html_body, &plain_body)
+        .await
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Revoke invitation", skip(state, messages))]
+pub async fn revoke_invitation(
+    State(state): State<AppState>,
+    messages: Messages,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    if !authentication::revoke_invitation(&state.db_pool, invitation_id).await? {
+        return Err(e404(anyhow::anyhow!("There is no pending invitation")));
+    }
+    Ok(users_redirect(messages, "The invitation has been revoked."))
+}
+
+/// Owners cannot demote, disable or delete themselves, so there always is an
+/// active owner left.
+fn is_self(current_user: &CurrentUser, user_id: Uuid) -> bool {
+    current_user.user_id == user_id
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Change user role", skip(state, messages, current_user))]
+pub async fn change_user_role(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    messages: Messages,
+    Path(user_id): Path<Uuid>,
+    Form(form): Form<RoleForm>,
+) -> Result<Redirect, AppError> {
+    if is_self(&current_user, user_id) {
+        return Ok(users_error(messages, "You cannot change your own role."));
+    }
+    let role = match Role::parse(&form.role) {
+        Ok(role) => role,
+        Err(e) => return Ok(users_error(messages, e)),
+    };
+    if !authentication::set_user_role(&state.db_pool, user_id, role).await? {
+        return Err(e404(anyhow::anyhow!("The user does not exist")));
+    }
+    Ok(users_redirect(messages, "The role has been changed."))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Disable user", skip(state, messages, current_user))]
+pub async fn disable_user(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    messages: Messages,
+    Path(user_id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    if is_self(&current_user, user_id) {
+        return Ok(users_error(
+            messages,
+            "You cannot disable your own account.",
+        ));
+    }
+    if !authentication::set_user_disabled(&state.db_pool, user_id, true).await? {
+        return Err(e404(anyhow::anyhow!("The user does not exist")));
+    }
+    Ok(users_redirect(messages, "The user has been disabled."))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Enable user", skip(state, messages))]
+pub async fn enable_user(
+    State(state): State<AppState>,
+    messages: Messages,
+    Path(user_id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    if !authentication::set_user_disabled(&state.db_pool, user_id, false).await? {
+        return Err(e404(anyhow::anyhow!("The user does not exist")));
+    }
+    Ok(users_redirect(messages, "The user has been enabled."))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Delete user", skip(state, messages, current_user))]
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    messages: Messages,
+    Path(user_id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    if is_self(&current_user, user_id) {
+        return Ok(users_error(messages, "You cannot delete your own account."));
+    }
+    if !authentication::delete_user(&state.db_pool, user_id).await? {
+        return Err(e404(anyhow::anyhow!("The user does not exist")));
+    }
+    Ok(users_redirect(messages, "The user has been deleted."))
+}
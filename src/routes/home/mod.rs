@@ -1,5 +1,17 @@
-use axum::response::{Html, IntoResponse};
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+};
 
-pub async fn home() -> impl IntoResponse {
-    Html(include_str!("home.html"))
+use crate::{anti_bot::HONEYPOT_FIELD, startup::AppState};
+
+pub async fn home(State(state): State<AppState>) -> impl IntoResponse {
+    // Every page view gets a fresh token, so we can tell how long the form was
+    // on screen before it was submitted.
+    Html(
+        include_str!("home.html")
+            .replace("{form_token}", state.anti_bot.issue_token().as_ref())
+            .replace("{honeypot_field}", HONEYPOT_FIELD)
+            .replace("{challenge}", &state.anti_bot.challenge_widget()),
+    )
 }
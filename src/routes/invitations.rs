@@ -0,0 +1,214 @@
+use axum::{
+    Form,
+    extract::{Query, State},
+    response::Redirect,
+};
+use axum_messages::Messages;
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{
+        self, Invitation, InvitationError, NewAdminUser, PasswordError, get_pending_invitation,
+        validate_password_security,
+    },
+    csrf::CsrfToken,
+    domain::SubscriberEmail,
+    html,
+    html::Markup,
+    signing,
+    startup::AppState,
+    utils::{AppError, e401, get_all_messages},
+};
+
+/// The query string of the link sent to an invitee, it proves we invited
+/// them and until when the link may be used.
+#[derive(Deserialize, Debug)]
+pub struct SignedInvitationLink {
+    invitation_id: Uuid,
+    expires: i64,
+    signature: String,
+}
+
+impl SignedInvitationLink {
+    pub(crate) fn new(state: &AppState, invitation: &Invitation) -> Self {
+        let expires = invitation.expires_at.timestamp();
+        let signature = signing::sign(
+            &state.hmac_secret,
+            &Self::message(invitation.invitation_id, expires),
+        );
+        SignedInvitationLink {
+            invitation_id: invitation.invitation_id,
+            expires,
+            signature,
+        }
+    }
+
+    fn message(invitation_id: Uuid, expires: i64) -> String {
+        format!("user_invitation\n{invitation_id}\n{expires}")
+    }
+
+    /// The link must be ours and the invitation still pending: it is neither
+    /// used, revoked nor expired.
+    async fn verify(&self, state: &AppState) -> Result<Invitation, AppError> {
+        if self.expires < Utc::now().timestamp() {
+            return Err(e401(anyhow::anyhow!("This invitation has expired")));
+        }
+        if !signing::verify(
+            &state.hmac_secret,
+            &Self::message(self.invitation_id, self.expires),
+            &self.signature,
+        ) {
+            return Err(e401(anyhow::anyhow!("This invitation is not valid")));
+        }
+        get_pending_invitation(&state.db_pool, self.invitation_id)
+            .await?
+            .ok_or_else(|| e401(InvitationError::NoLongerValid))
+    }
+
+    pub(crate) fn query_string(&self) -> String {
+        format!(
+            "invitation_id={}&expires={}&signature={}",
+            self.invitation_id,
+            self.expires,
+            urlencoding::encode(&self.signature),
+        )
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AcceptInvitationForm {
+    invitation_id: Uuid,
+    expires: i64,
+    signature: String,
+    username: String,
+    password: SecretString,
+    password_check: SecretString,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Show invitation", skip(state, csrf_token, messages))]
+pub async fn invitation_form(
+    State(state): State<AppState>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+    Query(link): Query<SignedInvitationLink>,
+) -> Result<Markup, AppError> {
+    let invitation = link.verify(&state).await?;
+    let message = get_all_messages(messages);
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Create your account</title>
+  </head>
+  <body>
+    {message}
+    <p>You have been invited to join as {role} with the address {email}.</p>
+    <form action="/invitations/accept" method="post">
+      {csrf_field}
+      <input type="hidden" name="invitation_id" value="{invitation_id}" />
+      <input type="hidden" name="expires" value="{expires}" />
+      <input type="hidden" name="signature" value="{signature}" />
+      <label
+        >Username
+        <input type="text" placeholder="Choose a username" name="username" />
+      </label>
+      <br />
+      <label
+        >Password
+        <input type="password" placeholder="Choose a password" name="password" />
+      </label>
+      <br />
+      <label
+        >Confirm password
+        <input type="password" placeholder="Type the password again" name="password_check" />
+      </label>
+      <br />
+      <p>{requirements}</p>
+      <button type="submit">Create my account</button>
+    </form>
+  </body>
+</html>
+        "#,
+        message,
+        csrf_field = csrf_token.field(),
+        role = invitation.role.as_str(),
+        email = invitation.email,
+        invitation_id = link.invitation_id,
+        expires = link.expires,
+        signature = link.signature,
+        requirements = state.password_policy.requirements(),
+    ))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Accept invitation", skip(state, messages, form))]
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    messages: Messages,
+    Form(form): Form<AcceptInvitationForm>,
+) -> Result<Redirect, AppError> {
+    let link = SignedInvitationLink {
+        invitation_id: form.invitation_id,
+        expires: form.expires,
+        signature: form.signature,
+    };
+    let invitation = link.verify(&state).await?;
+    let retry = || Redirect::to(&format!("/invitations/accept?{}", link.query_string()));
+
+    let username = form.username.trim().to_string();
+    if username.is_empty() {
+        messages.error("The username cannot be empty.");
+        return Ok(retry());
+    }
+    if form.password.expose_secret() != form.password_check.expose_secret() {
+        messages.error("You entered two different passwords - the field values must match.");
+        return Ok(retry());
+    }
+    match validate_password_security(
+        &state.password_policy,
+        &form.password,
+        &[&username, &invitation.email],
+    )
+    .await
+    {
+        Ok(()) => {}
+        Err(PasswordError::Rejected(e)) => {
+            messages.error(e);
+            return Ok(retry());
+        }
+        Err(PasswordError::UnexpectedError(e)) => return Err(e.into()),
+    }
+    let email = SubscriberEmail::parse(invitation.email).map_err(|e| anyhow::anyhow!(e))?;
+
+    let user = NewAdminUser {
+        username,
+        email: Some(email),
+        role: invitation.role,
+        password: form.password,
+    };
+    match authentication::accept_invitation(
+        &state.db_pool,
+        invitation.invitation_id,
+        user,
+        &state.password_hashing,
+    )
+    .await
+    {
+        Ok(_) => {
+            messages.info("Your account has been created, you can now log in.");
+            Ok(Redirect::to("/login"))
+        }
+        Err(e @ InvitationError::UsernameTaken(_)) => {
+            messages.error(e.to_string());
+            Ok(retry())
+        }
+        Err(e @ InvitationError::NoLongerValid) => Err(e401(e)),
+        Err(InvitationError::UnexpectedError(e)) => Err(e.into()),
+    }
+}
@@ -1,12 +1,12 @@
-use axum::response::{Html, IntoResponse};
 use axum_messages::Messages;
 
-use crate::utils::get_all_messages;
+use crate::{csrf::CsrfToken, html, html::Markup, utils::get_all_messages};
 
 #[axum::debug_handler]
-pub async fn login_form(messages: Messages) -> impl IntoResponse {
+pub async fn login_form(csrf_token: CsrfToken, messages: Messages) -> Markup {
     let message = get_all_messages(messages);
-    Html(format!(
+    let csrf_field = csrf_token.field();
+    html!(
         r#"
 <!doctype html>
 <html lang="en">
@@ -17,6 +17,7 @@ pub async fn login_form(messages: Messages) -> impl IntoResponse {
   <body>
     {message}
     <form action="/login" method="post">
+      {csrf_field}
       <label
         >Username
         <input type="text" placeholder="Enter Username" name="username" />
@@ -28,8 +29,11 @@ pub async fn login_form(messages: Messages) -> impl IntoResponse {
 
       <button type="submit">Login</button>
     </form>
+    <p><a href="/login/forgot">Forgot your password?</a></p>
   </body>
 </html>
-            "#
-    ))
+            "#,
+        message,
+        csrf_field,
+    )
 }
@@ -1,5 +1,11 @@
 mod get;
+mod password_reset;
 mod post;
+mod two_factor;
 
 pub use get::login_form;
+pub use password_reset::{
+    forgot_password_form, request_password_reset, reset_password, reset_password_form,
+};
 pub use post::login;
+pub use two_factor::{two_factor, two_factor_form};
@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::{
+    Form,
+    extract::{Query, State},
+    response::Redirect,
+};
+use axum_messages::Messages;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    authentication::{
+        self, PASSWORD_RESET_VALIDITY, PasswordError, ResetRecipient, check_password_reset,
+        find_reset_recipients, get_username, issue_password_reset, validate_password_security,
+    },
+    csrf::CsrfToken,
+    domain::SubscriberEmail,
+    email_client::EmailClient,
+    html,
+    html::Markup,
+    startup::AppState,
+    utils::{AppError, e401, get_all_messages},
+};
+
+const RESET_REQUESTED: &str =
+    "If an account uses this address, we have sent it a link to choose a new password.";
+
+#[derive(Deserialize, Debug)]
+pub struct ForgotPasswordForm {
+    email: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ResetLink {
+    token: SecretString,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ResetPasswordForm {
+    token: SecretString,
+    new_password: SecretString,
+    new_password_check: SecretString,
+}
+
+#[axum::debug_handler]
+pub async fn forgot_password_form(csrf_token: CsrfToken, messages: Messages) -> Markup {
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+    html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Forgot your password</title>
+  </head>
+  <body>
+    {message}
+    <p>Enter the email address of your account to receive a link to choose a new password.</p>
+    <form action="/login/forgot" method="post">
+      {csrf_field}
+      <input type="email" placeholder="Email" name="email" />
+      <button type="submit">Send me a link</button>
+    </form>
+    <p><a href="/login">&lt;- Back to login</a></p>
+  </body>
+</html>
+        "#,
+        message,
+        csrf_field,
+    )
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Request password reset", skip(state, messages, form))]
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    messages: Messages,
+    Form(form): Form<ForgotPasswordForm>,
+) -> Redirect {
+    // The answer is the same whether or not an account uses the address, so
+    // the form cannot be used to find out who the admins are.
+    messages.info(RESET_REQUESTED);
+    // The links are sent in the background, otherwise a known address would
+    // take longer to answer, or fail when the email API is down.
+    if let Ok(email) = SubscriberEmail::parse(form.email) {
+        tokio::spawn(send_password_reset_links(
+            state.db_pool.clone(),
+            state.email_client.clone(),
+            state.base_url.clone(),
+            email,
+        ));
+    }
+    Redirect::to("/login/forgot")
+}
+
+#[tracing::instrument(
+    name = "Send password reset links",
+    skip(pool, email_client, base_url, email)
+)]
+async fn send_password_reset_links(
+    pool: PgPool,
+    email_client: Arc<EmailClient>,
+    base_url: String,
+    email: SubscriberEmail,
+) {
+    let send = async {
+        for recipient in find_reset_recipients(&pool, email.as_ref()).await? {
+            let token = issue_password_reset(&pool, recipient.user_id).await?;
+            send_password_reset_email(&email_client, &email, &base_url, &recipient, &token)
+                .await
+                .context("Failed to send a password reset email")?;
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+    if let Err(e) = send.await {
+        tracing::error!(error.cause_chain = ?e, "Failed to send password reset links");
+    }
+}
+
+async fn send_password_reset_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    base_url: &str,
+    recipient: &ResetRecipient,
+    token: &SecretString,
+) -> Result<(), reqwest::Error> {
+    let reset_link = format!(
+        "{base_url}/login/reset?token={}",
+        urlencoding::encode(token.expose_secret())
+    );
+    let validity = PASSWORD_RESET_VALIDITY.num_minutes();
+    let plain_body = format!(
+        "Someone asked to reset the password of the account {}.\n\
+        Visit {reset_link} within {validity} minutes to choose a new password.\n\
+        If it was not you, you can ignore this email.",
+        recipient.username,
+    );
+    let html_body = format!(
+        "Someone asked to reset the password of the account {}.<br />\
+        Click <a href=\"{reset_link}\">here</a> within {validity} minutes to choose a new password.<br />\
+        If it was not you, you can ignore this email.",
+        htmlescape::encode_minimal(&recipient.username),
+    );
+    email_client
+        .send_email(email, "Reset your password", &html_body, &plain_body)
+        .await
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(
+    name = "Show password reset form",
+    skip(state, csrf_token, messages, link)
+)]
+pub async fn reset_password_form(
+    State(state): State<AppState>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+    Query(link): Query<ResetLink>,
+) -> Result<Markup, AppError> {
+    if check_password_reset(&state.db_pool, &link.token)
+        .await?
+        .is_none()
+    {
+        return Err(e401(anyhow::anyhow!(
+            "This link is not valid or has expired"
+        )));
+    }
+    let message = get_all_messages(messages);
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Choose a new password</title>
+  </head>
+  <body>
+    {message}
+    <p>{requirements}</p>
+    <form action="/login/reset" method="post">
+      {csrf_field}
+      <input type="hidden" name="token" value="{token}" />
+      <label
+        >New password
+        <input type="password" placeholder="Enter new password" name="new_password" />
+      </label>
+      <br />
+      <label
+        >Confirm new password
+        <input type="password" placeholder="Type the new password again" name="new_password_check" />
+      </label>
+      <br />
+      <button type="submit">Change password</button>
+    </form>
+  </body>
+</html>
+        "#,
+        message,
+        csrf_field = csrf_token.field(),
+        token = link.token.expose_secret(),
+        requirements = state.password_policy.requirements(),
+    ))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Reset password", skip(state, messages, form))]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    messages: Messages,
+    Form(form): Form<ResetPasswordForm>,
+) -> Result<Redirect, AppError> {
+    let retry = || {
+        Redirect::to(&format!(
+            "/login/reset?token={}",
+            urlencoding::encode(form.token.expose_secret())
+        ))
+    };
+    if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
+        messages.error("You entered two different new passwords - the field values must match.");
+        return Ok(retry());
+    }
+    let Some(user_id) = check_password_reset(&state.db_pool, &form.token).await? else {
+        return Err(e401(anyhow::anyhow!(
+            "This link is not valid or has expired"
+        )));
+    };
+    let username = get_username(&user_id, &state.db_pool).await?;
+    match validate_password_security(&state.password_policy, &form.new_password, &[&username]).await
+    {
+        Ok(()) => {}
+        Err(PasswordError::Rejected(e)) => {
+            messages.error(e);
+            return Ok(retry());
+        }
+        Err(PasswordError::UnexpectedError(e)) => return Err(e.into()),
+    }
+
+    let Some(user_id) = authentication::reset_password(
+        &state.db_pool,
+        &form.token,
+        form.new_password,
+        &state.password_hashing,
+    )
+    .await?
+    else {
+        return Err(e401(anyhow::anyhow!(
+            "This link is not valid or has expired"
+        )));
+    };
+    // Whoever knew the old password is logged out everywhere.
+    state.session_index.revoke_all(user_id, None).await?;
+    messages.info("Your password has been changed, you can now log in.");
+    Ok(Redirect::to("/login"))
+}
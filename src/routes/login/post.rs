@@ -1,11 +1,17 @@
 use axum::{Form, extract::State, response::Redirect};
 use axum_messages::Messages;
+use chrono::Utc;
 use secrecy::SecretString;
 use serde::Deserialize;
+use uuid::Uuid;
 
 use crate::{
-    authentication::{AuthError, Credentials, validate_credentials},
-    session_state::TypedSession,
+    authentication::{
+        AuthError, Credentials, clear_failed_logins, get_totp, has_users, login_blocked_until,
+        notify_lockout, record_failed_login, validate_credentials,
+    },
+    request_metadata::RequestMetadata,
+    session_state::{SessionMetadata, TypedSession},
     startup::AppState,
 };
 
@@ -18,44 +24,140 @@ pub struct FormData {
 #[axum::debug_handler]
 #[tracing::instrument(
     name = "Login",
-    skip(state, form, messages, session),
+    skip(state, form, messages, session, request),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
     State(state): State<AppState>,
     messages: Messages,
     session: TypedSession,
+    request: RequestMetadata,
     Form(form): Form<FormData>,
 ) -> Redirect {
+    let username = form.username;
+    let ip = request.source_ip.as_deref();
+    tracing::Span::current().record("username", tracing::field::display(&username));
+
+    match has_users(&state.db_pool).await {
+        Ok(true) => {}
+        Ok(false) => return login_redirect(LoginError::NotSetUp, messages),
+        Err(e) => return login_redirect(LoginError::UnexpectedError(e), messages),
+    }
+    if let Err(e) = ensure_not_locked_out(&state, &username, ip).await {
+        return login_redirect(e, messages);
+    }
+
     let credentials = Credentials {
-        username: form.username,
+        username: username.clone(),
         password: form.password,
     };
-
-    tracing::Span::current().record("username", tracing::field::display(&credentials.username));
-    match validate_credentials(credentials, &state.db_pool).await {
+    match validate_credentials(credentials, &state.password_hashing, &state.db_pool).await {
         Ok(user_id) => {
             tracing::Span::current().record("user_id", tracing::field::display(&user_id));
 
+            let has_second_factor =
+                match get_totp(&state.db_pool, &state.hmac_secret, user_id).await {
+                    Ok(totp) => totp.is_some(),
+                    Err(e) => return login_redirect(LoginError::UnexpectedError(e), messages),
+                };
             if let Err(e) = session.renew().await {
                 return login_redirect(LoginError::UnexpectedError(e.into()), messages);
             }
-            if let Err(e) = session.insert_user_id(user_id).await {
-                return login_redirect(LoginError::UnexpectedError(e.into()), messages);
+            if has_second_factor {
+                // Failures stay on the books until the second factor is verified.
+                if let Err(e) = session.insert_pending_user_id(user_id).await {
+                    return login_redirect(LoginError::UnexpectedError(e.into()), messages);
+                }
+                return Redirect::to("/login/two_factor");
+            }
+            match complete_login(&state, &session, user_id, &username, &request).await {
+                Ok(redirect) => redirect,
+                Err(e) => login_redirect(e, messages),
             }
-            Redirect::to("/admin/dashboard")
         }
-        Err(e) => {
-            let e = match e {
-                AuthError::InvalidCredentials(error) => LoginError::AuthError(error),
-                AuthError::UnexpectedError(error) => LoginError::UnexpectedError(error),
-            };
+        Err(AuthError::InvalidCredentials(error)) => {
+            let e = record_failure(&state, &username, ip, LoginError::AuthError(error)).await;
             login_redirect(e, messages)
         }
+        Err(AuthError::UnexpectedError(error)) => {
+            login_redirect(LoginError::UnexpectedError(error), messages)
+        }
+    }
+}
+
+/// Locked out logins are refused before any secret is even looked at.
+pub(super) async fn ensure_not_locked_out(
+    state: &AppState,
+    username: &str,
+    ip: Option<&str>,
+) -> Result<(), LoginError> {
+    match login_blocked_until(&state.db_pool, username, ip).await? {
+        Some(locked_until) => Err(LoginError::Throttled(
+            (locked_until - Utc::now()).num_seconds().max(1),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Count a failed attempt against `username` and `ip`, returns the error to
+/// show for it.
+pub(super) async fn record_failure(
+    state: &AppState,
+    username: &str,
+    ip: Option<&str>,
+    error: LoginError,
+) -> LoginError {
+    let locked_out =
+        match record_failed_login(&state.db_pool, &state.login_throttling, username, ip).await {
+            Ok(locked_out) => locked_out,
+            Err(e) => return LoginError::UnexpectedError(e),
+        };
+    if locked_out {
+        // The attempt was refused already, a failed email must not change that.
+        if let Err(e) = notify_lockout(
+            &state.db_pool,
+            &state.email_client,
+            username,
+            ip,
+            state.login_throttling.lockout_minutes,
+        )
+        .await
+        {
+            tracing::error!(error.cause_chain = ?e, "Failed to notify a lockout");
+        }
+    }
+    error
+}
+
+pub(super) async fn complete_login(
+    state: &AppState,
+    session: &TypedSession,
+    user_id: Uuid,
+    username: &str,
+    request: &RequestMetadata,
+) -> Result<Redirect, LoginError> {
+    clear_failed_logins(&state.db_pool, username).await?;
+    session
+        .insert_user_id(user_id)
+        .await
+        .map_err(|e| LoginError::UnexpectedError(e.into()))?;
+    let metadata = SessionMetadata::new(request);
+    session
+        .insert_metadata(&metadata)
+        .await
+        .map_err(|e| LoginError::UnexpectedError(e.into()))?;
+    session.keep_until(state.session_settings.end_of(&metadata));
+    if let Some(session_id) = session
+        .id()
+        .await
+        .map_err(|e| LoginError::UnexpectedError(e.into()))?
+    {
+        state.session_index.add(user_id, session_id).await?;
     }
+    Ok(Redirect::to("/admin/dashboard"))
 }
 
-fn login_redirect(e: LoginError, messages: Messages) -> Redirect {
+pub(super) fn login_redirect(e: LoginError, messages: Messages) -> Redirect {
     tracing::error!(
         error.message = %e,
         error.cause_chain = ?e,
@@ -69,6 +171,12 @@ fn login_redirect(e: LoginError, messages: Messages) -> Redirect {
 pub enum LoginError {
     #[error("Authentication failed")]
     AuthError(#[source] anyhow::Error),
+    #[error(
+        "No account exists yet, create the first owner with the setup link printed in the server logs"
+    )]
+    NotSetUp,
+    #[error("Too many failed login attempts, try again in {0} seconds")]
+    Throttled(i64),
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
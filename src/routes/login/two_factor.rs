@@ -0,0 +1,113 @@
+use axum::{
+    Form,
+    extract::State,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use serde::Deserialize;
+
+use super::post::{
+    LoginError, complete_login, ensure_not_locked_out, login_redirect, record_failure,
+};
+use crate::{
+    authentication::{get_username, verify_second_factor},
+    csrf::CsrfToken,
+    html,
+    request_metadata::RequestMetadata,
+    session_state::TypedSession,
+    startup::AppState,
+    utils::{AppError, e500, get_all_messages},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct FormData {
+    code: String,
+}
+
+#[axum::debug_handler]
+pub async fn two_factor_form(
+    session: TypedSession,
+    csrf_token: CsrfToken,
+    messages: Messages,
+) -> Result<Response, AppError> {
+    if session.get_pending_user_id().await.map_err(e500)?.is_none() {
+        return Ok(Redirect::to("/login").into_response());
+    }
+    let message = get_all_messages(messages);
+    let csrf_field = csrf_token.field();
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Two-factor authentication</title>
+  </head>
+  <body>
+    {message}
+    <form action="/login/two_factor" method="post">
+      {csrf_field}
+      <label
+        >Code from your authenticator app, or a recovery code
+        <input type="text" inputmode="numeric" autocomplete="one-time-code" name="code" />
+      </label>
+
+      <button type="submit">Verify</button>
+    </form>
+  </body>
+</html>
+            "#,
+        message,
+        csrf_field,
+    )
+    .into_response())
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(
+    name = "Verify second factor",
+    skip(state, form, messages, session, request),
+    fields(user_id=tracing::field::Empty)
+)]
+pub async fn two_factor(
+    State(state): State<AppState>,
+    messages: Messages,
+    session: TypedSession,
+    request: RequestMetadata,
+    Form(form): Form<FormData>,
+) -> Redirect {
+    let user_id = match session.get_pending_user_id().await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return Redirect::to("/login"),
+        Err(e) => return login_redirect(LoginError::UnexpectedError(e.into()), messages),
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    let username = match get_username(&user_id, &state.db_pool).await {
+        Ok(username) => username,
+        Err(e) => return login_redirect(LoginError::UnexpectedError(e), messages),
+    };
+    let ip = request.source_ip.as_deref();
+
+    if let Err(e) = ensure_not_locked_out(&state, &username, ip).await {
+        return login_redirect(e, messages);
+    }
+    match verify_second_factor(&state.db_pool, &state.hmac_secret, user_id, &form.code).await {
+        Ok(true) => {}
+        Ok(false) => {
+            let error = LoginError::AuthError(anyhow::anyhow!("Invalid second factor"));
+            let e = record_failure(&state, &username, ip, error).await;
+            // The password was right, let them try another code while not locked out.
+            messages.error(e.to_string());
+            return Redirect::to("/login/two_factor");
+        }
+        Err(e) => return login_redirect(LoginError::UnexpectedError(e), messages),
+    }
+
+    if let Err(e) = session.remove_pending_user_id().await {
+        return login_redirect(LoginError::UnexpectedError(e.into()), messages);
+    }
+    match complete_login(&state, &session, user_id, &username, &request).await {
+        Ok(redirect) => redirect,
+        Err(e) => login_redirect(e, messages),
+    }
+}
@@ -1,13 +1,21 @@
 mod admin;
 mod health_check;
 mod home;
+mod invitations;
 mod login;
+mod setup;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_data;
+mod webhooks;
 
 pub use admin::*;
 pub use health_check::*;
 pub use home::*;
+pub use invitations::*;
 pub use login::*;
+pub use setup::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_data::*;
+pub use webhooks::*;
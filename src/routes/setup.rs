@@ -0,0 +1,168 @@
+use axum::{
+    Form,
+    extract::{Query, State},
+    response::Redirect,
+};
+use axum_messages::Messages;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::{
+    authentication::{
+        NewAdminUser, PasswordError, Role, UserError, create_first_owner, has_users,
+        validate_password_security,
+    },
+    csrf::CsrfToken,
+    domain::SubscriberEmail,
+    html,
+    html::Markup,
+    startup::AppState,
+    utils::{AppError, e401, e404, get_all_messages},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct SetupLink {
+    token: SecretString,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SetupForm {
+    token: SecretString,
+    username: String,
+    #[serde(default)]
+    email: String,
+    password: SecretString,
+    password_check: SecretString,
+}
+
+/// Setup is only possible with the token printed at startup, and only until
+/// the first owner exists.
+async fn ensure_setup_allowed(state: &AppState, token: &SecretString) -> Result<(), AppError> {
+    let Some(setup_token) = &state.setup_token else {
+        return Err(e404(anyhow::anyhow!("Setup is already done")));
+    };
+    if has_users(&state.db_pool).await? {
+        return Err(e404(anyhow::anyhow!("Setup is already done")));
+    }
+    if !setup_token.matches(token) {
+        return Err(e401(anyhow::anyhow!("This setup link is not valid")));
+    }
+    Ok(())
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Show setup form", skip(state, csrf_token, messages, link))]
+pub async fn setup_form(
+    State(state): State<AppState>,
+    csrf_token: CsrfToken,
+    messages: Messages,
+    Query(link): Query<SetupLink>,
+) -> Result<Markup, AppError> {
+    ensure_setup_allowed(&state, &link.token).await?;
+    let message = get_all_messages(messages);
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Setup</title>
+  </head>
+  <body>
+    {message}
+    <p>Create the first owner account, it can then invite everyone else.</p>
+    <form action="/setup" method="post">
+      {csrf_field}
+      <input type="hidden" name="token" value="{token}" />
+      <label
+        >Username
+        <input type="text" placeholder="Choose a username" name="username" />
+      </label>
+      <br />
+      <label
+        >Email
+        <input type="email" placeholder="Email (optional)" name="email" />
+      </label>
+      <br />
+      <label
+        >Password
+        <input type="password" placeholder="Choose a password" name="password" />
+      </label>
+      <br />
+      <label
+        >Confirm password
+        <input type="password" placeholder="Type the password again" name="password_check" />
+      </label>
+      <br />
+      <p>{requirements}</p>
+      <button type="submit">Create owner</button>
+    </form>
+  </body>
+</html>
+        "#,
+        message,
+        csrf_field = csrf_token.field(),
+        token = link.token.expose_secret(),
+        requirements = state.password_policy.requirements(),
+    ))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Setup", skip(state, messages, form))]
+pub async fn setup(
+    State(state): State<AppState>,
+    messages: Messages,
+    Form(form): Form<SetupForm>,
+) -> Result<Redirect, AppError> {
+    ensure_setup_allowed(&state, &form.token).await?;
+    let retry = || {
+        Redirect::to(&format!(
+            "/setup?token={}",
+            urlencoding::encode(form.token.expose_secret())
+        ))
+    };
+
+    let username = form.username.trim().to_string();
+    if username.is_empty() {
+        messages.error("The username cannot be empty.");
+        return Ok(retry());
+    }
+    let email = match form.email.trim() {
+        "" => None,
+        email => match SubscriberEmail::parse(email.to_string()) {
+            Ok(email) => Some(email),
+            Err(e) => {
+                messages.error(e);
+                return Ok(retry());
+            }
+        },
+    };
+    if form.password.expose_secret() != form.password_check.expose_secret() {
+        messages.error("You entered two different passwords - the field values must match.");
+        return Ok(retry());
+    }
+    match validate_password_security(&state.password_policy, &form.password, &[&username]).await {
+        Ok(()) => {}
+        Err(PasswordError::Rejected(e)) => {
+            messages.error(e);
+            return Ok(retry());
+        }
+        Err(PasswordError::UnexpectedError(e)) => return Err(e.into()),
+    }
+
+    let user = NewAdminUser {
+        username,
+        email,
+        role: Role::Owner,
+        password: form.password,
+    };
+    match create_first_owner(&state.db_pool, user, &state.password_hashing).await {
+        Ok(Some(_)) => {
+            messages.info("The owner account has been created, you can now log in.");
+            Ok(Redirect::to("/login"))
+        }
+        Ok(None) => Err(e404(anyhow::anyhow!("Setup is already done"))),
+        Err(e @ UserError::UsernameTaken(_)) => Err(e404(e)),
+        Err(UserError::UnexpectedError(e)) => Err(e.into()),
+    }
+}
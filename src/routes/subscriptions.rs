@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, HashMap};
+
 use anyhow::Context;
 use axum::{
     Form,
@@ -8,13 +10,17 @@ use axum::{
 use chrono::Utc;
 use rand::distr::{Alphanumeric, SampleString};
 use serde::Deserialize;
-use sqlx::PgConnection;
+use sqlx::{PgConnection, types::Json};
 use uuid::Uuid;
 
 use crate::{
-    domain::{NewSubscriber, SubscriberEmail, SubscriberName},
+    anti_bot::{BotCheckError, HONEYPOT_FIELD},
+    domain::{NewSubscriber, SubscriberAttributeRules, SubscriberEmail},
     email_client::EmailClient,
+    request_metadata::RequestMetadata,
     startup::AppState,
+    subscription_events::{SubscriptionEvent, SubscriptionEventKind},
+    suppression::is_suppressed,
     utils::Transaction,
 };
 
@@ -22,22 +28,39 @@ use crate::{
 pub struct FormData {
     name: String,
     email: String,
+    /// Comma separated list of tags.
+    #[serde(default)]
+    tags: String,
+    /// Identifies the form the signup came from, for the consent trail.
+    #[serde(default)]
+    source: Option<String>,
+    /// Signed when the form was served, see [`crate::anti_bot::FormToken`].
+    #[serde(default)]
+    form_token: String,
+    /// Custom attributes are submitted as `attributes[<name>]=<value>`.
+    #[serde(flatten)]
+    extra: HashMap<String, String>,
 }
 
-impl TryFrom<FormData> for NewSubscriber {
-    type Error = String;
-
-    fn try_from(form: FormData) -> Result<Self, Self::Error> {
-        let email = SubscriberEmail::parse(form.email)?;
-        let name = SubscriberName::parse(form.name)?;
-        Ok(NewSubscriber { email, name })
+impl FormData {
+    pub fn parse(self, rules: &SubscriberAttributeRules) -> Result<NewSubscriber, String> {
+        let attributes: BTreeMap<String, String> = self
+            .extra
+            .into_iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix("attributes[")
+                    .and_then(|key| key.strip_suffix(']'))
+                    .map(|key| (key.to_string(), value))
+            })
+            .collect();
+        NewSubscriber::parse(self.email, self.name, &self.tags, attributes, rules)
     }
 }
 
 #[axum::debug_handler]
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, state),
+    skip(form, state, request),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name,
@@ -47,19 +70,100 @@ impl TryFrom<FormData> for NewSubscriber {
 ]
 pub async fn subscribe(
     State(state): State<AppState>,
+    request: RequestMetadata,
     Form(form): Form<FormData>,
 ) -> Result<(), SubscribeError> {
-    let new_subscriber: NewSubscriber =
-        form.try_into().map_err(SubscribeError::ValidiationError)?;
+    // Bots filling in every field get a success page and nothing else.
+    if form
+        .extra
+        .get(HONEYPOT_FIELD)
+        .is_some_and(|value| !value.is_empty())
+    {
+        tracing::info!("Ignoring a signup with the honeypot filled in");
+        return Ok(());
+    }
+    let challenge_response = form
+        .extra
+        .get(state.anti_bot.challenge_field())
+        .map(String::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let form_token = form.form_token.clone();
+    let source = form.source.clone();
+    let mut new_subscriber = form
+        .parse(&state.attribute_rules)
+        .map_err(SubscribeError::ValidiationError)?;
+    state
+        .signup_policy
+        .apply(&mut new_subscriber)
+        .map_err(SubscribeError::ValidiationError)?;
+
+    // Checked once the form is valid, a typo does not use the form up.
+    state
+        .anti_bot
+        .check(
+            &form_token,
+            &challenge_response,
+            request.source_ip.as_deref(),
+        )
+        .await?;
     let mut txn = state
         .db_pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool.")?;
 
-    let subscriber_id = insert_subscriber(&mut txn, &new_subscriber)
+    // Suppressed addresses get the same answer as everyone else, but no email.
+    if is_suppressed(&mut txn, &state.hmac_secret, new_subscriber.email.as_ref())
+        .await
+        .context("Failed to check the suppression list.")?
+    {
+        tracing::info!("Ignoring the signup of a suppressed address");
+        return Ok(());
+    }
+
+    // Neither does an address already on the list, in any case. One still
+    // pending is sent a new link, the first one may never have arrived.
+    let (subscriber_id, detail) = match insert_subscriber(&mut txn, &new_subscriber).await {
+        Ok(subscriber_id) => (subscriber_id, None),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            // The failed insert aborted the transaction.
+            txn.rollback()
+                .await
+                .context("Failed to roll back the signup transaction.")?;
+            txn = state
+                .db_pool
+                .begin()
+                .await
+                .context("Failed to acquire a Postgres connection from the pool.")?;
+            let pending = pending_subscriber_id(&mut txn, new_subscriber.email.as_ref())
+                .await
+                .context("Failed to look up the existing subscriber.")?;
+            let Some(subscriber_id) = pending else {
+                tracing::info!("Ignoring the signup of an address already subscribed");
+                return Ok(());
+            };
+            (
+                subscriber_id,
+                Some("Signed up again, resent the confirmation email"),
+            )
+        }
+        Err(e) => Err(e).context("Failed to insert new subscriber in the database.")?,
+    };
+
+    let mut event =
+        SubscriptionEvent::new(SubscriptionEventKind::Signup, new_subscriber.email.as_ref())
+            .request(&request);
+    if let Some(source) = &source {
+        event = event.source(source);
+    }
+    if let Some(detail) = detail {
+        event = event.detail(detail);
+    }
+    event
+        .record(&mut txn)
         .await
-        .context("Failed to insert new subscriber in the database.")?;
+        .context("Failed to record the signup.")?;
 
     let subscription_token = generate_subscription_token();
     store_token(txn, subscriber_id, &subscription_token)
@@ -68,7 +172,7 @@ pub async fn subscribe(
 
     send_confirmation_email(
         &state.email_client,
-        new_subscriber,
+        &new_subscriber.email,
         &state.base_url,
         &subscription_token,
     )
@@ -89,13 +193,30 @@ pub async fn insert_subscriber(
     let subscriber_id = Uuid::new_v4();
     sqlx::query!(
         r#"
-        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, attributes)
+        VALUES ($1, $2, $3, $4, 'pending_confirmation', $5)
         "#,
         subscriber_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
         Utc::now(),
+        Json(new_subscriber.attributes.as_ref()) as _,
+    )
+    .execute(&mut *txn)
+    .await?;
+
+    let tags: Vec<String> = new_subscriber
+        .tags
+        .iter()
+        .map(|tag| tag.as_ref().to_string())
+        .collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_tags (subscriber_id, tag)
+        SELECT $1, unnest($2::text[])
+        "#,
+        subscriber_id,
+        &tags,
     )
     .execute(&mut *txn)
     .await?;
@@ -103,6 +224,22 @@ pub async fn insert_subscriber(
     Ok(subscriber_id)
 }
 
+#[tracing::instrument(name = "Look up a pending subscriber", skip(txn))]
+async fn pending_subscriber_id(
+    txn: &mut PgConnection,
+    email: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT id FROM subscriptions
+        WHERE lower(email) = lower($1) AND status = 'pending_confirmation'
+        "#,
+        email,
+    )
+    .fetch_optional(txn)
+    .await
+}
+
 #[tracing::instrument(
     name = "Store subscription token in the database",
     skip(subscription_token, txn)
@@ -129,11 +266,11 @@ pub async fn store_token(
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(new_subscriber, email_client)
+    skip(subscriber_email, email_client)
 )]
 pub async fn send_confirmation_email(
     email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
+    subscriber_email: &SubscriberEmail,
     base_url: &str,
     subscription_token: &str,
 ) -> Result<(), reqwest::Error> {
@@ -147,13 +284,13 @@ pub async fn send_confirmation_email(
                 Click <a href=\"{confirmation_link}\">here</a> to confirm your subscription.",
     );
     email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
+        .send_email(subscriber_email, "Welcome!", &html_body, &plain_body)
         .await?;
 
     Ok(())
 }
 
-fn generate_subscription_token() -> String {
+pub fn generate_subscription_token() -> String {
     Alphanumeric.sample_string(&mut rand::rng(), 25)
 }
 
@@ -161,14 +298,26 @@ fn generate_subscription_token() -> String {
 pub enum SubscribeError {
     #[error("{0}")]
     ValidiationError(String),
+    #[error("{0}")]
+    BotCheckFailed(String),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
 
+impl From<BotCheckError> for SubscribeError {
+    fn from(e: BotCheckError) -> Self {
+        match e {
+            BotCheckError::UnexpectedError(e) => SubscribeError::UnexpectedError(e),
+            e => SubscribeError::BotCheckFailed(e.to_string()),
+        }
+    }
+}
+
 impl SubscribeError {
     fn status(&self) -> StatusCode {
         match self {
             SubscribeError::ValidiationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            SubscribeError::BotCheckFailed(_) => StatusCode::BAD_REQUEST,
             SubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
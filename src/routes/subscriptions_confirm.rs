@@ -6,7 +6,11 @@ use serde::Deserialize;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::startup::AppState;
+use crate::{
+    request_metadata::RequestMetadata,
+    startup::AppState,
+    subscription_events::{SubscriptionEvent, SubscriptionEventKind},
+};
 
 #[derive(Deserialize)]
 pub struct Parameters {
@@ -14,9 +18,14 @@ pub struct Parameters {
 }
 
 #[axum::debug_handler]
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(state, parameters), err)]
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(state, request, parameters),
+    err
+)]
 pub async fn confirm(
     State(state): State<AppState>,
+    request: RequestMetadata,
     parameters: Query<Parameters>,
 ) -> Result<(), StatusCode> {
     let subscriber_id =
@@ -26,29 +35,49 @@ pub async fn confirm(
 
     let subscriber_id = subscriber_id.ok_or(StatusCode::UNAUTHORIZED)?;
 
-    confirm_subscriber(&state.db_pool, subscriber_id)
+    confirm_subscriber(&state.db_pool, subscriber_id, &request)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(())
 }
 
-#[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, pool), err)]
-async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
+#[tracing::instrument(
+    name = "Mark subscriber as confirmed",
+    skip(subscriber_id, pool, request),
+    err
+)]
+async fn confirm_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    request: &RequestMetadata,
+) -> Result<(), sqlx::Error> {
+    let mut txn = pool.begin().await?;
+    // Only pending subscribers are confirmed: following the link again changes
+    // nothing, and an old link must not bring back someone who left or was
+    // suppressed since.
+    let record = sqlx::query!(
         r#"
         UPDATE subscriptions
         SET status = 'confirmed'
-        WHERE id = $1
+        WHERE id = $1 AND status = 'pending_confirmation'
+        RETURNING email
         "#,
         subscriber_id,
     )
-    .execute(pool)
+    .fetch_optional(&mut *txn)
     .await
     .map_err(|e| {
         tracing::error!(error = ?e, "failed to execute query");
         e
     })?;
+    if let Some(record) = record {
+        SubscriptionEvent::new(SubscriptionEventKind::Confirmation, &record.email)
+            .request(request)
+            .record(&mut txn)
+            .await?;
+    }
+    txn.commit().await?;
     Ok(())
 }
 
@@ -0,0 +1,254 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::{
+    Form,
+    extract::{Query, State},
+    http::header,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    domain::SubscriberEmail,
+    email_client::EmailClient,
+    html,
+    html::Markup,
+    signing,
+    startup::AppState,
+    subscriber_data::{collect_subscriber_data, erase_subscriber},
+    utils::{AppError, e401},
+};
+
+/// How long the link sent on a data request stays valid.
+const LINK_VALIDITY: Duration = Duration::hours(24);
+
+const REQUEST_RECEIVED: &str =
+    "<p>If we hold data about this address, we have sent it a link to access it.</p>";
+
+#[derive(Deserialize, Debug)]
+pub struct DataRequestForm {
+    email: String,
+}
+
+/// The query string of the link sent by email, it proves the holder received
+/// mail at `email` and asked about their data before `expires`.
+#[derive(Deserialize, Debug)]
+pub struct SignedDataLink {
+    email: String,
+    expires: i64,
+    signature: String,
+}
+
+impl SignedDataLink {
+    fn new(state: &AppState, email: &str) -> Self {
+        let expires = (Utc::now() + LINK_VALIDITY).timestamp();
+        let signature = signing::sign(&state.hmac_secret, &Self::message(email, expires));
+        SignedDataLink {
+            email: email.to_string(),
+            expires,
+            signature,
+        }
+    }
+
+    fn message(email: &str, expires: i64) -> String {
+        format!("subscriber_data\n{email}\n{expires}")
+    }
+
+    fn verify(&self, state: &AppState) -> Result<(), AppError> {
+        if self.expires < Utc::now().timestamp() {
+            return Err(e401(anyhow::anyhow!("This link has expired")));
+        }
+        if !signing::verify(
+            &state.hmac_secret,
+            &Self::message(&self.email, self.expires),
+            &self.signature,
+        ) {
+            return Err(e401(anyhow::anyhow!("This link is not valid")));
+        }
+        Ok(())
+    }
+
+    fn query_string(&self) -> String {
+        format!(
+            "email={}&expires={}&signature={}",
+            urlencoding::encode(&self.email),
+            self.expires,
+            urlencoding::encode(&self.signature),
+        )
+    }
+}
+
+#[axum::debug_handler]
+pub async fn data_request_form() -> Html<&'static str> {
+    Html(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Your data</title>
+  </head>
+  <body>
+    <p>Enter your email address to receive a link to download or erase the data we hold about you.</p>
+    <form action="/subscriptions/data" method="post">
+      <input type="email" placeholder="Email" name="email" />
+      <button type="submit">Send me a link</button>
+    </form>
+  </body>
+</html>
+        "#,
+    )
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Request subscriber data", skip(state, form))]
+pub async fn request_data(
+    State(state): State<AppState>,
+    Form(form): Form<DataRequestForm>,
+) -> Html<&'static str> {
+    // The answer is the same whether or not we know the address, so the form
+    // cannot be used to find out who is subscribed. The link is sent in the
+    // background, otherwise a known address would take longer to answer.
+    if let Ok(email) = SubscriberEmail::parse(form.email) {
+        let link = SignedDataLink::new(&state, email.as_ref());
+        tokio::spawn(send_data_request_link(
+            state.db_pool.clone(),
+            state.email_client.clone(),
+            state.base_url.clone(),
+            email,
+            link,
+        ));
+    }
+    Html(REQUEST_RECEIVED)
+}
+
+#[tracing::instrument(
+    name = "Send a data request link",
+    skip(pool, email_client, base_url, email, link)
+)]
+async fn send_data_request_link(
+    pool: PgPool,
+    email_client: Arc<EmailClient>,
+    base_url: String,
+    email: SubscriberEmail,
+    link: SignedDataLink,
+) {
+    let send = async {
+        if holds_data_about(&pool, email.as_ref()).await? {
+            send_data_request_email(&email_client, &email, &base_url, &link)
+                .await
+                .context("Failed to send a data request email")?;
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+    if let Err(e) = send.await {
+        tracing::error!(error.cause_chain = ?e, "Failed to send a data request link");
+    }
+}
+
+async fn holds_data_about(pool: &PgPool, email: &str) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+SELECT
+    EXISTS(SELECT 1 FROM subscriptions WHERE lower(email) = lower($1))
+    OR EXISTS(SELECT 1 FROM issue_delivery_log WHERE lower(subscriber_email) = lower($1)) AS "found!"
+        "#,
+        email,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to look up the subscriber")?;
+    Ok(row.found)
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Show subscriber data options", skip(state))]
+pub async fn data_options(
+    State(state): State<AppState>,
+    Query(link): Query<SignedDataLink>,
+) -> Result<Markup, AppError> {
+    link.verify(&state)?;
+    Ok(html!(
+        r#"
+<!doctype html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+    <title>Your data</title>
+  </head>
+  <body>
+    <p>Data we hold about {email}</p>
+    <p><a href="/subscriptions/data/export?{query_string}">Download a copy (JSON)</a></p>
+    <form action="/subscriptions/data/erase" method="post" onsubmit="return confirm('Erase all your data? This cannot be undone.');">
+      <input type="hidden" name="email" value="{email}" />
+      <input type="hidden" name="expires" value="{expires}" />
+      <input type="hidden" name="signature" value="{signature}" />
+      <button type="submit">Erase my data</button>
+    </form>
+  </body>
+</html>
+            "#,
+        email = link.email,
+        expires = link.expires,
+        signature = link.signature,
+        query_string = link.query_string(),
+    ))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Export subscriber data", skip(state))]
+pub async fn export_data(
+    State(state): State<AppState>,
+    Query(link): Query<SignedDataLink>,
+) -> Result<Response, AppError> {
+    link.verify(&state)?;
+    let data = collect_subscriber_data(&state.db_pool, &link.email).await?;
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            r#"attachment; filename="subscriber-data.json""#,
+        )],
+        axum::Json(data),
+    )
+        .into_response())
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Erase subscriber data", skip(state))]
+pub async fn erase_data(
+    State(state): State<AppState>,
+    Form(link): Form<SignedDataLink>,
+) -> Result<Html<&'static str>, AppError> {
+    link.verify(&state)?;
+    erase_subscriber(&state.db_pool, &state.hmac_secret, &link.email).await?;
+    Ok(Html("<p>Your data has been erased.</p>"))
+}
+
+#[tracing::instrument(
+    name = "Send a data request email",
+    skip(subscriber_email, email_client, link)
+)]
+async fn send_data_request_email(
+    email_client: &EmailClient,
+    subscriber_email: &SubscriberEmail,
+    base_url: &str,
+    link: &SignedDataLink,
+) -> Result<(), reqwest::Error> {
+    let data_link = format!("{base_url}/subscriptions/data?{}", link.query_string());
+    let plain_body = format!(
+        "Someone asked for the data we hold about this address.\n\
+        Visit {data_link} within 24 hours to download or erase it.\n\
+        If it was not you, you can ignore this email."
+    );
+    let html_body = format!(
+        "Someone asked for the data we hold about this address.<br />\
+        Click <a href=\"{data_link}\">here</a> within 24 hours to download or erase it.<br />\
+        If it was not you, you can ignore this email."
+    );
+    email_client
+        .send_email(subscriber_email, "Your data", &html_body, &plain_body)
+        .await
+}
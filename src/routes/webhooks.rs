@@ -0,0 +1,128 @@
+use anyhow::Context;
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Basic},
+};
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    email_feedback::{EmailFeedback, FeedbackKind, Provider},
+    startup::AppState,
+    subscription_events::{SubscriptionEvent, SubscriptionEventKind},
+    suppression::{SuppressionReason, suppress},
+    utils::{AppError, e400, e401, e404},
+};
+
+#[axum::debug_handler]
+#[tracing::instrument(name = "Receive email feedback", skip(state, authorization, body))]
+pub async fn email_webhook(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    authorization: Option<TypedHeader<Authorization<Basic>>>,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    let provider = Provider::from_name(&provider)
+        .ok_or_else(|| e404(anyhow::anyhow!("{provider} is not a supported provider")))?;
+
+    // Providers are given the token as the password of the webhook URL.
+    let authorized = authorization.is_some_and(|TypedHeader(Authorization(credentials))| {
+        credentials
+            .password()
+            .as_bytes()
+            .ct_eq(state.webhook_token.expose_secret().as_bytes())
+            .into()
+    });
+    if !authorized {
+        return Err(e401(anyhow::anyhow!("Invalid webhook credentials")));
+    }
+
+    let feedback = provider
+        .parse(&body)
+        .context("The webhook payload cannot be parsed")
+        .map_err(e400)?;
+    if let Some(feedback) = feedback {
+        record_feedback(&state.db_pool, &state.hmac_secret, provider, &feedback).await?;
+    }
+    Ok(StatusCode::OK)
+}
+
+#[tracing::instrument(name = "Record email feedback", skip(pool, secret), err(Debug))]
+async fn record_feedback(
+    pool: &PgPool,
+    secret: &SecretString,
+    provider: Provider,
+    feedback: &EmailFeedback,
+) -> Result<(), anyhow::Error> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+
+    sqlx::query!(
+        r#"
+INSERT INTO issue_delivery_log (subscriber_email, outcome, detail, recorded_at)
+VALUES ($1, $2, $3, now())
+        "#,
+        feedback.email,
+        feedback.kind.outcome(),
+        feedback.detail,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to record the feedback in the delivery log")?;
+
+    if let Some(status) = feedback.kind.suppressed_status() {
+        let suppressed = sqlx::query!(
+            r#"
+UPDATE subscriptions
+SET status = $2
+WHERE lower(email) = lower($1) AND status <> $2
+            "#,
+            feedback.email,
+            status,
+        )
+        .execute(&mut *txn)
+        .await
+        .context("Failed to suppress the subscriber")?;
+        sqlx::query!(
+            r#"DELETE FROM issue_delivery_queue WHERE lower(subscriber_email) = lower($1)"#,
+            feedback.email,
+        )
+        .execute(&mut *txn)
+        .await
+        .context("Failed to cancel pending deliveries")?;
+        let reason = match feedback.kind {
+            FeedbackKind::Complaint => SuppressionReason::Complaint,
+            _ => SuppressionReason::HardBounce,
+        };
+        // The suppression outlives the subscription, should it be deleted.
+        suppress(
+            &mut txn,
+            secret,
+            &feedback.email,
+            reason,
+            Some(&feedback.detail),
+        )
+        .await
+        .context("Failed to add the address to the suppression list")?;
+
+        if suppressed.rows_affected() > 0 {
+            SubscriptionEvent::new(SubscriptionEventKind::BounceSuppression, &feedback.email)
+                .source(provider.as_str())
+                .detail(&feedback.detail)
+                .record(&mut txn)
+                .await
+                .context("Failed to record the subscription event")?;
+        }
+    }
+
+    txn.commit().await.context("Failed to commit")?;
+    Ok(())
+}
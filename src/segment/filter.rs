@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::domain::SubscriberTag;
+
+/// A dynamic subset of `subscriptions`, stored as JSON, e.g.
+/// `{"all": [{"status": "confirmed"}, {"subscribed_within_days": 30}]}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum SegmentFilter {
+    All(Vec<SegmentFilter>),
+    Any(Vec<SegmentFilter>),
+    Not(Box<SegmentFilter>),
+    Status(String),
+    SubscribedWithinDays(u32),
+    EmailDomain(String),
+    Tag(String),
+    Attribute { name: String, equals: String },
+}
+
+impl SegmentFilter {
+    const MAX_DEPTH: usize = 8;
+    const MAX_DAYS: u32 = 36_500;
+
+    /// Append this filter as a boolean SQL expression over `subscriptions`.
+    /// Every user supplied value is sent as a bind parameter.
+    pub fn push_condition(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            SegmentFilter::All(filters) => push_group(qb, filters, " AND ", "TRUE"),
+            SegmentFilter::Any(filters) => push_group(qb, filters, " OR ", "FALSE"),
+            SegmentFilter::Not(filter) => {
+                qb.push("NOT (");
+                filter.push_condition(qb);
+                qb.push(")");
+            }
+            SegmentFilter::Status(status) => {
+                qb.push("status = ").push_bind(status.clone());
+            }
+            SegmentFilter::SubscribedWithinDays(days) => {
+                qb.push("subscribed_at >= now() - make_interval(days => ")
+                    .push_bind(*days as i32)
+                    .push(")");
+            }
+            SegmentFilter::EmailDomain(domain) => {
+                qb.push("lower(split_part(email, '@', 2)) = lower(")
+                    .push_bind(domain.clone())
+                    .push(")");
+            }
+            SegmentFilter::Tag(tag) => {
+                qb.push(
+                    "EXISTS (SELECT 1 FROM subscriber_tags \
+                    WHERE subscriber_tags.subscriber_id = subscriptions.id \
+                    AND subscriber_tags.tag = ",
+                )
+                .push_bind(tag.trim().to_lowercase())
+                .push(")");
+            }
+            SegmentFilter::Attribute { name, equals } => {
+                qb.push("subscriptions.attributes ->> ")
+                    .push_bind(name.trim().to_lowercase())
+                    .push(" = ")
+                    .push_bind(equals.clone());
+            }
+        }
+    }
+
+    fn validate(&self, depth: usize) -> Result<(), anyhow::Error> {
+        if depth > Self::MAX_DEPTH {
+            anyhow::bail!(
+                "The segment definition cannot be nested more than {} levels deep",
+                Self::MAX_DEPTH
+            );
+        }
+        match self {
+            SegmentFilter::All(filters) | SegmentFilter::Any(filters) => filters
+                .iter()
+                .try_for_each(|filter| filter.validate(depth + 1)),
+            SegmentFilter::Not(filter) => filter.validate(depth + 1),
+            SegmentFilter::Status(status) if status.trim().is_empty() => {
+                anyhow::bail!("The status filter cannot be empty")
+            }
+            SegmentFilter::SubscribedWithinDays(days) if *days == 0 || *days > Self::MAX_DAYS => {
+                anyhow::bail!(
+                    "The number of days must be between 1 and {}",
+                    Self::MAX_DAYS
+                )
+            }
+            SegmentFilter::EmailDomain(domain) if domain.trim().is_empty() => {
+                anyhow::bail!("The email domain filter cannot be empty")
+            }
+            SegmentFilter::Tag(tag) => SubscriberTag::parse(tag.clone())
+                .map(|_| ())
+                .map_err(anyhow::Error::msg),
+            SegmentFilter::Attribute { name, .. } if name.trim().is_empty() => {
+                anyhow::bail!("The attribute name cannot be empty")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn push_group(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    filters: &[SegmentFilter],
+    separator: &str,
+    empty: &str,
+) {
+    if filters.is_empty() {
+        qb.push(empty);
+        return;
+    }
+    qb.push("(");
+    for (i, filter) in filters.iter().enumerate() {
+        if i > 0 {
+            qb.push(separator);
+        }
+        filter.push_condition(qb);
+    }
+    qb.push(")");
+}
+
+impl TryFrom<String> for SegmentFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let filter: SegmentFilter = serde_json::from_str(&s)
+            .map_err(|e| anyhow::anyhow!("The segment definition is invalid: {e}"))?;
+        filter.validate(0)?;
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+    use sqlx::{Postgres, QueryBuilder};
+
+    use super::SegmentFilter;
+
+    fn compile(definition: &str) -> String {
+        let filter = SegmentFilter::try_from(definition.to_string()).unwrap();
+        let mut qb = QueryBuilder::<Postgres>::new("");
+        filter.push_condition(&mut qb);
+        qb.sql().to_string()
+    }
+
+    #[test]
+    fn values_are_compiled_to_bind_parameters() {
+        let sql = compile(r#"{"email_domain": "x'); DROP TABLE subscriptions; --"}"#);
+        assert_that!(sql).is_equal_to("lower(split_part(email, '@', 2)) = lower($1)".to_string());
+    }
+
+    #[test]
+    fn groups_are_compiled_with_their_separator() {
+        let sql = compile(
+            r#"{"all": [{"subscribed_within_days": 30}, {"any": [{"status": "a"}, {"not": {"status": "b"}}]}]}"#,
+        );
+        assert_that!(sql).is_equal_to(
+            "(subscribed_at >= now() - make_interval(days => $1) AND (status = $2 OR NOT (status = $3)))"
+                .to_string(),
+        );
+    }
+
+    #[test]
+    fn tags_and_attributes_are_compiled_to_bind_parameters() {
+        let sql = compile(
+            r#"{"any": [{"tag": "Beta"}, {"attribute": {"name": "company", "equals": "Acme"}}]}"#,
+        );
+        assert_that!(sql).is_equal_to(
+            "(EXISTS (SELECT 1 FROM subscriber_tags WHERE subscriber_tags.subscriber_id = subscriptions.id AND subscriber_tags.tag = $1) OR subscriptions.attributes ->> $2 = $3)"
+                .to_string(),
+        );
+    }
+
+    #[test]
+    fn empty_groups_are_compiled_to_constants() {
+        assert_that!(compile(r#"{"all": []}"#)).is_equal_to("TRUE".to_string());
+        assert_that!(compile(r#"{"any": []}"#)).is_equal_to("FALSE".to_string());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let definition = r#"{"favourite_colour": "blue"}"#.to_string();
+        assert_that!(SegmentFilter::try_from(definition)).is_err();
+    }
+
+    #[test]
+    fn zero_days_is_rejected() {
+        let definition = r#"{"subscribed_within_days": 0}"#.to_string();
+        assert_that!(SegmentFilter::try_from(definition)).is_err();
+    }
+
+    #[test]
+    fn deeply_nested_definitions_are_rejected() {
+        let definition = format!(
+            "{}{}{}",
+            r#"{"not": "#.repeat(10),
+            r#"{"status": "confirmed"}"#,
+            "}".repeat(10)
+        );
+        assert_that!(SegmentFilter::try_from(definition)).is_err();
+    }
+}
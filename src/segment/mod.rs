@@ -0,0 +1,7 @@
+mod filter;
+mod persistence;
+
+pub use filter::SegmentFilter;
+pub use persistence::{
+    Segment, count_recipients, get_segment, insert_segment, list_segments, push_recipients,
+};
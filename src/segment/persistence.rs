@@ -0,0 +1,112 @@
+use anyhow::Context;
+use secrecy::SecretString;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, types::Json};
+use uuid::Uuid;
+
+use crate::{segment::SegmentFilter, suppression::push_not_suppressed};
+
+pub struct Segment {
+    pub segment_id: Uuid,
+    pub name: String,
+    pub filter: SegmentFilter,
+}
+
+/// Append the condition selecting the subscribers an issue is delivered to.
+/// Only confirmed subscribers off the suppression list are ever eligible, a
+/// segment narrows them down further.
+pub fn push_recipients(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    secret: &SecretString,
+    filter: Option<&SegmentFilter>,
+) {
+    qb.push("status = 'confirmed' AND ");
+    push_not_suppressed(qb, secret);
+    if let Some(filter) = filter {
+        qb.push(" AND ");
+        filter.push_condition(qb);
+    }
+}
+
+#[tracing::instrument(name = "Count recipients", skip(pool, secret))]
+pub async fn count_recipients(
+    pool: &PgPool,
+    secret: &SecretString,
+    filter: Option<&SegmentFilter>,
+) -> Result<i64, anyhow::Error> {
+    let mut qb = QueryBuilder::new("SELECT count(*) FROM subscriptions WHERE ");
+    push_recipients(&mut qb, secret, filter);
+    let count = qb
+        .build_query_scalar()
+        .fetch_one(pool)
+        .await
+        .context("Failed to count the subscribers matching a segment")?;
+    Ok(count)
+}
+
+#[tracing::instrument(name = "Insert segment", skip(pool))]
+pub async fn insert_segment(
+    pool: &PgPool,
+    name: &str,
+    filter: SegmentFilter,
+) -> Result<Uuid, sqlx::Error> {
+    let segment_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+INSERT INTO segments (segment_id, name, definition, created_at)
+VALUES ($1, $2, $3, now())
+        "#,
+        segment_id,
+        name,
+        Json(filter) as _,
+    )
+    .execute(pool)
+    .await?;
+    Ok(segment_id)
+}
+
+#[tracing::instrument(name = "List segments", skip(pool))]
+pub async fn list_segments(pool: &PgPool) -> Result<Vec<Segment>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+SELECT segment_id, name, definition AS "definition: Json<SegmentFilter>"
+FROM segments
+ORDER BY name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to list segments")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Segment {
+            segment_id: r.segment_id,
+            name: r.name,
+            filter: r.definition.0,
+        })
+        .collect())
+}
+
+#[tracing::instrument(name = "Get segment", skip(conn))]
+pub async fn get_segment(
+    conn: &mut PgConnection,
+    segment_id: Uuid,
+) -> Result<Option<Segment>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+SELECT name, definition AS "definition: Json<SegmentFilter>"
+FROM segments
+WHERE segment_id = $1
+        "#,
+        segment_id
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to get segment")?;
+
+    Ok(row.map(|r| Segment {
+        segment_id,
+        name: r.name,
+        filter: r.definition.0,
+    }))
+}
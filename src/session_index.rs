@@ -0,0 +1,130 @@
+use anyhow::Context;
+use fred::prelude::{KeysInterface, Pool, SetsInterface};
+use tower_sessions::{SessionStore, session::Id};
+use tower_sessions_redis_store::RedisStore;
+use uuid::Uuid;
+
+use crate::session_state::{SessionMetadata, TypedSession};
+
+/// A logged in session of a user, as found in the session store.
+#[derive(Debug)]
+pub struct ActiveSession {
+    pub id: Id,
+    pub metadata: SessionMetadata,
+}
+
+/// Remembers which sessions in Redis belong to which user, so they can be
+/// listed and ended, e.g. after a password reset.
+#[derive(Clone)]
+pub struct SessionIndex {
+    redis_pool: Pool,
+    store: RedisStore<Pool>,
+}
+
+impl SessionIndex {
+    pub fn new(redis_pool: Pool) -> Self {
+        SessionIndex {
+            store: RedisStore::new(redis_pool.clone()),
+            redis_pool,
+        }
+    }
+
+    fn key(user_id: Uuid) -> String {
+        format!("user_sessions:{user_id}")
+    }
+
+    #[tracing::instrument(name = "Index session", skip(self, session_id))]
+    pub async fn add(&self, user_id: Uuid, session_id: Id) -> Result<(), anyhow::Error> {
+        self.redis_pool
+            .sadd::<(), _, _>(Self::key(user_id), session_id.to_string())
+            .await
+            .context("Failed to index the session")
+    }
+
+    /// The sessions of the user that still exist, most recently used first.
+    /// Ids of sessions that expired or logged out are dropped on the way.
+    #[tracing::instrument(name = "List user sessions", skip(self))]
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<ActiveSession>, anyhow::Error> {
+        let session_ids: Vec<String> = self
+            .redis_pool
+            .smembers(Self::key(user_id))
+            .await
+            .context("Failed to list the sessions")?;
+        let mut sessions = Vec::new();
+        let mut gone = Vec::new();
+        for session_id in session_ids {
+            let Ok(id) = session_id.parse::<Id>() else {
+                gone.push(session_id);
+                continue;
+            };
+            let record = self
+                .store
+                .load(&id)
+                .await
+                .context("Failed to load a session")?;
+            let metadata = record
+                .and_then(|record| record.data.get(TypedSession::METADATA_KEY).cloned())
+                .and_then(|metadata| serde_json::from_value(metadata).ok());
+            match metadata {
+                Some(metadata) => sessions.push(ActiveSession { id, metadata }),
+                None => gone.push(session_id),
+            }
+        }
+        if !gone.is_empty() {
+            self.redis_pool
+                .srem::<(), _, _>(Self::key(user_id), gone)
+                .await
+                .context("Failed to update the session index")?;
+        }
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.metadata.last_seen_at));
+        Ok(sessions)
+    }
+
+    /// End the session of the user shown as `handle`. Returns `false` if the
+    /// user has no such session.
+    #[tracing::instrument(name = "Revoke user session", skip(self))]
+    pub async fn revoke(&self, user_id: Uuid, handle: Uuid) -> Result<bool, anyhow::Error> {
+        let Some(session) = self
+            .list(user_id)
+            .await?
+            .into_iter()
+            .find(|session| session.metadata.handle == handle)
+        else {
+            return Ok(false);
+        };
+        self.delete(user_id, vec![session.id.to_string()]).await?;
+        Ok(true)
+    }
+
+    /// Delete every session of the user from the session store, except
+    /// `keep`.
+    #[tracing::instrument(name = "Revoke user sessions", skip(self, keep))]
+    pub async fn revoke_all(&self, user_id: Uuid, keep: Option<Id>) -> Result<(), anyhow::Error> {
+        let keep = keep.map(|id| id.to_string());
+        let session_ids: Vec<String> = self
+            .redis_pool
+            .smembers(Self::key(user_id))
+            .await
+            .context("Failed to list the sessions")?;
+        let revoked = session_ids
+            .into_iter()
+            .filter(|id| Some(id) != keep.as_ref())
+            .collect::<Vec<_>>();
+        self.delete(user_id, revoked).await
+    }
+
+    async fn delete(&self, user_id: Uuid, session_ids: Vec<String>) -> Result<(), anyhow::Error> {
+        if session_ids.is_empty() {
+            return Ok(());
+        }
+        // The Redis session store keys sessions by their id.
+        self.redis_pool
+            .del::<(), _>(session_ids.clone())
+            .await
+            .context("Failed to delete the sessions")?;
+        self.redis_pool
+            .srem::<(), _, _>(Self::key(user_id), session_ids)
+            .await
+            .context("Failed to update the session index")
+    }
+}
@@ -2,18 +2,160 @@ use axum::{
     extract::FromRequestParts,
     http::{StatusCode, request::Parts},
 };
-use tower_sessions::Session;
+use chrono::{DateTime, Duration, Utc};
+use rand::distr::{Alphanumeric, SampleString};
+use serde::{Deserialize, Serialize};
+use serde_aux::field_attributes::deserialize_number_from_string;
+use tower_sessions::{
+    Expiry, Session,
+    cookie::{self, time::OffsetDateTime},
+    session::Id,
+};
 use uuid::Uuid;
 
+use crate::request_metadata::RequestMetadata;
+
+/// How stale the last seen time of a session may get, so not every request
+/// writes to the session store.
+const LAST_SEEN_RESOLUTION: Duration = Duration::minutes(1);
+/// How long after the password was verified the second factor may be given.
+const PENDING_LOGIN_VALIDITY: Duration = Duration::minutes(5);
+
+/// How the session cookie is sent and how long sessions last.
+#[derive(Deserialize, Clone, Debug)]
+pub struct SessionSettings {
+    /// Only send the cookie over HTTPS, wherever the application is behind TLS.
+    pub secure_cookies: bool,
+    pub same_site: SameSite,
+    /// Signed in sessions end this long after login, however active they are.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lifetime_seconds: u64,
+    /// Sessions end after this long without a request. Activity is recorded
+    /// once a minute, so it should be well above that.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub idle_timeout_seconds: u64,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<SameSite> for cookie::SameSite {
+    fn from(same_site: SameSite) -> Self {
+        match same_site {
+            SameSite::Strict => cookie::SameSite::Strict,
+            SameSite::Lax => cookie::SameSite::Lax,
+            SameSite::None => cookie::SameSite::None,
+        }
+    }
+}
+
+impl SessionSettings {
+    pub fn lifetime(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.lifetime_seconds)
+    }
+
+    pub fn idle_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.idle_timeout_seconds)
+    }
+
+    /// The expiry of sessions nobody signed in with, they only carry flash
+    /// messages and pending logins.
+    pub fn anonymous_expiry(&self) -> Expiry {
+        Expiry::OnInactivity(
+            self.idle_timeout()
+                .try_into()
+                .unwrap_or(cookie::time::Duration::MAX),
+        )
+    }
+
+    /// When a signed in session ends, however active it is.
+    pub fn end_of(&self, metadata: &SessionMetadata) -> DateTime<Utc> {
+        let lifetime = Duration::from_std(self.lifetime()).unwrap_or(Duration::MAX);
+        metadata
+            .created_at
+            .checked_add_signed(lifetime)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// Whether a signed in session has been idle, or alive, for too long.
+    pub fn has_expired(&self, metadata: &SessionMetadata) -> bool {
+        let now = Utc::now();
+        let idle_timeout = Duration::from_std(self.idle_timeout()).unwrap_or(Duration::MAX);
+        now - metadata.last_seen_at > idle_timeout || now >= self.end_of(metadata)
+    }
+}
+
+/// Where and when a session was used, shown on the session management page.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionMetadata {
+    /// Stands for the session on that page, the session id is never shown.
+    pub handle: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl SessionMetadata {
+    pub fn new(request: &RequestMetadata) -> Self {
+        let now = Utc::now();
+        SessionMetadata {
+            handle: Uuid::new_v4(),
+            created_at: now,
+            last_seen_at: now,
+            ip: request.source_ip.clone(),
+            user_agent: request.user_agent.clone(),
+        }
+    }
+}
+
+/// A login waiting for its second factor.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PendingLogin {
+    user_id: Uuid,
+    password_verified_at: DateTime<Utc>,
+}
+
+impl PendingLogin {
+    fn is_stale(&self) -> bool {
+        Utc::now() - self.password_verified_at > PENDING_LOGIN_VALIDITY
+    }
+}
+
 pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    /// Set once the password was verified, until the second factor is.
+    const PENDING_USER_ID_KEY: &'static str = "pending_second_factor";
+    /// The TOTP secret shown during enrollment, until the user confirms it.
+    const TOTP_ENROLLMENT_KEY: &'static str = "totp_enrollment_secret";
+    /// Read straight from the store to list the sessions of a user.
+    pub(crate) const METADATA_KEY: &'static str = "metadata";
+    /// Checked against the token in the forms posted, see [`crate::csrf`].
+    const CSRF_TOKEN_KEY: &'static str = "csrf_token";
 
+    /// Give the session a new id, and a new CSRF token, so nothing known
+    /// about it before carries over.
     pub async fn renew(&self) -> Result<(), tower_sessions::session::Error> {
+        self.0.remove::<String>(Self::CSRF_TOKEN_KEY).await?;
         self.0.cycle_id().await
     }
 
+    /// The id of the session in the store. A new session is saved first, so
+    /// it has one.
+    pub async fn id(&self) -> Result<Option<Id>, tower_sessions::session::Error> {
+        if self.0.id().is_none() {
+            self.0.save().await?;
+        }
+        Ok(self.0.id())
+    }
+
     pub async fn insert_user_id(
         &self,
         user_id: Uuid,
@@ -25,8 +167,122 @@ impl TypedSession {
         self.0.get(Self::USER_ID_KEY).await
     }
 
+    pub async fn insert_pending_user_id(
+        &self,
+        user_id: Uuid,
+    ) -> Result<(), tower_sessions::session::Error> {
+        let pending = PendingLogin {
+            user_id,
+            password_verified_at: Utc::now(),
+        };
+        self.0.insert(Self::PENDING_USER_ID_KEY, pending).await
+    }
+
+    /// The user whose password was verified, unless that was too long ago and
+    /// they must start over.
+    pub async fn get_pending_user_id(
+        &self,
+    ) -> Result<Option<Uuid>, tower_sessions::session::Error> {
+        let Some(pending) = self
+            .0
+            .get::<PendingLogin>(Self::PENDING_USER_ID_KEY)
+            .await?
+        else {
+            return Ok(None);
+        };
+        if pending.is_stale() {
+            self.remove_pending_user_id().await?;
+            return Ok(None);
+        }
+        Ok(Some(pending.user_id))
+    }
+
+    pub async fn remove_pending_user_id(&self) -> Result<(), tower_sessions::session::Error> {
+        self.0
+            .remove::<PendingLogin>(Self::PENDING_USER_ID_KEY)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn insert_totp_enrollment(
+        &self,
+        secret: &str,
+    ) -> Result<(), tower_sessions::session::Error> {
+        self.0.insert(Self::TOTP_ENROLLMENT_KEY, secret).await
+    }
+
+    pub async fn get_totp_enrollment(
+        &self,
+    ) -> Result<Option<String>, tower_sessions::session::Error> {
+        self.0.get(Self::TOTP_ENROLLMENT_KEY).await
+    }
+
+    pub async fn remove_totp_enrollment(&self) -> Result<(), tower_sessions::session::Error> {
+        self.0.remove::<String>(Self::TOTP_ENROLLMENT_KEY).await?;
+        Ok(())
+    }
+
+    pub async fn insert_metadata(
+        &self,
+        metadata: &SessionMetadata,
+    ) -> Result<(), tower_sessions::session::Error> {
+        self.0.insert(Self::METADATA_KEY, metadata).await
+    }
+
+    pub async fn get_metadata(
+        &self,
+    ) -> Result<Option<SessionMetadata>, tower_sessions::session::Error> {
+        self.0.get(Self::METADATA_KEY).await
+    }
+
+    /// The CSRF token of the session, created the first time a form needs it.
+    pub async fn csrf_token(&self) -> Result<String, tower_sessions::session::Error> {
+        if let Some(token) = self.get_csrf_token().await? {
+            return Ok(token);
+        }
+        let token = Alphanumeric.sample_string(&mut rand::rng(), 40);
+        self.0.insert(Self::CSRF_TOKEN_KEY, &token).await?;
+        Ok(token)
+    }
+
+    pub async fn get_csrf_token(&self) -> Result<Option<String>, tower_sessions::session::Error> {
+        self.0.get(Self::CSRF_TOKEN_KEY).await
+    }
+
+    /// Record that the session was just used.
+    pub async fn touch(&self) -> Result<(), tower_sessions::session::Error> {
+        let Some(mut metadata) = self.get_metadata().await? else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        if now - metadata.last_seen_at < LAST_SEEN_RESOLUTION {
+            return Ok(());
+        }
+        metadata.last_seen_at = now;
+        self.insert_metadata(&metadata).await
+    }
+
+    /// Keep a signed in session, and its cookie, until `end` rather than
+    /// expiring it when idle, so the user can be told why they were signed
+    /// out. The expiry is not stored, so it is set again whenever the session
+    /// is saved.
+    pub fn keep_until(&self, end: DateTime<Utc>) {
+        let end = OffsetDateTime::from_unix_timestamp(end.timestamp())
+            .unwrap_or(OffsetDateTime::now_utc());
+        self.0.set_expiry(Some(Expiry::AtDateTime(end)));
+    }
+
+    /// Whether the session will be saved at the end of the request.
+    pub fn is_modified(&self) -> bool {
+        self.0.is_modified()
+    }
+
     pub async fn log_out(&self) -> Result<(), tower_sessions::session::Error> {
-        self.0.flush().await
+        self.0.flush().await?;
+        // What is stored afterwards, like a flash message, must not inherit
+        // the end of the signed in session, which may have passed already.
+        self.0.set_expiry(None);
+        Ok(())
     }
 }
 
@@ -42,3 +298,22 @@ where
         Ok(extractor)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use super::PendingLogin;
+
+    #[test]
+    fn a_pending_login_goes_stale_after_five_minutes() {
+        let pending = |minutes_ago| PendingLogin {
+            user_id: Uuid::new_v4(),
+            password_verified_at: Utc::now() - Duration::minutes(minutes_ago),
+        };
+        assert_that!(pending(4).is_stale()).is_false();
+        assert_that!(pending(6).is_stale()).is_true();
+    }
+}
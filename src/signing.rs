@@ -0,0 +1,47 @@
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Hex encoded HMAC-SHA256 of `message`, used to sign links sent by email.
+pub fn sign(secret: &SecretString, message: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Check a signature produced by [`sign`] in constant time.
+pub fn verify(secret: &SecretString, message: &str, signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(message.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+    use secrecy::SecretString;
+
+    use super::{sign, verify};
+
+    #[test]
+    fn a_signature_is_verified_with_the_same_secret_and_message() {
+        let secret = SecretString::from("secret");
+        let signature = sign(&secret, "message");
+        assert_that!(verify(&secret, "message", &signature)).is_true();
+        assert_that!(verify(&secret, "other message", &signature)).is_false();
+        assert_that!(verify(&SecretString::from("other"), "message", &signature)).is_false();
+    }
+
+    #[test]
+    fn a_malformed_signature_is_rejected() {
+        let secret = SecretString::from("secret");
+        assert_that!(verify(&secret, "message", "not hex")).is_false();
+    }
+}
@@ -1,10 +1,12 @@
-use std::{sync::Arc, time::Duration};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Context;
 use axum::{
     Router,
     body::Body,
+    extract::DefaultBodyLimit,
     http::Request,
+    middleware::{from_fn, from_fn_with_state},
     routing::{get, post},
 };
 use axum_messages::MessagesManagerLayer;
@@ -19,18 +21,42 @@ use tower_sessions_redis_store::{
 };
 
 use crate::{
-    authentication::reject_anonymous_users,
+    anti_bot::AntiBot,
+    authentication::{
+        LoginThrottling, PasswordHashing, PasswordPolicy, Role, SetupToken, has_users,
+        reject_anonymous_users, require_role,
+    },
     configuration::{DatabaseSettings, Settings},
+    csrf::verify_csrf_token,
+    domain::{SignupPolicy, SubscriberAttributeRules},
     email_client::EmailClient,
+    rate_limit::{RateLimiter, rate_limit},
     routes::{
-        admin_dashboard, change_password, change_password_form, confirm, health_check, home, login,
-        login_form, logout, newsletters_form, publish_newsletters, subscribe,
+        accept_invitation, add_suppression, admin_dashboard, change_password, change_password_form,
+        change_user_role, confirm, create_segment, data_options, data_request_form,
+        delete_subscriber, delete_suppression, delete_user, disable_two_factor, disable_user,
+        email_webhook, enable_two_factor, enable_user, erase_data, erase_subscriber_data,
+        export_data, export_subscriber_data, export_subscribers, forgot_password_form,
+        health_check, home, import_subscribers, import_subscribers_form, invitation_form,
+        invite_user, list_subscribers, lockouts_form, login, login_form, logout, newsletters_form,
+        publish_newsletters, request_data, request_password_reset, resend_confirmation,
+        reset_password, reset_password_form, revoke_invitation, revoke_other_sessions,
+        revoke_session, security_form, segments_form, sessions_form, setup, setup_form, subscribe,
+        subscriber_details, suppressions_form, two_factor, two_factor_form, unlock_lockout,
+        unsubscribe_subscriber, users_form,
     },
+    session_index::SessionIndex,
+    session_state::SessionSettings,
 };
 
+/// Largest CSV file accepted by the subscriber import.
+const MAX_IMPORT_SIZE: usize = 50 * 1024 * 1024;
+
 pub struct Application {
     pub address: String,
     pub router: Router,
+    /// Set when the application started without any user.
+    pub setup_token: Option<SecretString>,
 }
 
 #[derive(Clone)]
@@ -39,6 +65,16 @@ pub struct AppState {
     pub email_client: Arc<EmailClient>,
     pub base_url: String,
     pub hmac_secret: SecretString,
+    pub webhook_token: SecretString,
+    pub attribute_rules: Arc<SubscriberAttributeRules>,
+    pub signup_policy: Arc<SignupPolicy>,
+    pub anti_bot: Arc<AntiBot>,
+    pub login_throttling: Arc<LoginThrottling>,
+    pub password_hashing: Arc<PasswordHashing>,
+    pub password_policy: Arc<PasswordPolicy>,
+    pub session_index: SessionIndex,
+    pub session_settings: Arc<SessionSettings>,
+    pub setup_token: Option<Arc<SetupToken>>,
 }
 
 impl Application {
@@ -59,13 +95,6 @@ impl Application {
 
         let address = configuration.application.address();
 
-        let state = AppState {
-            db_pool: connection_pool,
-            email_client: Arc::new(email_client),
-            base_url: configuration.application.base_url,
-            hmac_secret: configuration.application.hmac_secret,
-        };
-
         let redis_pool = get_redis_connection_pool(&configuration.redis_uri)
             .context("failed to create redis pool")?;
         redis_pool
@@ -73,43 +102,236 @@ impl Application {
             .await
             .context("failed to connect to redis")?;
 
-        let router = router(state, redis_pool);
+        // Until there is a user, the first owner is created with a token
+        // only those who can read the logs know.
+        let (setup_token, setup_secret) = if has_users(&connection_pool).await? {
+            (None, None)
+        } else {
+            let (setup_token, secret) = SetupToken::generate();
+            tracing::warn!(
+                "No user exists yet, create the first owner at {}/setup?token={}",
+                configuration.application.base_url,
+                secret.expose_secret()
+            );
+            (Some(Arc::new(setup_token)), Some(secret))
+        };
 
-        Ok(Application { address, router })
+        let anti_bot = AntiBot::new(
+            &configuration.anti_bot,
+            configuration.application.hmac_secret.clone(),
+            Some(redis_pool.clone()),
+        );
+        let state = AppState {
+            db_pool: connection_pool,
+            email_client: Arc::new(email_client),
+            base_url: configuration.application.base_url,
+            hmac_secret: configuration.application.hmac_secret,
+            webhook_token: configuration.email_client.webhook_token,
+            attribute_rules: Arc::new(configuration.subscriber_attributes),
+            signup_policy: Arc::new(configuration.signup_policy),
+            anti_bot: Arc::new(anti_bot),
+            login_throttling: Arc::new(configuration.login_throttling),
+            password_hashing: Arc::new(configuration.password_hashing),
+            password_policy: Arc::new(configuration.password_policy),
+            session_index: SessionIndex::new(redis_pool.clone()),
+            session_settings: Arc::new(configuration.application.session),
+            setup_token,
+        };
+
+        let rate_limiter = RateLimiter::new(configuration.rate_limit, Some(redis_pool.clone()));
+        let router = router(state, redis_pool, rate_limiter);
+
+        Ok(Application {
+            address,
+            router,
+            setup_token: setup_secret,
+        })
     }
 
     pub async fn run_until_stopped(self, listener: TcpListener) -> std::io::Result<()> {
         tracing::info!("listening on {}", listener.local_addr().unwrap());
-        axum::serve(listener, self.router).await?;
+        axum::serve(
+            listener,
+            self.router
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
         Ok(())
     }
 }
 
-fn router(state: AppState, redis_pool: fred::prelude::Pool) -> Router {
+fn router(
+    state: AppState,
+    redis_pool: fred::prelude::Pool,
+    rate_limiter: Arc<RateLimiter>,
+) -> Router {
     let session_store = RedisStore::new(redis_pool);
     let session_layer = SessionManagerLayer::new(session_store)
-        .with_secure(false)
+        .with_secure(state.session_settings.secure_cookies)
+        .with_same_site(state.session_settings.same_site.into())
+        .with_expiry(state.session_settings.anonymous_expiry())
         .with_signed(Key::from(state.hmac_secret.expose_secret().as_bytes()));
 
-    let admin_route = Router::new()
+    let limits = rate_limiter.settings().clone();
+
+    // Every signed in user can look around and manage their own account.
+    let viewer_routes = Router::new()
         .route("/dashboard", get(admin_dashboard))
         .route("/password", get(change_password_form))
         .route("/password", post(change_password))
         .route("/logout", post(logout))
+        .route("/security", get(security_form))
+        .route("/security/totp", post(enable_two_factor))
+        .route("/security/totp/disable", post(disable_two_factor))
+        .route("/security/sessions", get(sessions_form))
+        .route("/security/sessions/{handle}/revoke", post(revoke_session))
+        .route(
+            "/security/sessions/revoke_others",
+            post(revoke_other_sessions),
+        )
         .route("/newsletters", get(newsletters_form))
+        .route("/segments", get(segments_form))
+        .route("/subscribers", get(list_subscribers))
+        .route("/subscribers/{subscriber_id}", get(subscriber_details))
+        .route("/subscribers/import", get(import_subscribers_form))
+        .route("/suppressions", get(suppressions_form));
+
+    let editor_routes = Router::new()
+        .route("/segments", post(create_segment))
+        .route(
+            "/subscribers/{subscriber_id}/resend_confirmation",
+            post(resend_confirmation),
+        )
+        .route(
+            "/subscribers/{subscriber_id}/unsubscribe",
+            post(unsubscribe_subscriber),
+        )
+        .route(
+            "/subscribers/import",
+            post(import_subscribers).layer(DefaultBodyLimit::max(MAX_IMPORT_SIZE)),
+        )
+        .route("/suppressions", post(add_suppression))
+        .route_layer(from_fn_with_state(Role::Editor, require_role));
+
+    // Removing data cannot be undone, and a removed suppression makes an
+    // address that bounced or complained mailable again.
+    let publisher_routes = Router::new()
         .route("/newsletters", post(publish_newsletters))
-        .layer(axum::middleware::from_fn_with_state(
-            state.clone(),
-            reject_anonymous_users,
-        ));
+        .route(
+            "/subscribers/{subscriber_id}/delete",
+            post(delete_subscriber),
+        )
+        .route(
+            "/subscribers/{subscriber_id}/erase",
+            post(erase_subscriber_data),
+        )
+        .route(
+            "/suppressions/{email_hash}/delete",
+            post(delete_suppression),
+        )
+        .route_layer(from_fn_with_state(Role::Publisher, require_role));
+
+    let owner_routes = Router::new()
+        .route("/users", get(users_form))
+        .route("/users/invite", post(invite_user))
+        .route(
+            "/users/invitations/{invitation_id}/revoke",
+            post(revoke_invitation),
+        )
+        .route("/users/{user_id}/role", post(change_user_role))
+        .route("/users/{user_id}/disable", post(disable_user))
+        .route("/users/{user_id}/enable", post(enable_user))
+        .route("/users/{user_id}/delete", post(delete_user))
+        .route("/lockouts", get(lockouts_form))
+        .route("/lockouts/unlock", post(unlock_lockout))
+        // Exports hand out every address at once, only owners may take them.
+        .route("/subscribers/export", get(export_subscribers))
+        .route(
+            "/subscribers/{subscriber_id}/data",
+            get(export_subscriber_data),
+        )
+        .route_layer(from_fn_with_state(Role::Owner, require_role));
+
+    let admin_route = Router::new()
+        .merge(viewer_routes)
+        .merge(editor_routes)
+        .merge(publisher_routes)
+        .merge(owner_routes)
+        .layer(from_fn(verify_csrf_token))
+        .layer(from_fn_with_state(state.clone(), reject_anonymous_users));
+
+    // The forms used to sign in, or to get an account to sign in with.
+    let login_routes = Router::new()
+        .route("/login", get(login_form))
+        .route("/setup", get(setup_form))
+        .route("/setup", post(setup))
+        .route("/login/forgot", get(forgot_password_form))
+        .route(
+            "/login/forgot",
+            post(request_password_reset).layer(from_fn_with_state(
+                rate_limiter
+                    .policy("password_reset", limits.password_reset)
+                    .keyed_by_field("email"),
+                rate_limit,
+            )),
+        )
+        .route("/login/reset", get(reset_password_form))
+        .route("/login/reset", post(reset_password))
+        .route("/invitations/accept", get(invitation_form))
+        .route("/invitations/accept", post(accept_invitation))
+        .route(
+            "/login",
+            post(login).layer(from_fn_with_state(
+                rate_limiter
+                    .policy("login", limits.login)
+                    .keyed_by_field("username"),
+                rate_limit,
+            )),
+        )
+        .route("/login/two_factor", get(two_factor_form))
+        .route(
+            "/login/two_factor",
+            post(two_factor).layer(from_fn_with_state(
+                rate_limiter.policy("two_factor", limits.login),
+                rate_limit,
+            )),
+        )
+        .route_layer(from_fn(verify_csrf_token));
 
     Router::new()
         .route("/health_check", get(health_check))
-        .route("/subscriptions", post(subscribe))
-        .route("/subscriptions/confirm", get(confirm))
+        .route(
+            "/subscriptions",
+            post(subscribe).layer(from_fn_with_state(
+                rate_limiter
+                    .policy("subscriptions", limits.subscriptions)
+                    .keyed_by_field("email"),
+                rate_limit,
+            )),
+        )
+        .route(
+            "/subscriptions/confirm",
+            get(confirm).layer(from_fn_with_state(
+                rate_limiter.policy("confirm", limits.confirm),
+                rate_limit,
+            )),
+        )
+        .route("/subscriptions/data", get(data_options))
+        .route(
+            "/subscriptions/data",
+            post(request_data).layer(from_fn_with_state(
+                rate_limiter
+                    .policy("data_request", limits.data_request)
+                    .keyed_by_field("email"),
+                rate_limit,
+            )),
+        )
+        .route("/subscriptions/data/request", get(data_request_form))
+        .route("/subscriptions/data/export", get(export_data))
+        .route("/subscriptions/data/erase", post(erase_data))
+        .route("/webhooks/email/{provider}", post(email_webhook))
         .route("/", get(home))
-        .route("/login", get(login_form))
-        .route("/login", post(login))
+        .merge(login_routes)
         .nest("/admin", admin_route)
         .with_state(state)
         .layer(MessagesManagerLayer)
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use secrecy::SecretString;
+use serde::Serialize;
+use sqlx::{PgPool, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    subscription_events::{RecordedSubscriptionEvent, list_subscription_events},
+    suppression::{SuppressionReason, suppress},
+};
+
+/// Everything we hold about an email address, as handed out on a data subject
+/// access request. We do not record opens or clicks, so delivery attempts and
+/// the consent trail are the only tracking data there is.
+#[derive(Serialize, Debug)]
+pub struct SubscriberData {
+    pub email: String,
+    pub subscription: Option<SubscriptionData>,
+    pub subscription_tokens: Vec<TokenData>,
+    pub pending_deliveries: Vec<PendingDeliveryData>,
+    pub delivery_log: Vec<DeliveryData>,
+    pub subscription_events: Vec<RecordedSubscriptionEvent>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SubscriptionData {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub consent_source: Option<String>,
+    pub tags: Vec<String>,
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TokenData {
+    pub subscription_token: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PendingDeliveryData {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DeliveryData {
+    pub newsletter_issue_id: Option<Uuid>,
+    pub title: Option<String>,
+    pub outcome: String,
+    pub detail: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Collect subscriber data", skip(pool), err(Debug))]
+pub async fn collect_subscriber_data(
+    pool: &PgPool,
+    email: &str,
+) -> Result<SubscriberData, anyhow::Error> {
+    let subscription = sqlx::query!(
+        r#"
+SELECT
+    s.id,
+    s.name,
+    s.status,
+    s.subscribed_at,
+    s.consent_source,
+    s.attributes AS "attributes: Json<BTreeMap<String, String>>",
+    ARRAY(
+        SELECT tag FROM subscriber_tags WHERE subscriber_id = s.id ORDER BY tag
+    ) AS "tags!"
+FROM subscriptions s
+WHERE lower(s.email) = lower($1)
+        "#,
+        email,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the subscription")?
+    .map(|r| SubscriptionData {
+        id: r.id,
+        name: r.name,
+        status: r.status,
+        subscribed_at: r.subscribed_at,
+        consent_source: r.consent_source,
+        tags: r.tags,
+        attributes: r.attributes.0,
+    });
+
+    let subscription_tokens = match &subscription {
+        Some(subscription) => sqlx::query_as!(
+            TokenData,
+            r#"
+SELECT subscription_token, created_at
+FROM subscription_tokens
+WHERE subscriber_id = $1
+ORDER BY created_at
+            "#,
+            subscription.id,
+        )
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch the subscription tokens")?,
+        None => vec![],
+    };
+
+    let pending_deliveries = sqlx::query_as!(
+        PendingDeliveryData,
+        r#"
+SELECT q.newsletter_issue_id, i.title
+FROM issue_delivery_queue q
+JOIN newsletter_issues i ON i.newsletter_issue_id = q.newsletter_issue_id
+WHERE lower(q.subscriber_email) = lower($1)
+        "#,
+        email,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the pending deliveries")?;
+
+    let delivery_log = sqlx::query_as!(
+        DeliveryData,
+        r#"
+SELECT l.newsletter_issue_id, i.title AS "title?", l.outcome, l.detail, l.recorded_at
+FROM issue_delivery_log l
+LEFT JOIN newsletter_issues i ON i.newsletter_issue_id = l.newsletter_issue_id
+WHERE lower(l.subscriber_email) = lower($1)
+ORDER BY l.recorded_at
+        "#,
+        email,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the delivery log")?;
+
+    let subscription_events = list_subscription_events(pool, email)
+        .await
+        .context("Failed to fetch the subscription events")?;
+
+    Ok(SubscriberData {
+        email: email.to_string(),
+        subscription,
+        subscription_tokens,
+        pending_deliveries,
+        delivery_log,
+        subscription_events,
+    })
+}
+
+/// Hard-delete every row mentioning `email` and leave only its hash on the
+/// suppression list.
+#[tracing::instrument(name = "Erase subscriber", skip(pool, secret), err(Debug))]
+pub async fn erase_subscriber(
+    pool: &PgPool,
+    secret: &SecretString,
+    email: &str,
+) -> Result<(), anyhow::Error> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE lower(subscriber_email) = lower($1)"#,
+        email,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to delete the pending deliveries")?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_log WHERE lower(subscriber_email) = lower($1)"#,
+        email,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to delete the delivery log")?;
+    sqlx::query!(
+        r#"DELETE FROM subscription_events WHERE lower(subscriber_email) = lower($1)"#,
+        email,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to delete the subscription events")?;
+    sqlx::query!(
+        r#"
+DELETE FROM subscription_tokens
+WHERE subscriber_id IN (SELECT id FROM subscriptions WHERE lower(email) = lower($1))
+        "#,
+        email,
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to delete the subscription tokens")?;
+    // Tags go along with the subscription through their foreign key.
+    sqlx::query!(
+        r#"DELETE FROM subscriptions WHERE lower(email) = lower($1)"#,
+        email
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to delete the subscription")?;
+    suppress(&mut txn, secret, email, SuppressionReason::Erased, None)
+        .await
+        .context("Failed to record the erasure")?;
+    txn.commit().await.context("Failed to commit")?;
+    Ok(())
+}
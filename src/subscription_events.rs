@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::request_metadata::RequestMetadata;
+
+/// A change to a subscription worth proving later on: how and when someone
+/// opted in, and who changed their subscription afterwards.
+#[derive(Debug, Clone, Copy)]
+pub enum SubscriptionEventKind {
+    Signup,
+    Confirmation,
+    Unsubscribe,
+    BounceSuppression,
+    AdminEdit,
+}
+
+impl SubscriptionEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionEventKind::Signup => "signup",
+            SubscriptionEventKind::Confirmation => "confirmation",
+            SubscriptionEventKind::Unsubscribe => "unsubscribe",
+            SubscriptionEventKind::BounceSuppression => "bounce_suppression",
+            SubscriptionEventKind::AdminEdit => "admin_edit",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SubscriptionEvent<'a> {
+    kind: SubscriptionEventKind,
+    subscriber_email: &'a str,
+    source: Option<&'a str>,
+    request: Option<&'a RequestMetadata>,
+    admin_user_id: Option<Uuid>,
+    detail: Option<&'a str>,
+}
+
+impl<'a> SubscriptionEvent<'a> {
+    pub fn new(kind: SubscriptionEventKind, subscriber_email: &'a str) -> Self {
+        SubscriptionEvent {
+            kind,
+            subscriber_email,
+            source: None,
+            request: None,
+            admin_user_id: None,
+            detail: None,
+        }
+    }
+
+    /// The form, import or integration the change came through.
+    pub fn source(mut self, source: &'a str) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn request(mut self, request: &'a RequestMetadata) -> Self {
+        self.request = Some(request);
+        self
+    }
+
+    pub fn admin(mut self, admin_user_id: Uuid) -> Self {
+        self.admin_user_id = Some(admin_user_id);
+        self
+    }
+
+    pub fn detail(mut self, detail: &'a str) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Record the event, meant to run in the transaction making the change.
+    #[tracing::instrument(name = "Record subscription event", skip(conn), err(Debug))]
+    pub async fn record(self, conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+INSERT INTO subscription_events (
+    subscriber_email,
+    event_type,
+    source,
+    source_ip,
+    user_agent,
+    admin_user_id,
+    detail,
+    occurred_at
+)
+VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+            "#,
+            self.subscriber_email,
+            self.kind.as_str(),
+            self.source,
+            self.request.and_then(|r| r.source_ip.as_deref()),
+            self.request.and_then(|r| r.user_agent.as_deref()),
+            self.admin_user_id,
+            self.detail,
+        )
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct RecordedSubscriptionEvent {
+    pub event_type: String,
+    pub source: Option<String>,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub admin_username: Option<String>,
+    pub detail: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "List subscription events", skip(pool), err(Debug))]
+pub async fn list_subscription_events(
+    pool: &PgPool,
+    subscriber_email: &str,
+) -> Result<Vec<RecordedSubscriptionEvent>, sqlx::Error> {
+    sqlx::query_as!(
+        RecordedSubscriptionEvent,
+        r#"
+SELECT
+    e.event_type,
+    e.source,
+    e.source_ip,
+    e.user_agent,
+    u.username AS "admin_username?",
+    e.detail,
+    e.occurred_at
+FROM subscription_events e
+LEFT JOIN users u ON u.user_id = e.admin_user_id
+WHERE lower(e.subscriber_email) = lower($1)
+ORDER BY e.occurred_at, e.id
+        "#,
+        subscriber_email,
+    )
+    .fetch_all(pool)
+    .await
+}
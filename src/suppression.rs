@@ -0,0 +1,175 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+
+/// Why an address must never be mailed or subscribed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuppressionReason {
+    Erased,
+    HardBounce,
+    Complaint,
+    Manual,
+}
+
+impl SuppressionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionReason::Erased => "erased",
+            SuppressionReason::HardBounce => "hard_bounce",
+            SuppressionReason::Complaint => "complaint",
+            SuppressionReason::Manual => "manual",
+        }
+    }
+}
+
+pub struct Suppression {
+    pub email_hash: String,
+    pub email: Option<String>,
+    pub reason: String,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The HMAC key of the email hashes, derived from the application secret so
+/// an erased address cannot be found again by hashing candidate addresses.
+fn hash_key(secret: &SecretString) -> Vec<u8> {
+    let mut key = vec![0u8; 32];
+    Hkdf::<Sha256>::new(None, secret.expose_secret().as_bytes())
+        .expand(b"zero2prod email_hash", &mut key)
+        .expect("HKDF can output 32 bytes");
+    key
+}
+
+/// The key of the suppression list. Addresses are compared ignoring case and
+/// surrounding whitespace, and erased ones are only ever stored as this hash.
+pub fn email_hash(secret: &SecretString, email: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&hash_key(secret)).expect("HMAC can take key of any size");
+    mac.update(email.trim().to_lowercase().as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Append a condition excluding suppressed addresses of `subscriptions`. It
+/// hashes their email the same way [`email_hash`] does, with `pgcrypto`.
+pub fn push_not_suppressed(qb: &mut QueryBuilder<'_, Postgres>, secret: &SecretString) {
+    qb.push(
+        "NOT EXISTS (SELECT 1 FROM suppressions WHERE email_hash = \
+        encode(hmac(convert_to(lower(btrim(subscriptions.email)), 'UTF8'), ",
+    );
+    qb.push_bind(hash_key(secret));
+    qb.push(", 'sha256'), 'hex'))");
+}
+
+/// Add `email` to the suppression list. An erasure only stores the hash, and
+/// wipes the address from an entry that already exists. Confirmation links
+/// already sent to the address stop working.
+#[tracing::instrument(name = "Suppress email address", skip(conn, secret), err(Debug))]
+pub async fn suppress(
+    conn: &mut PgConnection,
+    secret: &SecretString,
+    email: &str,
+    reason: SuppressionReason,
+    detail: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let erased = reason == SuppressionReason::Erased;
+    let email_hash = email_hash(secret, email);
+    sqlx::query!(
+        r#"
+INSERT INTO suppressions (email_hash, email, reason, detail, created_at)
+VALUES ($1, $2, $3, $4, now())
+ON CONFLICT (email_hash) DO UPDATE
+SET email = NULL, reason = EXCLUDED.reason, detail = NULL
+WHERE EXCLUDED.reason = 'erased'
+        "#,
+        email_hash,
+        (!erased).then_some(email),
+        reason.as_str(),
+        detail,
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        r#"
+DELETE FROM subscription_tokens
+WHERE subscriber_id IN (SELECT id FROM subscriptions WHERE lower(email) = lower($1))
+        "#,
+        email,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Check for a suppressed address",
+    skip(conn, secret),
+    err(Debug)
+)]
+pub async fn is_suppressed(
+    conn: &mut PgConnection,
+    secret: &SecretString,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM suppressions WHERE email_hash = $1) AS "suppressed!""#,
+        email_hash(secret, email),
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(row.suppressed)
+}
+
+#[tracing::instrument(name = "List suppressions", skip(pool))]
+pub async fn list_suppressions(pool: &PgPool) -> Result<Vec<Suppression>, anyhow::Error> {
+    sqlx::query_as!(
+        Suppression,
+        r#"
+SELECT email_hash, email, reason, detail, created_at
+FROM suppressions
+ORDER BY created_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to list suppressions")
+}
+
+#[tracing::instrument(name = "Remove suppression", skip(pool))]
+pub async fn remove_suppression(pool: &PgPool, email_hash: &str) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"DELETE FROM suppressions WHERE email_hash = $1"#,
+        email_hash,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to remove the suppression")?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod test {
+    use assertor::*;
+    use secrecy::SecretString;
+
+    use super::email_hash;
+
+    #[test]
+    fn the_hash_ignores_case_and_surrounding_whitespace() {
+        let secret = SecretString::from("secret");
+        assert_that!(email_hash(&secret, " Ursula@Example.com "))
+            .is_equal_to(email_hash(&secret, "ursula@example.com"));
+        assert_that!(email_hash(&secret, "ursula@example.com"))
+            .is_not_equal_to(email_hash(&secret, "le_guin@example.com"));
+    }
+
+    #[test]
+    fn the_hash_cannot_be_computed_without_the_secret() {
+        let email = "ursula@example.com";
+        let hash = email_hash(&SecretString::from("secret"), email);
+        assert_that!(hash).is_not_equal_to(email_hash(&SecretString::from("other"), email));
+    }
+}
@@ -2,6 +2,8 @@ use axum::response::IntoResponse;
 use axum_messages::Messages;
 use reqwest::StatusCode;
 
+use crate::{html, html::Markup};
+
 pub type Transaction = sqlx::Transaction<'static, sqlx::Postgres>;
 
 pub fn e500<E>(e: E) -> AppError
@@ -18,10 +20,37 @@ where
     AppError::BadRequest(e.into())
 }
 
+pub fn e401<E>(e: E) -> AppError
+where
+    E: Into<anyhow::Error>,
+{
+    AppError::Unauthorized(e.into())
+}
+
+pub fn e403<E>(e: E) -> AppError
+where
+    E: Into<anyhow::Error>,
+{
+    AppError::Forbidden(e.into())
+}
+
+pub fn e404<E>(e: E) -> AppError
+where
+    E: Into<anyhow::Error>,
+{
+    AppError::NotFound(e.into())
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum AppError {
     #[error("{0}")]
     BadRequest(#[source] anyhow::Error),
+    #[error("{0}")]
+    Unauthorized(#[source] anyhow::Error),
+    #[error("{0}")]
+    Forbidden(#[source] anyhow::Error),
+    #[error("{0}")]
+    NotFound(#[source] anyhow::Error),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -30,6 +59,9 @@ impl AppError {
     fn status(&self) -> StatusCode {
         match self {
             AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
             AppError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -41,12 +73,11 @@ impl IntoResponse for AppError {
     }
 }
 
-pub fn get_all_messages(messages: Messages) -> String {
+pub fn get_all_messages(messages: Messages) -> Markup {
     let mut messages = messages.into_iter().collect::<Vec<_>>();
     messages.sort_by_key(|m| std::cmp::Reverse(m.level));
     messages
         .iter()
-        .map(|m| format!("<p><i>{}</i></p>", m.message))
-        .collect::<Vec<_>>()
-        .join("")
+        .map(|m| html!("<p><i>{}</i></p>", m.message))
+        .collect()
 }
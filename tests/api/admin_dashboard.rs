@@ -1,4 +1,4 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{TestUser, assert_is_redirect_to, spawn_app};
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_access_the_admin_dashboard() {
@@ -33,3 +33,23 @@ async fn logout_clears_session_state() {
     let response = app.get_admin_dashboard().await;
     assert_is_redirect_to(&response, "/login");
 }
+
+#[tokio::test]
+async fn the_username_is_escaped_on_the_dashboard() {
+    let app = spawn_app().await;
+    let user = TestUser {
+        username: r#"<script>alert("owned")</script>"#.into(),
+        ..TestUser::generate()
+    };
+    user.store(&app.pool).await;
+
+    app.post_login(serde_json::json!({
+        "username": user.username,
+        "password": user.password,
+    }))
+    .await;
+
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(!html_page.contains("<script>"));
+    assert!(html_page.contains("Welcome &lt;script&gt;alert(&quot;owned&quot;)&lt;/script&gt;!"));
+}
@@ -0,0 +1,277 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+fn when_sending_an_email() -> wiremock::MockBuilder {
+    Mock::given(matchers::path("/email")).and(matchers::method("POST"))
+}
+
+async fn insert_subscriber(app: &TestApp, email: &str, name: &str, status: &str) -> Uuid {
+    insert_subscriber_at(app, email, name, status, Utc::now()).await
+}
+
+async fn insert_subscriber_at(
+    app: &TestApp,
+    email: &str,
+    name: &str,
+    status: &str,
+    subscribed_at: chrono::DateTime<Utc>,
+) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        id,
+        email,
+        name,
+        subscribed_at,
+        status,
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    id
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_browse_subscribers() {
+    let app = spawn_app().await;
+    let response = app.get_admin_subscribers("").await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_admin_subscriber_action(Uuid::new_v4(), "delete")
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_and_filtered_by_status() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+    insert_subscriber(
+        &app,
+        "le_guin@example.com",
+        "Le Guin",
+        "pending_confirmation",
+    )
+    .await;
+    insert_subscriber(
+        &app,
+        "tolkien@example.com",
+        "Ursula Tolkien",
+        "pending_confirmation",
+    )
+    .await;
+
+    let html_page = app.get_admin_subscribers_html("?q=ursula").await;
+    assert!(html_page.contains("ursula@example.com"));
+    assert!(html_page.contains("tolkien@example.com"));
+    assert!(!html_page.contains("le_guin@example.com"));
+
+    let html_page = app
+        .get_admin_subscribers_html("?q=ursula&status=confirmed")
+        .await;
+    assert!(html_page.contains("ursula@example.com"));
+    assert!(!html_page.contains("tolkien@example.com"));
+}
+
+#[tokio::test]
+async fn search_wildcards_are_matched_literally() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+
+    let html_page = app.get_admin_subscribers_html("?q=%25").await;
+    assert!(!html_page.contains("ursula@example.com"));
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let now = Utc::now();
+    for i in 0..55 {
+        insert_subscriber_at(
+            &app,
+            &format!("subscriber{i:02}@example.com"),
+            "Someone",
+            "confirmed",
+            now - Duration::minutes(i),
+        )
+        .await;
+    }
+
+    let html_page = app.get_admin_subscribers_html("").await;
+    assert!(html_page.contains("subscriber00@example.com"));
+    assert!(html_page.contains("subscriber49@example.com"));
+    assert!(!html_page.contains("subscriber50@example.com"));
+
+    let next_page = html_page
+        .split(r#"<a href="/admin/subscribers?"#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .expect("a link to the next page")
+        .replace("&amp;", "&");
+    let html_page = app
+        .get_admin_subscribers_html(&format!("?{next_page}"))
+        .await;
+    assert!(!html_page.contains("subscriber49@example.com"));
+    assert!(html_page.contains("subscriber50@example.com"));
+    assert!(html_page.contains("subscriber54@example.com"));
+    assert!(!html_page.contains("Next page"));
+}
+
+#[tokio::test]
+async fn the_detail_page_shows_the_confirmation_state_and_delivery_history() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let subscriber_id = insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(serde_json::json!({
+        "title": "Issue #1",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app
+        .get_admin_subscribers_html(&format!("/{subscriber_id}"))
+        .await;
+    assert!(html_page.contains("No confirmation link was needed."));
+    assert!(html_page.contains("<td>Issue #1</td><td>delivered</td>"));
+}
+
+#[tokio::test]
+async fn an_unknown_subscriber_returns_a_404() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let response = app
+        .get_admin_subscribers(&format!("/{}", Uuid::new_v4()))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn a_confirmation_email_can_be_resent() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let subscriber_id =
+        insert_subscriber(&app, "ursula@example.com", "Ursula", "pending_confirmation").await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_admin_subscriber_action(subscriber_id, "resend_confirmation")
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{subscriber_id}"));
+
+    let html_page = app
+        .get_admin_subscribers_html(&format!("/{subscriber_id}"))
+        .await;
+    assert!(html_page.contains("<p><i>A new confirmation email has been sent.</i></p>"));
+    assert!(html_page.contains("Awaiting confirmation, 1 link(s) sent"));
+}
+
+#[tokio::test]
+async fn a_subscriber_can_be_unsubscribed_manually() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let subscriber_id = insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+
+    let response = app
+        .post_admin_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{subscriber_id}"));
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+
+    let html_page = app
+        .get_admin_subscribers_html(&format!("/{subscriber_id}"))
+        .await;
+    assert!(html_page.contains("Consent history"));
+    assert!(html_page.contains("<td>unsubscribe</td>"));
+    assert!(html_page.contains(&format!("<td>{}</td>", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn an_old_confirmation_link_does_not_undo_an_unsubscribe() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com")
+        .await;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(&email_request).html;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+        .id;
+
+    app.post_admin_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn a_subscriber_can_be_deleted() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let subscriber_id =
+        insert_subscriber(&app, "ursula@example.com", "Ursula", "pending_confirmation").await;
+    sqlx::query!(
+        "INSERT INTO subscription_tokens (subscription_token, subscriber_id) VALUES ('token', $1)",
+        subscriber_id
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let response = app
+        .post_admin_subscriber_action(subscriber_id, "delete")
+        .await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+
+    let saved = sqlx::query!("SELECT count(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 0);
+}
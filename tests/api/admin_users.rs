@@ -0,0 +1,371 @@
+use reqwest::StatusCode;
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{TestApp, TestUser, assert_is_redirect_to, spawn_app};
+
+impl TestApp {
+    async fn get_admin_users_html(&self) -> String {
+        self.client
+            .get(format!("{}/admin/users", self.address()))
+            .send()
+            .await
+            .expect("failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    async fn post_admin_users(&self, path: &str, body: serde_json::Value) -> reqwest::Response {
+        self.client
+            .post(format!("{}/admin/users{path}", self.address()))
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    async fn login_as(&self, user: &TestUser) -> reqwest::Response {
+        self.post_login(serde_json::json!({
+            "username": user.username,
+            "password": user.password,
+        }))
+        .await
+    }
+
+    /// Invite `email` as the logged in owner and return the link of the
+    /// invitation email.
+    async fn invite(&self, email: &str, role: &str) -> reqwest::Url {
+        let _mock_guard = Mock::given(matchers::path("/email"))
+            .and(matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK))
+            .named("Send invitation")
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+        let response = self
+            .post_admin_users("/invite", serde_json::json!({"email": email, "role": role}))
+            .await;
+        assert_is_redirect_to(&response, "/admin/users");
+
+        let email_request = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        self.get_confirmation_links(&email_request).plain_text
+    }
+
+    async fn accept_invitation(
+        &self,
+        link: &reqwest::Url,
+        username: &str,
+        password: &str,
+    ) -> reqwest::Response {
+        let mut body: serde_json::Map<String, serde_json::Value> = link
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned().into()))
+            .collect();
+        body.insert("username".into(), username.into());
+        body.insert("password".into(), password.into());
+        body.insert("password_check".into(), password.into());
+        body.insert(
+            "csrf_token".into(),
+            self.csrf_token_of(&self.client).await.into(),
+        );
+        self.client
+            .post(format!("{}/invitations/accept", self.address()))
+            .form(&body)
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+}
+
+#[tokio::test]
+async fn an_invited_user_chooses_credentials_and_can_then_log_in() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let link = app.invite("ursula@example.com", "editor").await;
+    let html_page = app.get_admin_users_html().await;
+    assert!(html_page.contains("The invitation has been sent."));
+    assert!(html_page.contains("<td>ursula@example.com</td><td>editor</td>"));
+    app.post_logout().await;
+
+    let html_page = app.client.get(link.clone()).send().await.unwrap();
+    assert_eq!(html_page.status(), StatusCode::OK);
+    assert!(
+        html_page
+            .text()
+            .await
+            .unwrap()
+            .contains("invited to join as editor with the address ursula@example.com")
+    );
+    let response = app
+        .accept_invitation(&link, "ursula", "a-long-chosen-password")
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_login(serde_json::json!({
+            "username": "ursula",
+            "password": "a-long-chosen-password",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains("You are signed in as editor."));
+}
+
+#[tokio::test]
+async fn an_invitation_can_only_be_used_once() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let link = app.invite("ursula@example.com", "viewer").await;
+
+    let response = app
+        .accept_invitation(&link, "ursula", "a-long-chosen-password")
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .accept_invitation(&link, "ursula2", "a-long-chosen-password")
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app.client.get(link).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn an_invitation_must_be_signed_pending_and_not_expired() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let mut tampered = app.invite("ursula@example.com", "viewer").await;
+    let query = tampered.query().unwrap().replace("expires=", "expires=9");
+    tampered.set_query(Some(&query));
+    let response = app.client.get(tampered).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let revoked = app.invite("revoked@example.com", "viewer").await;
+    let invitation_id = revoked
+        .query_pairs()
+        .find(|(k, _)| k == "invitation_id")
+        .unwrap()
+        .1
+        .into_owned();
+    let response = app
+        .post_admin_users(
+            &format!("/invitations/{invitation_id}/revoke"),
+            serde_json::json!({}),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let response = app
+        .accept_invitation(&revoked, "revoked", "a-long-chosen-password")
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let expired = app.invite("expired@example.com", "viewer").await;
+    sqlx::query!("UPDATE user_invitations SET expires_at = now() - interval '1 minute'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let response = app
+        .accept_invitation(&expired, "expired", "a-long-chosen-password")
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn an_invitee_must_choose_a_secure_password() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let link = app.invite("ursula@example.com", "viewer").await;
+
+    let response = app.accept_invitation(&link, "ursula", "short").await;
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        &format!("/invitations/accept?{}", link.query().unwrap())
+    );
+    let html_page = app
+        .client
+        .get(link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("New password must be at least 12 characters."));
+
+    // The invitation was not used up by the failed attempt.
+    let response = app
+        .accept_invitation(&link, "ursula", "a-long-chosen-password")
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn roles_limit_what_users_can_do() {
+    let app = spawn_app().await;
+    let viewer = TestUser::with_role("viewer");
+    viewer.store(&app.pool).await;
+    app.login_as(&viewer).await;
+
+    let response = app.get_admin_subscribers("").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Issue #1",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Newsletter body as plain text",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = app
+        .client
+        .get(format!("{}/admin/users", app.address()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn viewers_cannot_export_subscriber_data() {
+    let app = spawn_app().await;
+    let viewer = TestUser::with_role("viewer");
+    viewer.store(&app.pool).await;
+    app.login_as(&viewer).await;
+
+    let response = app.get_subscribers_export("format=csv").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = app
+        .get_admin_subscribers(&format!("/{}/data", uuid::Uuid::new_v4()))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn editors_cannot_remove_subscribers_or_suppressions() {
+    let app = spawn_app().await;
+    let editor = TestUser::with_role("editor");
+    editor.store(&app.pool).await;
+    app.login_as(&editor).await;
+
+    for action in ["delete", "erase"] {
+        let response = app
+            .post_admin_subscriber_action(uuid::Uuid::new_v4(), action)
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+    let response = app
+        .client
+        .post(format!(
+            "{}/admin/suppressions/{}/delete",
+            app.address(),
+            "0".repeat(64)
+        ))
+        .form(&app.with_csrf_token(serde_json::json!({})).await)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn a_disabled_user_is_logged_out_and_cannot_log_in() {
+    let app = spawn_app().await;
+    let editor = TestUser::with_role("editor");
+    editor.store(&app.pool).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_admin_users(
+            &format!("/{}/disable", editor.user_id),
+            serde_json::json!({}),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+    app.post_logout().await;
+
+    let response = app.login_as(&editor).await;
+    assert_is_redirect_to(&response, "/login");
+
+    // An open session stops working too.
+    app.login_test_user().await;
+    sqlx::query!(
+        "UPDATE users SET disabled_at = now() WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn owners_cannot_remove_themselves() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    for action in ["disable", "delete"] {
+        app.post_admin_users(
+            &format!("/{}/{action}", app.test_user.user_id),
+            serde_json::json!({}),
+        )
+        .await;
+    }
+    app.post_admin_users(
+        &format!("/{}/role", app.test_user.user_id),
+        serde_json::json!({ "role": "viewer" }),
+    )
+    .await;
+
+    let saved = sqlx::query!(
+        "SELECT role, disabled_at FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.role, "owner");
+    assert!(saved.disabled_at.is_none());
+}
+
+#[tokio::test]
+async fn a_user_can_be_given_another_role_or_deleted() {
+    let app = spawn_app().await;
+    let editor = TestUser::with_role("editor");
+    editor.store(&app.pool).await;
+    app.login_test_user().await;
+
+    app.post_admin_users(
+        &format!("/{}/role", editor.user_id),
+        serde_json::json!({ "role": "publisher" }),
+    )
+    .await;
+    let saved = sqlx::query!("SELECT role FROM users WHERE user_id = $1", editor.user_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.role, "publisher");
+
+    let response = app
+        .post_admin_users(
+            &format!("/{}/delete", editor.user_id),
+            serde_json::json!({}),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let saved = sqlx::query!("SELECT count(*) AS \"count!\" FROM users")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 1);
+}
@@ -1,7 +1,18 @@
 use rstest::rstest;
 use uuid::Uuid;
 
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{
+    BreachedPasswords, TestApp, assert_is_redirect_to, spawn_app, spawn_app_with,
+};
+
+async fn change_to(app: &TestApp, new_password: &str) -> reqwest::Response {
+    app.post_change_password(serde_json::json!({
+        "current_password": app.test_user.password,
+        "new_password": new_password,
+        "new_password_check": new_password,
+    }))
+    .await
+}
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_see_the_change_password_form() {
@@ -113,6 +124,38 @@ async fn password_security(#[case] password: &'static str, #[case] error: &'stat
     assert!(html_page.contains(&format!("<p><i>{error}</i></p>")));
 }
 
+#[tokio::test]
+async fn a_guessable_password_is_rejected_with_feedback() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page.contains("must score at least 3 out of 4 on strength"));
+
+    let response = change_to(&app, "aaaaaaaaaaaaaaaa").await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page.contains("New password is too easy to guess (strength 0 out of 4"));
+    assert!(html_page.contains("Avoid repeated words and characters."));
+}
+
+#[tokio::test]
+async fn a_breached_password_is_rejected() {
+    let breached = "correct horse battery staple";
+    let breaches = BreachedPasswords::containing(&[breached]);
+    let app =
+        spawn_app_with(|c| c.password_policy.breached_passwords_dir = Some(breaches.dir.clone()))
+            .await;
+    app.login_test_user().await;
+
+    let response = change_to(&app, breached).await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page.contains("New password appears in a known data breach"));
+}
+
 #[tokio::test]
 async fn changing_password_works() {
     let app = spawn_app().await;
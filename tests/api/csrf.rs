@@ -0,0 +1,77 @@
+use reqwest::StatusCode;
+
+use crate::helpers::{assert_is_redirect_to, csrf_token_in, spawn_app};
+
+#[tokio::test]
+async fn a_login_without_a_csrf_token_is_forbidden() {
+    let app = spawn_app().await;
+
+    let response = app
+        .client
+        .post(format!("{}/login", app.address()))
+        .form(&serde_json::json!({
+            "username": app.test_user.username,
+            "password": app.test_user.password,
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn the_csrf_token_of_another_session_is_forbidden() {
+    let app = spawn_app().await;
+    let other_browser = reqwest::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let other_token = app.csrf_token_of(&other_browser).await;
+    app.login_test_user().await;
+
+    let response = app
+        .client
+        .post(format!("{}/admin/logout", app.address()))
+        .form(&serde_json::json!({ "csrf_token": other_token }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        app.get_admin_dashboard().await.status(),
+        StatusCode::OK,
+        "the user was logged out"
+    );
+}
+
+#[tokio::test]
+async fn the_csrf_token_changes_when_logging_in() {
+    let app = spawn_app().await;
+    let before = app.csrf_token_of(&app.client).await;
+
+    app.login_test_user().await;
+
+    let after = csrf_token_in(&app.get_admin_dashboard_html().await);
+    assert_ne!(before, after);
+}
+
+#[tokio::test]
+async fn admin_forms_carry_the_csrf_token() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let token = app.csrf_token_of(&app.client).await;
+
+    for html_page in [
+        app.get_admin_dashboard_html().await,
+        app.get_change_password_html().await,
+        app.get_newsletters_html().await,
+        app.get_segments_html().await,
+        app.get_subscribers_import_html().await,
+    ] {
+        assert_eq!(csrf_token_in(&html_page), token);
+    }
+}
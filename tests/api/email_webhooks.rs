@@ -0,0 +1,176 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{TestApp, spawn_app};
+
+async fn insert_confirmed_subscriber(app: &TestApp, email: &str) {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, 'Ursula', $3, 'confirmed')
+        "#,
+        Uuid::new_v4(),
+        email,
+        Utc::now(),
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+}
+
+fn bounce(bounce_type: &str, email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": bounce_type,
+        "Email": email,
+        "Description": "The server was unable to deliver your message",
+    })
+}
+
+async fn subscriber_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+        .status
+}
+
+#[tokio::test]
+async fn webhooks_without_valid_credentials_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_email_webhook(
+            "postmark",
+            "wrong-token",
+            bounce("HardBounce", "ursula@example.com"),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .client
+        .post(format!("{}/webhooks/email/postmark", app.address()))
+        .json(&bounce("HardBounce", "ursula@example.com"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn unknown_providers_return_a_404() {
+    let app = spawn_app().await;
+    let response = app
+        .post_email_webhook(
+            "carrier-pigeon",
+            &app.webhook_token,
+            bounce("HardBounce", "ursula@example.com"),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn a_hard_bounce_suppresses_the_subscriber() {
+    let app = spawn_app().await;
+    insert_confirmed_subscriber(&app, "ursula@example.com").await;
+
+    let response = app
+        .post_email_webhook(
+            "postmark",
+            &app.webhook_token,
+            bounce("HardBounce", "ursula@example.com"),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(subscriber_status(&app).await, "bounced");
+    let log = sqlx::query!("SELECT outcome, newsletter_issue_id FROM issue_delivery_log")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(log.outcome, "hard_bounce");
+    assert!(log.newsletter_issue_id.is_none());
+    let event = sqlx::query!("SELECT event_type, source FROM subscription_events")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(event.event_type, "bounce_suppression");
+    assert_eq!(event.source.as_deref(), Some("postmark"));
+
+    // Bounced subscribers no longer receive newsletters.
+    app.login_test_user().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn a_soft_bounce_is_logged_without_suppressing_the_subscriber() {
+    let app = spawn_app().await;
+    insert_confirmed_subscriber(&app, "ursula@example.com").await;
+
+    let response = app
+        .post_email_webhook(
+            "postmark",
+            &app.webhook_token,
+            bounce("Transient", "ursula@example.com"),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+    let log = sqlx::query!("SELECT outcome FROM issue_delivery_log")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(log.outcome, "soft_bounce");
+}
+
+#[tokio::test]
+async fn a_spam_complaint_suppresses_the_subscriber() {
+    let app = spawn_app().await;
+    insert_confirmed_subscriber(&app, "ursula@example.com").await;
+
+    let response = app
+        .post_email_webhook(
+            "postmark",
+            &app.webhook_token,
+            serde_json::json!({
+                "RecordType": "SpamComplaint",
+                "Type": "SpamComplaint",
+                "Email": "ursula@example.com",
+            }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(subscriber_status(&app).await, "complained");
+}
+
+#[tokio::test]
+async fn an_invalid_payload_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+    let response = app
+        .post_email_webhook(
+            "postmark",
+            &app.webhook_token,
+            serde_json::json!({ "Email": "ursula@example.com" }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
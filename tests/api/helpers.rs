@@ -1,16 +1,18 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 
 use axum::http::{self};
 use linkify::{Link, LinkFinder, LinkKind};
 use once_cell::sync::Lazy;
 use reqwest::{StatusCode, Url};
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, SecretString};
+use sha1::{Digest, Sha1};
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use uuid::Uuid;
 use wiremock::MockServer;
 use zero2prod::{
+    anti_bot::{ChallengeSettings, FormToken},
     authentication,
-    configuration::{DatabaseSettings, get_configuration},
+    configuration::{DatabaseSettings, Settings, get_configuration},
     email_client::EmailClient,
     issue_delivery_worker::{ExecutionOutCome, try_execute_task},
     startup::{Application, get_connection_pool},
@@ -23,6 +25,9 @@ static TRACING: Lazy<()> = Lazy::new(|| {
     }
 });
 
+/// The answer the fake challenge verifier accepts.
+pub const TEST_CHALLENGE_RESPONSE: &str = "human";
+
 pub struct TestApp {
     pub client: reqwest::Client,
     pub port: u16,
@@ -30,6 +35,9 @@ pub struct TestApp {
     pub email_server: MockServer,
     pub email_client: EmailClient,
     pub test_user: TestUser,
+    pub webhook_token: String,
+    pub hmac_secret: SecretString,
+    pub setup_token: Option<SecretString>,
 }
 
 pub struct ConfirmationLinks {
@@ -42,7 +50,21 @@ impl TestApp {
         format!("http://127.0.0.1:{}", self.port)
     }
 
+    /// Submit the subscription form the way a human would, passing the
+    /// anti-bot checks.
     pub async fn post_subscriptions(&self, body: &str) -> reqwest::Response {
+        self.post_subscriptions_raw(&format!("{body}&{}", self.anti_bot_fields()))
+            .await
+    }
+
+    pub fn anti_bot_fields(&self) -> String {
+        format!(
+            "form_token={}&challenge_response={TEST_CHALLENGE_RESPONSE}",
+            FormToken::issue(&self.hmac_secret).as_ref()
+        )
+    }
+
+    pub async fn post_subscriptions_raw(&self, body: &str) -> reqwest::Response {
         self.client
             .post(format!("{}/subscriptions", self.address()))
             .header(
@@ -66,14 +88,18 @@ impl TestApp {
     pub async fn post_login(&self, body: serde_json::Value) -> reqwest::Response {
         self.client
             .post(format!("{}/login", self.address()))
-            .form(&body)
+            .form(&self.with_csrf_token(body).await)
             .send()
             .await
             .expect("failed to execute request")
     }
 
     pub async fn get_login_html(&self) -> String {
-        self.client
+        self.get_login_html_of(&self.client).await
+    }
+
+    pub async fn get_login_html_of(&self, client: &reqwest::Client) -> String {
+        client
             .get(format!("{}/login", self.address()))
             .send()
             .await
@@ -83,6 +109,18 @@ impl TestApp {
             .unwrap()
     }
 
+    /// The CSRF token of the session of `client`, as the login form carries
+    /// it.
+    pub async fn csrf_token_of(&self, client: &reqwest::Client) -> String {
+        csrf_token_in(&self.get_login_html_of(client).await)
+    }
+
+    /// Add the CSRF token of the session to a form, the way the pages do.
+    pub async fn with_csrf_token(&self, mut body: serde_json::Value) -> serde_json::Value {
+        body["csrf_token"] = self.csrf_token_of(&self.client).await.into();
+        body
+    }
+
     pub async fn get_admin_dashboard(&self) -> reqwest::Response {
         self.client
             .get(format!("{}/admin/dashboard", self.address()))
@@ -98,6 +136,7 @@ impl TestApp {
     pub async fn post_logout(&self) -> reqwest::Response {
         self.client
             .post(format!("{}/admin/logout", self.address()))
+            .form(&self.with_csrf_token(serde_json::json!({})).await)
             .send()
             .await
             .expect("failed to execute request")
@@ -144,7 +183,7 @@ impl TestApp {
     pub async fn post_change_password(&self, body: serde_json::Value) -> reqwest::Response {
         self.client
             .post(format!("{}/admin/password", self.address()))
-            .form(&body)
+            .form(&self.with_csrf_token(body).await)
             .send()
             .await
             .expect("failed to execute request")
@@ -165,7 +204,138 @@ impl TestApp {
     pub async fn post_newsletters(&self, body: serde_json::Value) -> reqwest::Response {
         self.client
             .post(format!("{}/admin/newsletters", self.address()))
-            .form(&body)
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    pub async fn get_segments(&self) -> reqwest::Response {
+        self.client
+            .get(format!("{}/admin/segments", self.address()))
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    pub async fn get_segments_html(&self) -> String {
+        self.get_segments().await.text().await.unwrap()
+    }
+
+    pub async fn post_segments(&self, body: serde_json::Value) -> reqwest::Response {
+        self.client
+            .post(format!("{}/admin/segments", self.address()))
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    /// The CSRF token must come first in the upload, so the form is built
+    /// from one starting with it.
+    pub async fn post_subscribers_import(
+        &self,
+        form: impl FnOnce(reqwest::multipart::Form) -> reqwest::multipart::Form,
+    ) -> reqwest::Response {
+        let csrf_form = reqwest::multipart::Form::new()
+            .text("csrf_token", self.csrf_token_of(&self.client).await);
+        self.client
+            .post(format!("{}/admin/subscribers/import", self.address()))
+            .multipart(form(csrf_form))
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    pub async fn get_subscribers_import_html(&self) -> String {
+        self.client
+            .get(format!("{}/admin/subscribers/import", self.address()))
+            .send()
+            .await
+            .expect("failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_subscribers_export(&self, query: &str) -> reqwest::Response {
+        self.client
+            .get(format!(
+                "{}/admin/subscribers/export?{query}",
+                self.address()
+            ))
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    pub async fn get_admin_subscribers(&self, path_and_query: &str) -> reqwest::Response {
+        self.client
+            .get(format!(
+                "{}/admin/subscribers{path_and_query}",
+                self.address()
+            ))
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    pub async fn get_admin_subscribers_html(&self, path_and_query: &str) -> String {
+        self.get_admin_subscribers(path_and_query)
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_admin_subscriber_action(
+        &self,
+        subscriber_id: Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        self.client
+            .post(format!(
+                "{}/admin/subscribers/{subscriber_id}/{action}",
+                self.address()
+            ))
+            .form(&self.with_csrf_token(serde_json::json!({})).await)
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    /// The emails received so far, once there are at least `count`. Some are
+    /// sent in the background, after the answer.
+    pub async fn wait_for_emails(&self, count: usize) -> Vec<wiremock::Request> {
+        for _ in 0..100 {
+            let requests = self.email_server.received_requests().await.unwrap();
+            if requests.len() >= count {
+                return requests;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("{count} email(s) were not sent in time");
+    }
+
+    pub async fn post_data_request(&self, email: &str) -> reqwest::Response {
+        self.client
+            .post(format!("{}/subscriptions/data", self.address()))
+            .form(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    pub async fn post_email_webhook(
+        &self,
+        provider: &str,
+        password: &str,
+        body: serde_json::Value,
+    ) -> reqwest::Response {
+        self.client
+            .post(format!("{}/webhooks/email/{provider}", self.address()))
+            .basic_auth("postmark", Some(password))
+            .json(&body)
             .send()
             .await
             .expect("failed to execute request")
@@ -184,6 +354,11 @@ impl TestApp {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawn an app with test specific tweaks to its configuration.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     let email_server = MockServer::start().await;
@@ -201,6 +376,16 @@ pub async fn spawn_app() -> TestApp {
 
         c.application.port = address.port();
 
+        // Every test app gets its own buckets in the shared Redis.
+        c.rate_limit.key_prefix = format!("rate_limit:{}", Uuid::new_v4());
+
+        c.anti_bot.min_submit_seconds = 0;
+        c.anti_bot.challenge = ChallengeSettings::Fake {
+            expected_response: TEST_CHALLENGE_RESPONSE.into(),
+        };
+
+        configure(&mut c);
+
         c
     };
 
@@ -215,6 +400,7 @@ pub async fn spawn_app() -> TestApp {
     let application = Application::build(configuration.clone())
         .await
         .expect("failed to build application");
+    let setup_token = application.setup_token.clone();
 
     tokio::spawn(async move {
         application
@@ -236,6 +422,13 @@ pub async fn spawn_app() -> TestApp {
         email_server,
         email_client,
         test_user: TestUser::generate(),
+        webhook_token: configuration
+            .email_client
+            .webhook_token
+            .expose_secret()
+            .to_string(),
+        hmac_secret: configuration.application.hmac_secret.clone(),
+        setup_token,
     };
 
     app.test_user.store(&app.pool).await;
@@ -262,6 +455,16 @@ async fn configure_database(configuration: &DatabaseSettings) {
         .expect("can't migrate database");
 }
 
+/// The token in the hidden CSRF field of a page.
+pub fn csrf_token_in(html_page: &str) -> String {
+    html_page
+        .split(r#"name="csrf_token" value=""#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .expect("a CSRF token")
+        .to_string()
+}
+
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status(), StatusCode::SEE_OTHER);
     assert_eq!(
@@ -270,33 +473,72 @@ pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     );
 }
 
+/// A directory of breached password ranges, laid out like the downloads of
+/// the Have I Been Pwned API, removed when the test ends.
+pub struct BreachedPasswords {
+    pub dir: PathBuf,
+}
+
+impl BreachedPasswords {
+    pub fn containing(passwords: &[&str]) -> Self {
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        for password in passwords {
+            let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+            std::fs::write(
+                dir.join(format!("{}.txt", &hash[..5])),
+                format!("{}:1337\n", &hash[5..]),
+            )
+            .unwrap();
+        }
+        Self { dir }
+    }
+}
+
+impl Drop for BreachedPasswords {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
 #[derive(Clone)]
 pub struct TestUser {
     pub user_id: Uuid,
     pub username: String,
     pub password: String,
+    pub role: &'static str,
 }
 
 impl TestUser {
     pub fn generate() -> TestUser {
+        Self::with_role("owner")
+    }
+
+    pub fn with_role(role: &'static str) -> TestUser {
         TestUser {
             user_id: Uuid::new_v4(),
             username: Uuid::new_v4().to_string(),
             password: Uuid::new_v4().to_string(),
+            role,
         }
     }
 
-    async fn store(&self, pool: &PgPool) {
-        let password_hash = authentication::compute_password_hash(self.password.clone().into())
-            .expect("Failed to create password hash");
+    pub async fn store(&self, pool: &PgPool) {
+        let hashing = get_configuration()
+            .expect("failed to get configuration")
+            .password_hashing;
+        let password_hash =
+            authentication::compute_password_hash(self.password.clone().into(), &hashing)
+                .expect("Failed to create password hash");
         sqlx::query!(
             r#"
-        INSERT INTO users (user_id, username, password_hash)
-        VALUES ($1, $2, $3)
+        INSERT INTO users (user_id, username, password_hash, role)
+        VALUES ($1, $2, $3, $4)
             "#,
             self.user_id,
             self.username,
             password_hash.expose_secret(),
+            self.role,
         )
         .execute(pool)
         .await
@@ -1,4 +1,12 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use std::time::Duration;
+
+use argon2::{
+    Algorithm, Argon2, Params, Version,
+    password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+};
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{TestUser, assert_is_redirect_to, spawn_app, spawn_app_with};
 
 #[tokio::test]
 async fn an_error_flash_message_is_set_on_failure() {
@@ -41,3 +49,196 @@ async fn redirect_to_admin_dashboard_after_login_success() {
     let html_page = app.get_admin_dashboard_html().await;
     assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
 }
+
+#[tokio::test]
+async fn a_weak_password_hash_is_upgraded_after_login() {
+    let app = spawn_app().await;
+    let weak_hash = Argon2::new(
+        Algorithm::Argon2i,
+        Version::V0x13,
+        Params::new(1024, 1, 1, None).unwrap(),
+    )
+    .hash_password(
+        app.test_user.password.as_bytes(),
+        &SaltString::generate(&mut OsRng),
+    )
+    .unwrap()
+    .to_string();
+    sqlx::query!(
+        "UPDATE users SET password_hash = $2 WHERE user_id = $1",
+        app.test_user.user_id,
+        weak_hash,
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let response = app.login_test_user().await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // The upgrade happens in the background.
+    let mut upgraded = None;
+    for _ in 0..50 {
+        let saved = sqlx::query!(
+            "SELECT password_hash FROM users WHERE user_id = $1",
+            app.test_user.user_id
+        )
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        if saved.password_hash != weak_hash {
+            upgraded = Some(saved.password_hash);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let upgraded = upgraded.expect("the hash was not upgraded");
+    assert!(upgraded.starts_with("$argon2id$v=19$m=15000,t=2,p=1$"));
+
+    app.post_logout().await;
+    let response = app.login_test_user().await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn repeated_failures_delay_further_logins() {
+    let app = spawn_app().await;
+    for _ in 0..4 {
+        app.post_login(serde_json::json!({
+                "username": app.test_user.username,
+                "password": "wrong-password",
+        }))
+        .await;
+    }
+
+    // Even the right password is refused until the delay is over.
+    let response = app.login_test_user().await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts, try again in"));
+}
+
+#[tokio::test]
+async fn a_successful_login_resets_the_username_counter_only() {
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        app.post_login(serde_json::json!({
+                "username": app.test_user.username,
+                "password": "wrong-password",
+        }))
+        .await;
+    }
+
+    let response = app.login_test_user().await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let saved = sqlx::query!("SELECT scope, failed_attempts FROM login_failures")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].scope, "ip");
+    assert_eq!(saved[0].failed_attempts, 3);
+}
+
+#[tokio::test]
+async fn forgotten_failures_are_deleted() {
+    let app = spawn_app().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO login_failures (scope, key, failed_attempts, last_failed_at, locked_until)
+        VALUES
+            ('username', 'nobody', 9, now() - interval '2 hours', NULL),
+            ('username', 'locked', 10, now() - interval '2 hours', now() + interval '1 hour')
+        "#
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    app.post_login(serde_json::json!({
+            "username": "someone",
+            "password": "wrong-password",
+    }))
+    .await;
+
+    let saved =
+        sqlx::query!("SELECT key FROM login_failures WHERE scope = 'username' ORDER BY key")
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+    let keys: Vec<_> = saved.into_iter().map(|row| row.key).collect();
+    assert_eq!(keys, ["locked", "someone"]);
+}
+
+#[tokio::test]
+async fn a_locked_out_account_notifies_its_owner_and_can_be_unlocked() {
+    let app = spawn_app_with(|c| {
+        c.login_throttling.base_delay_seconds = 0;
+        c.login_throttling.lockout_threshold = 5;
+        c.rate_limit.login.per_target = None;
+    })
+    .await;
+    sqlx::query!(
+        "UPDATE users SET email = 'owner@example.com' WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..5 {
+        app.post_login(serde_json::json!({
+                "username": app.test_user.username,
+                "password": "wrong-password",
+        }))
+        .await;
+    }
+    app.login_test_user().await;
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts"));
+
+    // Another admin, connecting from elsewhere, lifts the lockout.
+    sqlx::query!("DELETE FROM login_failures WHERE scope = 'ip'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let other_admin = TestUser::generate();
+    other_admin.store(&app.pool).await;
+    app.post_login(serde_json::json!({
+            "username": other_admin.username,
+            "password": other_admin.password,
+    }))
+    .await;
+    let html_page = app
+        .client
+        .get(format!("{}/admin/lockouts", app.address()))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(&app.test_user.username));
+    let response = app
+        .client
+        .post(format!("{}/admin/lockouts/unlock", app.address()))
+        .form(&serde_json::json!({
+            "scope": "username",
+            "key": app.test_user.username,
+            "csrf_token": app.csrf_token_of(&app.client).await,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/lockouts");
+
+    app.post_logout().await;
+    let response = app.login_test_user().await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
@@ -1,8 +1,22 @@
 mod admin_dashboard;
+mod admin_subscribers;
+mod admin_users;
 mod change_password;
+mod csrf;
+mod email_webhooks;
 mod health_check;
 mod helpers;
 mod login;
 mod newsletter;
+mod password_reset;
+mod rate_limit;
+mod segments;
+mod sessions;
+mod setup;
+mod subscriber_data;
+mod subscribers_export;
+mod subscribers_import;
 mod subscriptions;
 mod subscriptions_confirm;
+mod suppressions;
+mod two_factor;
@@ -195,3 +195,48 @@ async fn create_confirmed_subscriber(app: &TestApp) {
         .await
         .expect("failed to execute request");
 }
+
+#[tokio::test]
+async fn newsletters_are_personalized_with_subscriber_attributes() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(
+        "name=Ursula&email=ursula_le_guin%40gmail.com&attributes%5Bcompany%5D=%3CAcme%3E",
+    )
+    .await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    app.client
+        .get(confirmation_links.html)
+        .send()
+        .await
+        .unwrap();
+    drop(_mock_guard);
+
+    when_sending_an_email()
+        .and(matchers::body_partial_json(serde_json::json!({
+            "Subject": "Hello Ursula",
+            "HtmlBody": "<p>News for &lt;Acme&gt;</p>",
+            "TextBody": "News for <Acme>",
+        })))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(serde_json::json!({
+        "title": "Hello {{name}}",
+        "html_content": "<p>News for {{attributes.company}}</p>",
+        "text_content": "News for {{ attributes.company }}",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    }))
+    .await;
+
+    app.dispatch_all_pending_emails().await;
+}
@@ -0,0 +1,249 @@
+use reqwest::StatusCode;
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+const NEW_PASSWORD: &str = "a-brand-new-password";
+
+impl TestApp {
+    async fn post_forgot_password(&self, email: &str) -> reqwest::Response {
+        self.client
+            .post(format!("{}/login/forgot", self.address()))
+            .form(
+                &self
+                    .with_csrf_token(serde_json::json!({ "email": email }))
+                    .await,
+            )
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    async fn get_forgot_password_html(&self) -> String {
+        self.client
+            .get(format!("{}/login/forgot", self.address()))
+            .send()
+            .await
+            .expect("failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    async fn post_reset_password(&self, link: &reqwest::Url, password: &str) -> reqwest::Response {
+        let token = link
+            .query_pairs()
+            .find(|(k, _)| k == "token")
+            .unwrap()
+            .1
+            .into_owned();
+        self.client
+            .post(format!("{}/login/reset", self.address()))
+            .form(
+                &self
+                    .with_csrf_token(serde_json::json!({
+                        "token": token,
+                        "new_password": password,
+                        "new_password_check": password,
+                    }))
+                    .await,
+            )
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    async fn set_test_user_email(&self) {
+        sqlx::query!(
+            "UPDATE users SET email = 'owner@example.com' WHERE user_id = $1",
+            self.test_user.user_id
+        )
+        .execute(&self.pool)
+        .await
+        .unwrap();
+    }
+
+    async fn request_reset_link(&self) -> reqwest::Url {
+        let _mock_guard = Mock::given(matchers::path("/email"))
+            .and(matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK))
+            .named("Send password reset link")
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+        let response = self.post_forgot_password("owner@example.com").await;
+        assert_is_redirect_to(&response, "/login/forgot");
+
+        let email_request = self.wait_for_emails(1).await.pop().unwrap();
+        self.get_confirmation_links(&email_request).plain_text
+    }
+}
+
+#[tokio::test]
+async fn the_answer_does_not_tell_whether_an_account_exists() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    Mock::given(matchers::path("/email"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let known = app.post_forgot_password("owner@example.com").await;
+    let known_status = known.status();
+    let known_location = known.headers().get("Location").cloned();
+    let known_html = app.get_forgot_password_html().await;
+    let unknown = app.post_forgot_password("nobody@example.com").await;
+    let unknown_html = app.get_forgot_password_html().await;
+
+    assert_eq!(known_status, unknown.status());
+    assert_eq!(known_location, unknown.headers().get("Location").cloned());
+    assert_eq!(known_html, unknown_html);
+    assert!(known_html.contains("If an account uses this address"));
+    app.wait_for_emails(1).await;
+}
+
+#[tokio::test]
+async fn a_failing_email_api_does_not_change_the_answer() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    Mock::given(matchers::path("/email"))
+        .respond_with(ResponseTemplate::new(StatusCode::INTERNAL_SERVER_ERROR))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_forgot_password("owner@example.com").await;
+
+    assert_is_redirect_to(&response, "/login/forgot");
+    assert!(
+        app.get_forgot_password_html()
+            .await
+            .contains("If an account uses this address")
+    );
+    app.wait_for_emails(1).await;
+}
+
+#[tokio::test]
+async fn a_reset_link_sets_a_new_password_once() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    let link = app.request_reset_link().await;
+
+    let response = app.client.get(link.clone()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post_reset_password(&link, NEW_PASSWORD).await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_login(serde_json::json!({
+            "username": app.test_user.username,
+            "password": app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app
+        .post_login(serde_json::json!({
+            "username": app.test_user.username,
+            "password": NEW_PASSWORD,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let response = app.post_reset_password(&link, "yet-another-password").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn an_expired_reset_link_is_rejected() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    let link = app.request_reset_link().await;
+    sqlx::query!("UPDATE password_resets SET expires_at = now() - interval '1 minute'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let response = app.client.get(link.clone()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app.post_reset_password(&link, NEW_PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn the_new_password_must_be_secure() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    let link = app.request_reset_link().await;
+
+    let response = app.post_reset_password(&link, "short").await;
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        &format!("/login/reset?{}", link.query().unwrap())
+    );
+    let html_page = app
+        .client
+        .get(link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("New password must be at least 12 characters."));
+
+    app.post_reset_password(&link, "aaaaaaaaaaaaaaaa").await;
+    let html_page = app
+        .client
+        .get(link)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("New password is too easy to guess"));
+}
+
+#[tokio::test]
+async fn a_reset_logs_the_user_out_of_their_other_sessions() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    app.login_test_user().await;
+    let response = app
+        .client
+        .get(format!("{}/admin/dashboard", app.address()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The reset happens from another browser.
+    let other_browser = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let link = app.request_reset_link().await;
+    let token = link.query_pairs().next().unwrap().1.into_owned();
+    let response = other_browser
+        .post(format!("{}/login/reset", app.address()))
+        .form(&serde_json::json!({
+            "token": token,
+            "new_password": NEW_PASSWORD,
+            "new_password_check": NEW_PASSWORD,
+            "csrf_token": app.csrf_token_of(&other_browser).await,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .client
+        .get(format!("{}/admin/dashboard", app.address()))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+}
@@ -0,0 +1,87 @@
+use reqwest::{StatusCode, header};
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn repeated_logins_for_a_username_are_limited() {
+    let app = spawn_app().await;
+    let attempt = |username: &str| {
+        app.post_login(serde_json::json!({
+            "username": username,
+            "password": "wrong-password",
+        }))
+    };
+
+    for _ in 0..5 {
+        let response = attempt("ursula").await;
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let response = attempt("ursula").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.headers()[header::RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0);
+
+    // Other users are not locked out by someone hammering this one.
+    let response = attempt("le_guin").await;
+    assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn repeated_signups_for_an_email_are_limited() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..3 {
+        let response = app
+            .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+            .await;
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let response = app
+        .post_subscriptions("name=le%20guin&email=Ursula_Le_Guin%40gmail.com")
+        .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[tokio::test]
+async fn repeated_data_requests_for_an_email_are_limited() {
+    let app = spawn_app().await;
+
+    for _ in 0..3 {
+        let response = app.post_data_request("ursula_le_guin@gmail.com").await;
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let response = app.post_data_request("Ursula_Le_Guin@gmail.com").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[tokio::test]
+async fn confirmations_are_limited_per_client_address() {
+    let app = spawn_app().await;
+    let confirm = || {
+        app.client
+            .get(format!(
+                "{}/subscriptions/confirm?subscription_token=guess",
+                app.address()
+            ))
+            .send()
+    };
+
+    for _ in 0..20 {
+        let response = confirm().await.unwrap();
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let response = confirm().await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
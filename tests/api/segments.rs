@@ -0,0 +1,181 @@
+use axum::http::StatusCode;
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+fn when_sending_an_email() -> wiremock::MockBuilder {
+    Mock::given(matchers::path("/email")).and(matchers::method("POST"))
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_segments() {
+    let app = spawn_app().await;
+    let response = app.get_segments().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_segments(serde_json::json!({
+            "name": "beta",
+            "definition": r#"{"email_domain": "example.com"}"#,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn an_invalid_segment_definition_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_segments(serde_json::json!({
+            "name": "beta",
+            "definition": r#"{"favourite_colour": "blue"}"#,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/segments");
+
+    let html_page = app.get_segments_html().await;
+    assert!(html_page.contains("The segment definition is invalid"));
+}
+
+#[tokio::test]
+async fn segment_names_must_be_unique() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let body = serde_json::json!({
+        "name": "beta",
+        "definition": r#"{"email_domain": "example.com"}"#,
+    });
+    app.post_segments(body.clone()).await;
+    let html_page = app.get_segments_html().await;
+    assert!(html_page.contains("<p><i>The segment has been created.</i></p>"));
+
+    let response = app.post_segments(body).await;
+    assert_is_redirect_to(&response, "/admin/segments");
+    let html_page = app.get_segments_html().await;
+    assert!(html_page.contains("<p><i>A segment with this name already exists.</i></p>"));
+}
+
+#[tokio::test]
+async fn the_publish_page_previews_the_segment_size() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app, serde_json::json!({"email": "ursula@example.com"})).await;
+    create_confirmed_subscriber(&app, serde_json::json!({"email": "le_guin@example.org"})).await;
+
+    app.post_segments(serde_json::json!({
+        "name": "example.com readers",
+        "definition": r#"{"email_domain": "example.com"}"#,
+    }))
+    .await;
+
+    let html_page = app.get_newsletters_html().await;
+    assert!(html_page.contains("All confirmed subscribers (2 recipients)"));
+    assert!(html_page.contains("example.com readers (1 recipients)"));
+}
+
+#[tokio::test]
+async fn newsletters_are_only_delivered_to_the_selected_segment() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app, serde_json::json!({"email": "ursula@example.com"})).await;
+    create_confirmed_subscriber(&app, serde_json::json!({"email": "le_guin@example.org"})).await;
+
+    app.post_segments(serde_json::json!({
+        "name": "example.com readers",
+        "definition": r#"{"email_domain": "example.com"}"#,
+    }))
+    .await;
+    let segment = sqlx::query!("SELECT segment_id FROM segments")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    when_sending_an_email()
+        .and(matchers::body_partial_json(
+            serde_json::json!({"To": "ursula@example.com"}),
+        ))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Newsletter body as plain text",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            "segment_id": segment.segment_id.to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn publishing_to_an_unknown_segment_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Newsletter body as plain text",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            "segment_id": uuid::Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn segments_can_target_tags() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app, serde_json::json!({"email": "ursula@example.com"})).await;
+    create_confirmed_subscriber(
+        &app,
+        serde_json::json!({"email": "le_guin@example.com", "tags": "beta"}),
+    )
+    .await;
+
+    app.post_segments(serde_json::json!({
+        "name": "beta testers",
+        "definition": r#"{"tag": "beta"}"#,
+    }))
+    .await;
+
+    let html_page = app.get_newsletters_html().await;
+    assert!(html_page.contains("beta testers (1 recipients)"));
+}
+
+async fn create_confirmed_subscriber(app: &TestApp, mut fields: serde_json::Value) {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .named("create confirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    fields["name"] = "le guin".into();
+    let body = serde_urlencoded::to_string(fields).unwrap();
+    app.post_subscriptions(&body).await;
+
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(email_request);
+    app.client
+        .get(confirmation_links.html)
+        .send()
+        .await
+        .expect("failed to execute request");
+}
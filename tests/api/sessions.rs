@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use reqwest::{
+    StatusCode,
+    header::{COOKIE, SET_COOKIE},
+};
+use zero2prod::session_state::SameSite;
+
+use crate::helpers::{TestApp, assert_is_redirect_to, csrf_token_in, spawn_app, spawn_app_with};
+
+impl TestApp {
+    /// Log the test user in from another browser.
+    async fn login_elsewhere(&self, user_agent: &str) -> reqwest::Client {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .cookie_store(true)
+            .user_agent(user_agent)
+            .build()
+            .unwrap();
+        let response = client
+            .post(format!("{}/login", self.address()))
+            .form(&serde_json::json!({
+                "username": self.test_user.username,
+                "password": self.test_user.password,
+                "csrf_token": self.csrf_token_of(&client).await,
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_is_redirect_to(&response, "/admin/dashboard");
+        client
+    }
+
+    async fn get_sessions_html(&self) -> String {
+        self.client
+            .get(format!("{}/admin/security/sessions", self.address()))
+            .send()
+            .await
+            .expect("failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    async fn post_sessions(&self, path: &str) -> reqwest::Response {
+        self.client
+            .post(format!("{}/admin/security/sessions{path}", self.address()))
+            .form(&self.with_csrf_token(serde_json::json!({})).await)
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+}
+
+async fn is_logged_in(app: &TestApp, client: &reqwest::Client) -> bool {
+    let response = client
+        .get(format!("{}/admin/dashboard", app.address()))
+        .send()
+        .await
+        .unwrap();
+    response.status() == StatusCode::OK
+}
+
+/// The revoke links on the sessions page, one per other session.
+fn revoke_paths(html_page: &str) -> Vec<String> {
+    html_page
+        .split(r#"action="/admin/security/sessions"#)
+        .skip(1)
+        .filter_map(|rest| rest.split('"').next())
+        .filter(|path| path.ends_with("/revoke"))
+        .map(str::to_string)
+        .collect()
+}
+
+#[tokio::test]
+async fn the_sessions_of_a_user_are_listed() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    app.login_elsewhere("Other Browser/1.0").await;
+
+    let html_page = app.get_sessions_html().await;
+
+    assert!(html_page.contains("This session"));
+    assert!(html_page.contains("<td>127.0.0.1</td><td>Other Browser/1.0</td>"));
+    assert_eq!(revoke_paths(&html_page).len(), 1);
+}
+
+#[tokio::test]
+async fn a_session_can_be_revoked() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let phone = app.login_elsewhere("Phone").await;
+    let laptop = app.login_elsewhere("Laptop").await;
+
+    let html_page = app.get_sessions_html().await;
+    let phone_row = html_page
+        .split("<tr>")
+        .find(|row| row.contains("<td>Phone</td>"))
+        .unwrap();
+    let path = revoke_paths(phone_row).pop().unwrap();
+    let response = app.post_sessions(&path).await;
+    assert_is_redirect_to(&response, "/admin/security/sessions");
+
+    assert!(!is_logged_in(&app, &phone).await);
+    assert!(is_logged_in(&app, &laptop).await);
+    assert!(is_logged_in(&app, &app.client).await);
+
+    let response = app.post_sessions(&path).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn all_other_sessions_can_be_revoked() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let phone = app.login_elsewhere("Phone").await;
+    let laptop = app.login_elsewhere("Laptop").await;
+
+    let response = app.post_sessions("/revoke_others").await;
+    assert_is_redirect_to(&response, "/admin/security/sessions");
+
+    assert!(!is_logged_in(&app, &phone).await);
+    assert!(!is_logged_in(&app, &laptop).await);
+    assert!(is_logged_in(&app, &app.client).await);
+    assert!(revoke_paths(&app.get_sessions_html().await).is_empty());
+}
+
+#[tokio::test]
+async fn changing_the_password_revokes_the_other_sessions() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let phone = app.login_elsewhere("Phone").await;
+
+    let new_password = "a-long-and-new-password";
+    let response = app
+        .post_change_password(serde_json::json!({
+            "current_password": app.test_user.password,
+            "new_password": new_password,
+            "new_password_check": new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    assert!(!is_logged_in(&app, &phone).await);
+    assert!(is_logged_in(&app, &app.client).await);
+}
+
+#[tokio::test]
+async fn idle_sessions_are_signed_out_with_a_message() {
+    let app = spawn_app_with(|c| c.application.session.idle_timeout_seconds = 1).await;
+    app.login_test_user().await;
+    assert_eq!(app.get_admin_dashboard().await.status(), StatusCode::OK);
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Your session has expired, please log in again."));
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn sessions_end_after_their_lifetime() {
+    let app = spawn_app_with(|c| c.application.session.lifetime_seconds = 2).await;
+    app.login_test_user().await;
+    assert_eq!(app.get_admin_dashboard().await.status(), StatusCode::OK);
+
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn session_cookie_follows_the_configured_flags() {
+    let app = spawn_app_with(|c| {
+        c.application.session.secure_cookies = true;
+        c.application.session.same_site = SameSite::Lax;
+    })
+    .await;
+
+    // A secure cookie is not sent back over plain HTTP, so the session of the
+    // login form is carried by hand.
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let login_form = client
+        .get(format!("{}/login", app.address()))
+        .send()
+        .await
+        .unwrap();
+    let form_cookie = login_form
+        .headers()
+        .get(SET_COOKIE)
+        .expect("no session cookie")
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+    let csrf_token = csrf_token_in(&login_form.text().await.unwrap());
+    let response = client
+        .post(format!("{}/login", app.address()))
+        .header(COOKIE, form_cookie)
+        .form(&serde_json::json!({
+            "username": app.test_user.username,
+            "password": app.test_user.password,
+            "csrf_token": csrf_token,
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let cookie = response
+        .headers()
+        .get(SET_COOKIE)
+        .expect("no session cookie")
+        .to_str()
+        .unwrap();
+    assert!(cookie.contains("Secure"));
+    assert!(cookie.contains("SameSite=Lax"));
+    assert!(cookie.contains("Max-Age="));
+}
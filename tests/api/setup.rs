@@ -0,0 +1,116 @@
+use reqwest::StatusCode;
+use secrecy::ExposeSecret;
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+impl TestApp {
+    /// Back to the state of a fresh deployment.
+    async fn delete_all_users(&self) {
+        sqlx::query!("DELETE FROM users")
+            .execute(&self.pool)
+            .await
+            .unwrap();
+    }
+
+    async fn post_setup(&self, token: &str, username: &str) -> reqwest::Response {
+        self.client
+            .post(format!("{}/setup", self.address()))
+            .form(&serde_json::json!({
+                "token": token,
+                "username": username,
+                "email": "owner@example.com",
+                "password": "the-first-owner-password",
+                "password_check": "the-first-owner-password",
+                "csrf_token": self.csrf_token_of(&self.client).await,
+            }))
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    fn setup_secret(&self) -> String {
+        self.setup_token
+            .as_ref()
+            .expect("the app started without users")
+            .expose_secret()
+            .to_string()
+    }
+}
+
+#[tokio::test]
+async fn the_seeded_admin_is_gone() {
+    let app = spawn_app().await;
+
+    let seeded = sqlx::query!(r#"SELECT count(*) AS "count!" FROM users WHERE username = 'admin'"#)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    assert_eq!(seeded.count, 0);
+}
+
+#[tokio::test]
+async fn login_is_refused_until_the_first_owner_exists() {
+    let app = spawn_app().await;
+    app.delete_all_users().await;
+
+    let response = app
+        .post_login(serde_json::json!({
+            "username": "anyone",
+            "password": "anything",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("No account exists yet"));
+}
+
+#[tokio::test]
+async fn the_setup_token_creates_the_first_owner_once() {
+    let app = spawn_app().await;
+    app.delete_all_users().await;
+    let token = app.setup_secret();
+
+    let response = app
+        .client
+        .get(format!("{}/setup?token={token}", app.address()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post_setup(&token, "founder").await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_login(serde_json::json!({
+            "username": "founder",
+            "password": "the-first-owner-password",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains("You are signed in as owner."));
+
+    let response = app.post_setup(&token, "intruder").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn setup_requires_the_token_printed_at_startup() {
+    let app = spawn_app().await;
+    app.delete_all_users().await;
+
+    let response = app.post_setup("not-the-token", "intruder").await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn setup_is_closed_once_there_are_users() {
+    let app = spawn_app().await;
+
+    let response = app.post_setup(&app.setup_secret(), "intruder").await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,193 @@
+use axum::http::StatusCode;
+use reqwest::multipart::{Form, Part};
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{TestApp, spawn_app};
+
+fn when_sending_an_email() -> wiremock::MockBuilder {
+    Mock::given(matchers::path("/email")).and(matchers::method("POST"))
+}
+
+async fn create_subscriber(app: &TestApp) {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .named("Create pending subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com&tags=sci-fi")
+        .await
+        .error_for_status()
+        .unwrap();
+}
+
+async fn request_data_link(app: &TestApp) -> reqwest::Url {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .named("Send data request link")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let response = app.post_data_request("ursula_le_guin@gmail.com").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // One email confirmed the subscription, the link is in the second one.
+    let email_request = app.wait_for_emails(2).await.pop().unwrap();
+    app.get_confirmation_links(&email_request).plain_text
+}
+
+#[tokio::test]
+async fn a_data_request_for_an_unknown_address_sends_no_email() {
+    let app = spawn_app().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_data_request("nobody@example.com").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("If we hold data about this address")
+    );
+}
+
+#[tokio::test]
+async fn the_signed_link_exports_everything_held_about_the_subscriber() {
+    let app = spawn_app().await;
+    create_subscriber(&app).await;
+    let link = request_data_link(&app).await;
+
+    let html_page = reqwest::get(link.clone())
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("Erase my data"));
+
+    let mut export_link = link.clone();
+    export_link.set_path("/subscriptions/data/export");
+    let response = reqwest::get(export_link).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["email"], "ursula_le_guin@gmail.com");
+    assert_eq!(data["subscription"]["name"], "le guin");
+    assert_eq!(data["subscription"]["tags"], serde_json::json!(["sci-fi"]));
+    assert_eq!(data["subscription_tokens"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn a_tampered_link_is_rejected() {
+    let app = spawn_app().await;
+    create_subscriber(&app).await;
+    let link = request_data_link(&app).await;
+
+    let query = link
+        .query()
+        .unwrap()
+        .replace("ursula_le_guin%40gmail.com", "someone_else%40gmail.com");
+    let mut tampered_link = link.clone();
+    tampered_link.set_query(Some(&query));
+    tampered_link.set_path("/subscriptions/data/export");
+    let response = reqwest::get(tampered_link).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn erasure_deletes_the_subscriber_and_leaves_a_tombstone() {
+    let app = spawn_app().await;
+    create_subscriber(&app).await;
+    let link = request_data_link(&app).await;
+
+    let parameters: Vec<(String, String)> = link.query_pairs().into_owned().collect();
+    let response = app
+        .client
+        .post(format!("{}/subscriptions/data/erase", app.address()))
+        .form(&parameters)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let subscriptions = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert!(subscriptions.is_empty());
+    let tokens = sqlx::query!("SELECT subscriber_id FROM subscription_tokens")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert!(tokens.is_empty());
+    let tombstones = sqlx::query!("SELECT email_hash, email, reason FROM suppressions")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(tombstones.len(), 1);
+    assert!(tombstones[0].email.is_none());
+    assert_eq!(tombstones[0].reason, "erased");
+}
+
+#[tokio::test]
+async fn an_erased_address_cannot_be_imported_again() {
+    let app = spawn_app().await;
+    create_subscriber(&app).await;
+    app.login_test_user().await;
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_admin_subscriber_action(subscriber.id, "erase")
+        .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let csv = "email,name\nUrsula_Le_Guin@gmail.com,Ursula\n";
+    let form = |form: Form| {
+        form.text("mode", "confirmed")
+            .text("consent_source", "Old provider export")
+            .part(
+                "file",
+                Part::text(csv)
+                    .file_name("subscribers.csv")
+                    .mime_str("text/csv")
+                    .unwrap(),
+            )
+    };
+    let html_page = app
+        .post_subscribers_import(form)
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(html_page.contains("Imported 0 subscribers."));
+    assert!(html_page.contains("Skipped 1 suppressed addresses. 2"));
+}
+
+#[tokio::test]
+async fn admins_can_export_a_subscriber_data() {
+    let app = spawn_app().await;
+    create_subscriber(&app).await;
+    app.login_test_user().await;
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .get_admin_subscribers(&format!("/{}/data", subscriber.id))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["subscription"]["id"], subscriber.id.to_string());
+}
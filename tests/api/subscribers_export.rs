@@ -0,0 +1,111 @@
+use axum::http::{StatusCode, header::CONTENT_TYPE};
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn insert_subscriber(app: &TestApp, email: &str, status: &str, tags: &[&str]) {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, attributes)
+        VALUES ($1, $2, 'Ursula, "the" author', now(), $3, '{"company": "Acme"}')
+        "#,
+        id,
+        email,
+        status,
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    for tag in tags {
+        sqlx::query!(
+            "INSERT INTO subscriber_tags (subscriber_id, tag) VALUES ($1, $2)",
+            id,
+            tag
+        )
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_export_subscribers() {
+    let app = spawn_app().await;
+    let response = app.get_subscribers_export("format=csv").await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_exported_as_csv() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    insert_subscriber(&app, "ursula@example.com", "confirmed", &["beta", "vip"]).await;
+
+    let response = app.get_subscribers_export("format=csv").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(CONTENT_TYPE).unwrap(),
+        "text/csv; charset=utf-8"
+    );
+
+    let body = response.text().await.unwrap();
+    let lines: Vec<_> = body.lines().collect();
+    assert_eq!(
+        lines[0],
+        "id,email,name,status,subscribed_at,tags,attributes"
+    );
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains(r#"ursula@example.com,"Ursula, ""the"" author",confirmed,"#));
+    assert!(lines[1].contains(r#","beta,vip","{""company"":""Acme""}""#));
+}
+
+#[tokio::test]
+async fn formulas_are_not_exported_as_formulas() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    insert_subscriber(&app, "ursula@example.com", "confirmed", &[]).await;
+    sqlx::query!(r#"UPDATE subscriptions SET name = '=HYPERLINK("https://evil.example","Click")'"#)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let response = app.get_subscribers_export("format=csv").await;
+
+    let body = response.text().await.unwrap();
+    assert!(body.contains(
+        r#",ursula@example.com,"'=HYPERLINK(""https://evil.example"",""Click"")",confirmed,"#
+    ));
+}
+
+#[tokio::test]
+async fn subscribers_are_exported_as_json_filtered_by_status() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    insert_subscriber(&app, "ursula@example.com", "confirmed", &["beta"]).await;
+    insert_subscriber(&app, "le_guin@example.com", "pending_confirmation", &[]).await;
+
+    let response = app
+        .get_subscribers_export("format=json&status=pending_confirmation")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    let subscribers = body.as_array().unwrap();
+    assert_eq!(subscribers.len(), 1);
+    assert_eq!(subscribers[0]["email"], "le_guin@example.com");
+    assert_eq!(subscribers[0]["status"], "pending_confirmation");
+    assert_eq!(subscribers[0]["tags"], serde_json::json!([]));
+    assert_eq!(
+        subscribers[0]["attributes"],
+        serde_json::json!({"company": "Acme"})
+    );
+}
+
+#[tokio::test]
+async fn an_unknown_export_format_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let response = app.get_subscribers_export("format=xml").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,136 @@
+use axum::http::StatusCode;
+use reqwest::multipart::{Form, Part};
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+const CSV: &str = "email,name,company\n\
+    ursula@example.com,Ursula,Acme\n\
+    le_guin@example.com,Le Guin,\n\
+    ursula@example.com,Ursula again,\n\
+    not-an-email,Someone,\n";
+
+fn import_form(mode: &str, consent_source: &str, csv: &str) -> impl FnOnce(Form) -> Form {
+    let (mode, consent_source, csv) = (
+        mode.to_string(),
+        consent_source.to_string(),
+        csv.to_string(),
+    );
+    move |form| {
+        form.text("mode", mode)
+            .text("consent_source", consent_source)
+            .part(
+                "file",
+                Part::text(csv)
+                    .file_name("subscribers.csv")
+                    .mime_str("text/csv")
+                    .unwrap(),
+            )
+    }
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_import_subscribers() {
+    let app = spawn_app().await;
+    let response = app
+        .post_subscribers_import(import_form("confirmed", "test", CSV))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn imported_subscribers_can_be_marked_as_confirmed() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscribers_import(import_form("confirmed", "Old provider export", CSV))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Imported 2 subscribers."));
+    assert!(html_page.contains("Skipped 1 duplicates. 4"));
+    assert!(
+        html_page
+            .contains("<tr><td>5</td><td>not-an-email is not a valid subscriber email</td></tr>")
+    );
+
+    let saved = sqlx::query!(
+        "SELECT email, status, consent_source, attributes FROM subscriptions ORDER BY email"
+    )
+    .fetch_all(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[1].email, "ursula@example.com");
+    assert_eq!(saved[1].status, "confirmed");
+    assert_eq!(
+        saved[1].consent_source.as_deref(),
+        Some("Old provider export")
+    );
+    assert_eq!(saved[1].attributes, serde_json::json!({"company": "Acme"}));
+}
+
+#[tokio::test]
+async fn imported_subscribers_can_be_sent_a_confirmation_email() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscribers_import(import_form("send_confirmation", "", CSV))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 2);
+    assert!(saved.iter().all(|r| r.status == "pending_confirmation"));
+}
+
+#[tokio::test]
+async fn confirmed_imports_require_a_consent_source() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_subscribers_import(import_form("confirmed", " ", CSV))
+        .await;
+    assert_is_redirect_to(&response, "/admin/subscribers/import");
+
+    let html_page = app.get_subscribers_import_html().await;
+    assert!(
+        html_page.contains(
+            "<p><i>A consent source is required to import confirmed subscribers.</i></p>"
+        )
+    );
+}
+
+#[tokio::test]
+async fn a_csv_without_the_required_columns_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_subscribers_import(import_form(
+            "confirmed",
+            "test",
+            "email\nursula@example.com\n",
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -1,8 +1,9 @@
 use axum::http::StatusCode;
 use rstest::rstest;
 use wiremock::{Mock, ResponseTemplate, matchers};
+use zero2prod::anti_bot::FormToken;
 
-use crate::helpers::spawn_app;
+use crate::helpers::{TEST_CHALLENGE_RESPONSE, spawn_app};
 
 #[tokio::test]
 async fn subscribe_return_a_200_for_valid_form_data() {
@@ -112,3 +113,314 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
         .await;
     assert_eq!(response.status(), 500);
 }
+
+#[tokio::test]
+async fn subscribe_persists_custom_attributes_and_tags() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com\
+            &attributes%5Bcompany%5D=Acme&attributes%5Breferral_source%5D=partner\
+            &tags=Beta%2Cearly-access",
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let saved = sqlx::query!(r#"SELECT attributes FROM subscriptions"#)
+        .fetch_one(&app.pool)
+        .await
+        .expect("failed to fetch saved subscriptions");
+    assert_eq!(
+        saved.attributes,
+        serde_json::json!({"company": "Acme", "referral_source": "partner"})
+    );
+
+    let tags = sqlx::query!("SELECT tag FROM subscriber_tags ORDER BY tag")
+        .fetch_all(&app.pool)
+        .await
+        .expect("failed to fetch saved tags");
+    let tags: Vec<_> = tags.into_iter().map(|r| r.tag).collect();
+    assert_eq!(tags, vec!["beta", "early-access"]);
+}
+
+#[rstest]
+#[case::attribute_too_long(
+    "name=le%20guin&email=ursula_le_guin%40gmail.com&attributes%5Breferral_source%5D=\
+    aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+)]
+#[case::invalid_attribute_name(
+    "name=le%20guin&email=ursula_le_guin%40gmail.com&attributes%5Bfirst-name%5D=Ursula"
+)]
+#[case::invalid_tag("name=le%20guin&email=ursula_le_guin%40gmail.com&tags=not%20a%20tag")]
+#[tokio::test]
+async fn subscribe_return_a_422_when_attributes_or_tags_are_invalid(
+    #[case] invalid_body: &'static str,
+) {
+    let app = spawn_app().await;
+    let response = app.post_subscriptions(invalid_body).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn subscribe_normalizes_the_email_and_ignores_case_variants() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        // Still pending, the second signup is sent a new link.
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions("name=le%20guin&email=%20Ursula_Le_Guin%40GMail.com%20")
+        .await
+        .error_for_status()
+        .unwrap();
+    // The answer does not tell the address was already subscribed.
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].email, "Ursula_Le_Guin@gmail.com");
+}
+
+#[tokio::test]
+async fn a_pending_subscriber_signing_up_again_gets_a_new_confirmation_link() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..2 {
+        app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(&email_request).html;
+    reqwest::get(confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn a_confirmed_subscriber_signing_up_again_is_sent_nothing() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await
+        .error_for_status()
+        .unwrap();
+    sqlx::query!("UPDATE subscriptions SET status = 'confirmed'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn subscribe_return_a_422_for_disposable_addresses() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula%40mailinator.com")
+        .await;
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("Addresses at mailinator.com are disposable")
+    );
+}
+
+#[tokio::test]
+async fn subscribe_flags_role_addresses() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions("name=le%20guin&email=postmaster%40example.com")
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let saved = sqlx::query!("SELECT tag FROM subscriber_tags")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.tag, "role-address");
+}
+
+#[tokio::test]
+async fn subscribe_ignores_submissions_with_the_honeypot_filled_in() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&website=http%3A%2F%2Fspam.example",
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let saved = sqlx::query!("SELECT count(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 0);
+}
+
+#[rstest]
+#[case::missing_token("name=le%20guin&email=ursula_le_guin%40gmail.com&challenge_response=human")]
+#[case::forged_token(
+    "name=le%20guin&email=ursula_le_guin%40gmail.com&challenge_response=human&form_token=0.abcd"
+)]
+#[tokio::test]
+async fn subscribe_return_a_400_without_a_valid_form_token(#[case] body: &'static str) {
+    let app = spawn_app().await;
+    let response = app.post_subscriptions_raw(body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn subscribe_return_a_400_when_the_challenge_fails() {
+    let app = spawn_app().await;
+    let form_token = FormToken::issue(&app.hmac_secret);
+    let response = app
+        .post_subscriptions_raw(&format!(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&challenge_response=robot\
+            &form_token={}",
+            form_token.as_ref()
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn a_form_cannot_be_submitted_twice() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let body = format!(
+        "name=le%20guin&email=ursula_le_guin%40gmail.com&{}",
+        app.anti_bot_fields()
+    );
+
+    let response = app.post_subscriptions_raw(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post_subscriptions_raw(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn a_form_refused_as_invalid_can_be_corrected_and_submitted() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let anti_bot_fields = app.anti_bot_fields();
+
+    let response = app
+        .post_subscriptions_raw(&format!(
+            "name=le%20guin&email=ursula_le_guin%40&{anti_bot_fields}"
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = app
+        .post_subscriptions_raw(&format!(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&{anti_bot_fields}"
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn the_home_page_form_can_be_submitted() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let html_page = app
+        .client
+        .get(app.address())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let form_token = html_page
+        .split(r#"name="form_token" value=""#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .expect("a form token");
+
+    let response = app
+        .post_subscriptions_raw(&format!(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&form_token={form_token}\
+            &challenge_response={TEST_CHALLENGE_RESPONSE}"
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -1,7 +1,9 @@
 use reqwest::StatusCode;
 use wiremock::{Mock, ResponseTemplate, matchers};
 
-use crate::helpers::spawn_app;
+use zero2prod::anti_bot::FormToken;
+
+use crate::helpers::{TEST_CHALLENGE_RESPONSE, spawn_app};
 
 #[tokio::test]
 async fn confirmations_without_token_are_rejected_with_a_400() {
@@ -70,3 +72,50 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     assert_eq!(saved.name, "le guin");
     assert_eq!(saved.status, "confirmed");
 }
+
+#[tokio::test]
+async fn signup_and_confirmation_are_recorded_in_the_consent_trail() {
+    let app = spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    app.client
+        .post(format!("{}/subscriptions", app.address()))
+        .header(reqwest::header::USER_AGENT, "integration-test")
+        .form(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "source": "footer_form",
+            "form_token": FormToken::issue(&app.hmac_secret).as_ref(),
+            "challenge_response": TEST_CHALLENGE_RESPONSE,
+        }))
+        .send()
+        .await
+        .expect("failed to execute request");
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    for _ in 0..2 {
+        app.client
+            .get(confirmation_links.html.clone())
+            .send()
+            .await
+            .expect("failed to execute request");
+    }
+
+    let events = sqlx::query!(
+        "SELECT event_type, source, source_ip, user_agent FROM subscription_events ORDER BY id"
+    )
+    .fetch_all(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].event_type, "signup");
+    assert_eq!(events[0].source.as_deref(), Some("footer_form"));
+    assert_eq!(events[0].source_ip.as_deref(), Some("127.0.0.1"));
+    assert_eq!(events[0].user_agent.as_deref(), Some("integration-test"));
+    assert_eq!(events[1].event_type, "confirmation");
+}
@@ -0,0 +1,192 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+use wiremock::{Mock, ResponseTemplate, matchers};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+fn when_sending_an_email() -> wiremock::MockBuilder {
+    Mock::given(matchers::path("/email")).and(matchers::method("POST"))
+}
+
+async fn post_suppression(app: &TestApp, email: &str) -> reqwest::Response {
+    app.client
+        .post(format!("{}/admin/suppressions", app.address()))
+        .form(
+            &app.with_csrf_token(serde_json::json!({ "email": email, "detail": "Asked by phone" }))
+                .await,
+        )
+        .send()
+        .await
+        .expect("failed to execute request")
+}
+
+async fn get_suppressions_html(app: &TestApp) -> String {
+    app.client
+        .get(format!("{}/admin/suppressions", app.address()))
+        .send()
+        .await
+        .expect("failed to execute request")
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_suppressions() {
+    let app = spawn_app().await;
+    let response = post_suppression(&app, "ursula@example.com").await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn markup_echoed_in_a_flash_message_is_escaped() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = post_suppression(&app, "<img src=x onerror=alert(1)>").await;
+    assert_is_redirect_to(&response, "/admin/suppressions");
+
+    let html_page = get_suppressions_html(&app).await;
+    assert!(!html_page.contains("<img"));
+    assert!(
+        html_page.contains("&lt;img src=x onerror=alert(1)&gt; is not a valid subscriber email")
+    );
+}
+
+#[tokio::test]
+async fn a_suppressed_address_cannot_subscribe_again() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let response = post_suppression(&app, "ursula_le_guin@gmail.com").await;
+    assert_is_redirect_to(&response, "/admin/suppressions");
+    let html_page = get_suppressions_html(&app).await;
+    assert!(html_page.contains("ursula_le_guin@gmail.com"));
+    assert!(html_page.contains("Asked by phone"));
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_subscriptions("name=le%20guin&email=Ursula_Le_Guin%40gmail.com")
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let saved = sqlx::query!("SELECT count(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 0);
+}
+
+#[tokio::test]
+async fn suppressed_subscribers_are_not_sent_newsletters() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, 'ursula@example.com', 'Ursula', $2, 'confirmed')
+        "#,
+        Uuid::new_v4(),
+        Utc::now(),
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    post_suppression(&app, "ursula@example.com").await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn a_complaint_keeps_the_address_suppressed_after_the_subscription_is_deleted() {
+    let app = spawn_app().await;
+    let subscriber_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, 'ursula_le_guin@gmail.com', 'Ursula', $2, 'confirmed')
+        "#,
+        subscriber_id,
+        Utc::now(),
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    app.post_email_webhook(
+        "postmark",
+        &app.webhook_token,
+        serde_json::json!({
+            "RecordType": "SpamComplaint",
+            "Type": "SpamComplaint",
+            "Email": "ursula_le_guin@gmail.com",
+        }),
+    )
+    .await;
+    app.login_test_user().await;
+    app.post_admin_subscriber_action(subscriber_id, "delete")
+        .await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+
+    let saved = sqlx::query!("SELECT count(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 0);
+}
+
+#[tokio::test]
+async fn a_removed_suppression_lets_the_address_subscribe_again() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    post_suppression(&app, "ursula_le_guin@gmail.com").await;
+    let suppression = sqlx::query!("SELECT email_hash FROM suppressions")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .client
+        .post(format!(
+            "{}/admin/suppressions/{}/delete",
+            app.address(),
+            suppression.email_hash
+        ))
+        .form(&app.with_csrf_token(serde_json::json!({})).await)
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/suppressions");
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await
+        .error_for_status()
+        .unwrap();
+}
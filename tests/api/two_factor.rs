@@ -0,0 +1,180 @@
+use secrecy::ExposeSecret;
+use zero2prod::authentication::Totp;
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+impl TestApp {
+    async fn get_security_html(&self) -> String {
+        self.client
+            .get(format!("{}/admin/security", self.address()))
+            .send()
+            .await
+            .expect("failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    async fn post_security(&self, action: &str, code: &str) -> reqwest::Response {
+        self.client
+            .post(format!("{}/admin/security/{action}", self.address()))
+            .form(
+                &self
+                    .with_csrf_token(serde_json::json!({ "code": code }))
+                    .await,
+            )
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    async fn post_two_factor(&self, code: &str) -> reqwest::Response {
+        self.client
+            .post(format!("{}/login/two_factor", self.address()))
+            .form(
+                &self
+                    .with_csrf_token(serde_json::json!({ "code": code }))
+                    .await,
+            )
+            .send()
+            .await
+            .expect("failed to execute request")
+    }
+
+    /// Enroll the logged in test user, returns their TOTP and recovery codes.
+    async fn enroll_two_factor(&self) -> (Totp, Vec<String>) {
+        let html_page = self.get_security_html().await;
+        let secret = html_page
+            .split(r#"<code id="totp-secret">"#)
+            .nth(1)
+            .and_then(|s| s.split('<').next())
+            .expect("a TOTP secret")
+            .to_string();
+        let totp = Totp::from_secret(secret.into());
+
+        let code = totp.code_at(Totp::current_step()).unwrap();
+        let html_page = self
+            .post_security("totp", &code)
+            .await
+            .text()
+            .await
+            .unwrap();
+        let recovery_codes = html_page
+            .split("<li><code>")
+            .skip(1)
+            .map(|s| s.split('<').next().unwrap().to_string())
+            .collect();
+        (totp, recovery_codes)
+    }
+}
+
+#[tokio::test]
+async fn an_enrolled_user_needs_a_code_to_log_in() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (totp, recovery_codes) = app.enroll_two_factor().await;
+    assert_eq!(recovery_codes.len(), 10);
+    app.post_logout().await;
+
+    let response = app.login_test_user().await;
+    assert_is_redirect_to(&response, "/login/two_factor");
+    // The password alone does not open the admin area.
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // The enrollment used the current step, the next one is still accepted.
+    let code = totp.code_at(Totp::current_step() + 1).unwrap();
+    let response = app.post_two_factor(&code).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn the_totp_secret_is_stored_encrypted() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (totp, _) = app.enroll_two_factor().await;
+    let secret = totp.secret().expose_secret().to_string();
+    let stored = || async {
+        sqlx::query!(
+            "SELECT totp_secret AS \"totp_secret!\" FROM users WHERE user_id = $1",
+            app.test_user.user_id
+        )
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+        .totp_secret
+    };
+    assert!(!stored().await.contains(&secret));
+    app.post_logout().await;
+
+    // A known secret written in clear into the database is refused.
+    sqlx::query!(
+        "UPDATE users SET totp_secret = $2 WHERE user_id = $1",
+        app.test_user.user_id,
+        secret,
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    app.login_test_user().await;
+    let code = totp.code_at(Totp::current_step() + 1).unwrap();
+    let response = app.post_two_factor(&code).await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_wrong_code_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    app.enroll_two_factor().await;
+    app.post_logout().await;
+
+    app.login_test_user().await;
+    let response = app.post_two_factor("000000").await;
+    assert_is_redirect_to(&response, "/login/two_factor");
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_recovery_code_works_only_once() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (_, recovery_codes) = app.enroll_two_factor().await;
+    app.post_logout().await;
+
+    app.login_test_user().await;
+    let response = app.post_two_factor(&recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    app.post_logout().await;
+
+    app.login_test_user().await;
+    let response = app.post_two_factor(&recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/login/two_factor");
+
+    let saved = sqlx::query!(
+        "SELECT count(*) AS \"count!\" FROM recovery_codes WHERE used_at IS NULL AND code_hash LIKE '$argon2id$%'"
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.count, 9);
+}
+
+#[tokio::test]
+async fn two_factor_authentication_can_be_disabled() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (_, recovery_codes) = app.enroll_two_factor().await;
+
+    let response = app.post_security("totp/disable", &recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/admin/security");
+    let html_page = app.get_security_html().await;
+    assert!(html_page.contains("Two-factor authentication has been disabled."));
+    app.post_logout().await;
+
+    let response = app.login_test_user().await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}